@@ -0,0 +1,150 @@
+use serde::Serialize;
+use std::fs;
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tauri_plugin_shell::ShellExt;
+
+const SIDECAR: &str = "aeat-bridge";
+pub const OUTPUT_EVENT: &str = "aeat-sidecar-output";
+pub const TERMINATED_EVENT: &str = "aeat-sidecar-terminated";
+// The certificate is removed once the bridge listens, or after this long
+const LOAD_TIMEOUT: Duration = Duration::from_secs(30);
+const LOAD_POLL: Duration = Duration::from_millis(250);
+
+/// PKCS#12 file and password the bridge signs with. The password only
+/// travels in the bridge's environment, never back to the webview.
+pub struct SigningCertificate {
+    pub path: PathBuf,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SidecarOutput {
+    stderr: bool,
+    line: String,
+}
+
+/// Managed state holding the running AEAT bridge process.
+#[derive(Default)]
+pub struct AeatSidecar {
+    child: Mutex<Option<CommandChild>>,
+}
+
+impl AeatSidecar {
+    pub fn pid(&self) -> Result<Option<u32>, String> {
+        let child = self.child.lock().map_err(|e| e.to_string())?;
+        Ok(child.as_ref().map(|child| child.pid()))
+    }
+
+    /// Spawns the bridge on `port` and returns its pid. Output and exit are
+    /// forwarded to the webview as `aeat-sidecar-output` and
+    /// `aeat-sidecar-terminated` events.
+    pub fn start(&self, app: &AppHandle, port: u16, certificate: Option<SigningCertificate>) -> Result<u32, String> {
+        let mut slot = self.child.lock().map_err(|e| e.to_string())?;
+        if let Some(child) = slot.as_ref() {
+            return Ok(child.pid());
+        }
+
+        let certificate_path = certificate.as_ref().map(|certificate| certificate.path.clone());
+        let spawned = app
+            .shell()
+            .sidecar(SIDECAR)
+            .map(|command| {
+                let command = command.args(["--port", &port.to_string()]);
+                match &certificate {
+                    Some(certificate) => command
+                        .env("PFX_PATH", &certificate.path)
+                        .env("PFX_PASSWORD", &certificate.password),
+                    None => command,
+                }
+            })
+            .and_then(|command| command.spawn());
+        let (mut events, child) = match spawned {
+            Ok(spawned) => spawned,
+            Err(e) => {
+                if let Some(path) = &certificate_path {
+                    remove_certificate(path);
+                }
+                return Err(format!("Failed to start the AEAT bridge: {}", e));
+            }
+        };
+
+        let pid = child.pid();
+        *slot = Some(child);
+
+        if let Some(path) = certificate_path.clone() {
+            std::thread::spawn(move || {
+                wait_until_listening(port);
+                remove_certificate(&path);
+            });
+        }
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            while let Some(event) = events.recv().await {
+                match event {
+                    CommandEvent::Stdout(line) | CommandEvent::Stderr(line) if line.is_empty() => {}
+                    CommandEvent::Stdout(line) => emit_output(&app, false, &line),
+                    CommandEvent::Stderr(line) => emit_output(&app, true, &line),
+                    CommandEvent::Terminated(payload) => {
+                        if let Some(path) = &certificate_path {
+                            remove_certificate(path);
+                        }
+                        app.state::<AeatSidecar>().forget(pid);
+                        let _ = app.emit(TERMINATED_EVENT, payload.code);
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        Ok(pid)
+    }
+
+    pub fn stop(&self) -> Result<(), String> {
+        let child = self.child.lock().map_err(|e| e.to_string())?.take();
+        match child {
+            Some(child) => child.kill().map_err(|e| format!("Failed to stop the AEAT bridge: {}", e)),
+            None => Ok(()),
+        }
+    }
+
+    // Clears the slot unless it already holds a newer process
+    fn forget(&self, pid: u32) {
+        if let Ok(mut child) = self.child.lock() {
+            if child.as_ref().is_some_and(|child| child.pid() == pid) {
+                *child = None;
+            }
+        }
+    }
+}
+
+fn emit_output(app: &AppHandle, stderr: bool, line: &[u8]) {
+    let line = String::from_utf8_lossy(line).trim_end().to_string();
+    let _ = app.emit(OUTPUT_EVENT, SidecarOutput { stderr, line });
+}
+
+// The bridge reads the certificate before it opens its port
+fn wait_until_listening(port: u16) {
+    let address = SocketAddr::from(([127, 0, 0, 1], port));
+    let deadline = Instant::now() + LOAD_TIMEOUT;
+    while Instant::now() < deadline {
+        if TcpStream::connect_timeout(&address, LOAD_POLL).is_ok() {
+            return;
+        }
+        std::thread::sleep(LOAD_POLL);
+    }
+}
+
+fn remove_certificate(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            eprintln!("Failed to remove signing certificate {}: {}", path.display(), e);
+        }
+    }
+}
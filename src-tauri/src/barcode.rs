@@ -0,0 +1,258 @@
+//! Product barcodes: GS1 check digits and in-store codes (prefix 2) that
+//! carry the price or the weight of what was weighed at the counter.
+
+use crate::models::{EmbeddedBarcodeLayout, Product, SaleUnit};
+use crate::pricing::{format_quantity, round_money, round_quantity};
+
+/// Price or weight read from an in-store barcode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Embedded {
+    /// Amount to charge, in euros
+    Price(f64),
+    /// Kilograms or litres
+    Weight(f64),
+}
+
+/// Hand-typed codes sometimes come with spaces between digit groups.
+pub fn normalize(code: &str) -> String {
+    code.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+fn is_digits(code: &str) -> bool {
+    !code.is_empty() && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// EAN-8, UPC-A, EAN-13 or GTIN-14 with a valid check digit.
+pub fn is_valid_gtin(code: &str) -> bool {
+    if !matches!(code.len(), 8 | 12 | 13 | 14) || !is_digits(code) {
+        return false;
+    }
+    let digits: Vec<u32> = code.bytes().map(|b| (b - b'0') as u32).collect();
+    let (body, check) = digits.split_at(digits.len() - 1);
+    // Weights alternate 3, 1 starting from the digit next to the check digit
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+    (10 - sum % 10) % 10 == check[0]
+}
+
+/// Normalized code, or why it can't be assigned to a product. Numeric codes
+/// of a GS1 length must have a valid check digit; 7-digit codes starting
+/// with 2 are the item part of in-store price or weight codes; anything else
+/// is taken as an internal code.
+pub fn validate(code: &str) -> Result<String, String> {
+    let code = normalize(code);
+    if code.is_empty() {
+        return Err("El código de barras está vacío".to_string());
+    }
+    if is_digits(&code) && matches!(code.len(), 8 | 12 | 13 | 14) && !is_valid_gtin(&code) {
+        return Err(format!("El dígito de control de {} no es correcto", code));
+    }
+    if is_digits(&code) && code.len() == 7 && !code.starts_with('2') {
+        return Err(format!("{}: los códigos de 7 cifras son de uso interno y empiezan por 2", code));
+    }
+    Ok(code)
+}
+
+/// Forms under which a scanned code may have been registered: UPC-A codes
+/// are EAN-13 codes with a leading zero and scanners report either.
+pub fn lookup_candidates(code: &str) -> Vec<String> {
+    let mut candidates = vec![code.to_string()];
+    if is_digits(code) && code.len() == 12 {
+        candidates.push(format!("0{}", code));
+    }
+    if let Some(upc) = code.strip_prefix('0').filter(|upc| is_digits(upc) && upc.len() == 12) {
+        candidates.push(upc.to_string());
+    }
+    candidates
+}
+
+/// Checks a layout before it is saved.
+pub fn validate_layout(layout: &EmbeddedBarcodeLayout) -> Result<(), String> {
+    for types in [&layout.price_types, &layout.weight_types] {
+        if !types.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(format!("Tipos no válidos: {}", types));
+        }
+    }
+    if let Some(digit) = layout.price_types.chars().find(|digit| layout.weight_types.contains(*digit)) {
+        return Err(format!("El tipo {} no puede indicar precio y peso a la vez", digit));
+    }
+    if !matches!(layout.item_digits, 4 | 5) {
+        return Err("El código de artículo debe tener 4 o 5 cifras".to_string());
+    }
+    Ok(())
+}
+
+// Weighted digits of the GS1 price check digit: "2-", "3", "5+" and "5-"
+const WEIGHT_2_MINUS: [u8; 10] = [0, 2, 4, 6, 8, 9, 1, 3, 5, 7];
+const WEIGHT_3: [u8; 10] = [0, 3, 6, 9, 2, 5, 8, 1, 4, 7];
+const WEIGHT_5_PLUS: [u8; 10] = [0, 5, 1, 6, 2, 7, 3, 8, 4, 9];
+const WEIGHT_5_MINUS: [u8; 10] = [0, 5, 9, 4, 8, 3, 7, 2, 6, 1];
+
+/// GS1 check digit of a 4 or 5-digit price field.
+pub fn price_check_digit(value: &str) -> Option<u8> {
+    if !is_digits(value) {
+        return None;
+    }
+    let digits: Vec<usize> = value.bytes().map(|b| (b - b'0') as usize).collect();
+    match digits.as_slice() {
+        [a, b, c, d] => {
+            let sum = WEIGHT_2_MINUS[*a] + WEIGHT_2_MINUS[*b] + WEIGHT_3[*c] + WEIGHT_5_MINUS[*d];
+            Some(sum * 3 % 10)
+        }
+        [a, b, c, d, e] => {
+            let sum = WEIGHT_5_PLUS[*a] + WEIGHT_2_MINUS[*b] + WEIGHT_5_MINUS[*c] + WEIGHT_5_PLUS[*d] + WEIGHT_2_MINUS[*e];
+            let target = (10 - sum % 10) % 10;
+            WEIGHT_5_MINUS.iter().position(|weighted| *weighted == target).map(|digit| digit as u8)
+        }
+        _ => None,
+    }
+}
+
+/// Item code and value of an in-store EAN-13 laid out as `layout` says.
+/// Prices are in cents and weights in grams (or millilitres); codes with a
+/// type digit the layout doesn't use or a wrong price check digit are not
+/// in-store codes.
+pub fn embedded(code: &str, layout: &EmbeddedBarcodeLayout) -> Option<(String, Embedded)> {
+    if !code.starts_with('2') || code.len() != 13 || !is_valid_gtin(code) {
+        return None;
+    }
+    let item_end = 2 + layout.item_digits;
+    let value = code.get(item_end + layout.price_check_digit as usize..12)?;
+    if layout.price_check_digit && price_check_digit(value)? != code.as_bytes()[item_end] - b'0' {
+        return None;
+    }
+    let amount: f64 = value.parse().ok()?;
+    let type_digit = code.chars().nth(1)?;
+    let embedded = if layout.price_types.contains(type_digit) {
+        Embedded::Price(amount / 100.0)
+    } else if layout.weight_types.contains(type_digit) {
+        Embedded::Weight(amount / 1000.0)
+    } else {
+        return None;
+    };
+    Some((code[..item_end].to_string(), embedded))
+}
+
+/// (quantity, unit price) of the line for an in-store code. A price on a
+/// product sold by weight becomes the weight it pays for; on a product sold
+/// by the unit it replaces the unit price.
+pub fn embedded_line(product: &Product, embedded: Embedded) -> Result<(f64, f64), String> {
+    match (embedded, product.sale_unit) {
+        (Embedded::Weight(weight), SaleUnit::Unit) => Err(format!(
+            "{} se vende por unidades y la etiqueta indica {}",
+            product.name,
+            format_quantity(weight, SaleUnit::Kg)
+        )),
+        (Embedded::Weight(weight), unit) => Ok((round_quantity(weight, unit), product.price)),
+        (Embedded::Price(amount), SaleUnit::Unit) => Ok((1.0, round_money(amount))),
+        (Embedded::Price(_), _) if product.price <= 0.0 => {
+            Err(format!("{} no tiene precio por {}", product.name, product.sale_unit.as_str()))
+        }
+        (Embedded::Price(amount), unit) => Ok((round_quantity(amount / product.price, unit), product.price)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Completes `body` with its EAN check digit
+    fn with_check_digit(body: &str) -> String {
+        (0..10).map(|digit| format!("{}{}", body, digit)).find(|code| is_valid_gtin(code)).unwrap()
+    }
+
+    fn product(price: f64, sale_unit: &str) -> Product {
+        serde_json::from_value(json!({
+            "id": 1, "name": "Queso", "price": price, "category": "Charcutería", "brand": "",
+            "iconType": "", "selectedIcon": "", "uploadedImage": null, "saleUnit": sale_unit,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn gtin_check_digits() {
+        assert!(is_valid_gtin("4006381333931"));
+        assert!(!is_valid_gtin("4006381333932"));
+        assert!(is_valid_gtin("036000291452"));
+        assert!(is_valid_gtin("96385074"));
+        assert!(!is_valid_gtin("40063813339a1"));
+    }
+
+    #[test]
+    fn validate_normalizes_and_rejects_bad_codes() {
+        assert_eq!(validate("4006 3813 3393 1").unwrap(), "4006381333931");
+        assert!(validate("4006381333932").unwrap_err().contains("dígito de control"));
+        assert!(validate("1234567").is_err());
+        assert_eq!(validate("2100123").unwrap(), "2100123");
+        assert_eq!(validate("INT-42").unwrap(), "INT-42");
+        assert!(validate("  ").is_err());
+    }
+
+    #[test]
+    fn upc_a_and_ean_13_forms_match() {
+        assert_eq!(lookup_candidates("036000291452"), ["036000291452", "0036000291452"]);
+        assert_eq!(lookup_candidates("0036000291452"), ["0036000291452", "036000291452"]);
+        assert_eq!(lookup_candidates("4006381333931"), ["4006381333931"]);
+    }
+
+    #[test]
+    fn price_check_digits() {
+        assert_eq!(price_check_digit("2875"), Some(9));
+        assert_eq!(price_check_digit("14685"), Some(6));
+        assert_eq!(price_check_digit("123"), None);
+    }
+
+    #[test]
+    fn embedded_price_and_weight() {
+        let layout = EmbeddedBarcodeLayout::default();
+        assert_eq!(
+            embedded(&with_check_digit("210012301234"), &layout),
+            Some(("2100123".to_string(), Embedded::Price(12.34)))
+        );
+        assert_eq!(
+            embedded(&with_check_digit("260012300250"), &layout),
+            Some(("2600123".to_string(), Embedded::Weight(0.25)))
+        );
+        assert_eq!(embedded("4006381333931", &layout), None);
+    }
+
+    #[test]
+    fn embedded_with_price_check_digit() {
+        let layout = EmbeddedBarcodeLayout {
+            price_types: "2".to_string(),
+            weight_types: "8".to_string(),
+            item_digits: 5,
+            price_check_digit: true,
+        };
+        assert_eq!(
+            embedded(&with_check_digit("222222292875"), &layout),
+            Some(("2222222".to_string(), Embedded::Price(28.75)))
+        );
+        // Wrong price check digit, and a type digit the layout doesn't use
+        assert_eq!(embedded(&with_check_digit("222222212875"), &layout), None);
+        assert_eq!(embedded(&with_check_digit("232222292875"), &layout), None);
+    }
+
+    #[test]
+    fn layouts_are_checked() {
+        assert!(validate_layout(&EmbeddedBarcodeLayout::default()).is_ok());
+        let overlapping = EmbeddedBarcodeLayout { weight_types: "4".to_string(), ..Default::default() };
+        assert!(validate_layout(&overlapping).is_err());
+        let long_items = EmbeddedBarcodeLayout { item_digits: 6, ..Default::default() };
+        assert!(validate_layout(&long_items).is_err());
+    }
+
+    #[test]
+    fn embedded_lines() {
+        assert_eq!(embedded_line(&product(20.0, "kg"), Embedded::Price(5.0)), Ok((0.25, 20.0)));
+        assert_eq!(embedded_line(&product(20.0, "kg"), Embedded::Weight(0.3)), Ok((0.3, 20.0)));
+        assert_eq!(embedded_line(&product(3.0, "unit"), Embedded::Price(2.5)), Ok((1.0, 2.5)));
+        assert!(embedded_line(&product(3.0, "unit"), Embedded::Weight(0.3)).is_err());
+        assert!(embedded_line(&product(0.0, "kg"), Embedded::Price(5.0)).is_err());
+    }
+}
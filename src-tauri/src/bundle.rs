@@ -0,0 +1,216 @@
+use crate::models::bundle::{BundleChoice, BundleComponent, BundleSlot};
+use crate::models::{OrderItem, Product};
+
+pub fn allows(slot: &BundleSlot, product: &Product) -> bool {
+    slot.product_ids.contains(&product.id) || slot.categories.contains(&product.category)
+}
+
+/// Checks a bundle's slots before they are saved.
+pub fn validate_slots(slots: &[BundleSlot]) -> Result<(), String> {
+    if slots.is_empty() {
+        return Err("El menú necesita al menos un plato".to_string());
+    }
+    for slot in slots {
+        if slot.name.trim().is_empty() {
+            return Err("Hay un plato del menú sin nombre".to_string());
+        }
+        if slot.categories.is_empty() && slot.product_ids.is_empty() {
+            return Err(format!("{}: indica las categorías o productos que admite", slot.name));
+        }
+    }
+    Ok(())
+}
+
+/// Components of a bundle from the products chosen for its slots, with the
+/// bundle price allocated among them.
+pub fn build(
+    slots: &[BundleSlot],
+    choices: &[BundleChoice],
+    products: &[Product],
+    bundle_price: f64,
+) -> Result<Vec<BundleComponent>, String> {
+    if let Some(choice) = choices.iter().find(|choice| !slots.iter().any(|slot| slot.id == choice.slot_id)) {
+        return Err(format!("El plato {} no pertenece al menú", choice.slot_id));
+    }
+
+    let mut components = Vec::new();
+    for slot in slots {
+        let chosen: Vec<&BundleChoice> = choices.iter().filter(|choice| choice.slot_id == slot.id).collect();
+        let choice = match chosen.as_slice() {
+            [] if slot.required => return Err(format!("Elige {}", slot.name)),
+            [] => continue,
+            [choice] => choice,
+            _ => return Err(format!("Solo se puede elegir un producto para {}", slot.name)),
+        };
+        let product = products
+            .iter()
+            .find(|product| product.id == choice.product_id)
+            .ok_or_else(|| format!("Producto {} no encontrado", choice.product_id))?;
+        if !allows(slot, product) {
+            return Err(format!("{} no se puede elegir como {}", product.name, slot.name));
+        }
+
+        components.push(BundleComponent {
+            slot_id: Some(slot.id),
+            slot_name: slot.name.clone(),
+            product_id: product.id,
+            name: product.name.clone(),
+            category: Some(product.category.clone()),
+            list_price: product.price,
+            allocated_price: 0.0,
+            tax_rate: product.tax_rate,
+        });
+    }
+
+    allocate(bundle_price, &mut components);
+    Ok(components)
+}
+
+/// Splits `price` among the components in proportion to their list prices
+/// (evenly when none has one), rounded to cents. The last component takes
+/// the rounding difference so the shares add up to the price.
+pub fn allocate(price: f64, components: &mut [BundleComponent]) {
+    let Some(last) = components.len().checked_sub(1) else {
+        return;
+    };
+    let list_total: f64 = components.iter().map(|component| component.list_price.max(0.0)).sum();
+    let count = components.len() as f64;
+
+    let mut allocated = 0.0;
+    for component in &mut components[..last] {
+        let share = if list_total > 0.0 {
+            price * component.list_price.max(0.0) / list_total
+        } else {
+            price / count
+        };
+        component.allocated_price = (share * 100.0).round() / 100.0;
+        allocated += component.allocated_price;
+    }
+    components[last].allocated_price = ((price - allocated) * 100.0).round() / 100.0;
+}
+
+/// Names of the products in a bundle, to print under it.
+pub fn component_names(components: &[BundleComponent]) -> Vec<String> {
+    components.iter().map(|component| component.name.clone()).collect()
+}
+
+/// Tells apart bundle lines with different choices.
+pub fn key(components: &[BundleComponent]) -> String {
+    let mut product_ids: Vec<String> = components.iter().map(|component| component.product_id.to_string()).collect();
+    product_ids.sort_unstable();
+    product_ids.join("|")
+}
+
+/// Order items with every bundle replaced by its components, for kitchen
+/// routing, stock and taxes. The line price is split again among the
+/// components, and its notes and modifiers go with the first one so they are
+/// printed and taken from stock once.
+pub fn expand_items(items: &[OrderItem]) -> Vec<OrderItem> {
+    let mut expanded = Vec::new();
+    for item in items {
+        if item.components.is_empty() {
+            expanded.push(item.clone());
+            continue;
+        }
+        let mut components = item.components.clone();
+        allocate(item.price, &mut components);
+        expanded.extend(components.iter().enumerate().map(|(index, component)| OrderItem {
+            id: component.product_id,
+            name: component.name.clone(),
+            price: component.allocated_price,
+            quantity: item.quantity,
+            unit: item.unit,
+            category: component.category.clone(),
+            kitchen_status: None,
+            cost_price: None,
+            modifiers: if index == 0 { item.modifiers.clone() } else { Vec::new() },
+            components: Vec::new(),
+            line_id: item.line_id.clone(),
+            notes: if index == 0 { item.notes.clone() } else { None },
+            tax_rate: component.tax_rate.or(item.tax_rate),
+        }));
+    }
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn component(product_id: i64, list_price: f64) -> BundleComponent {
+        serde_json::from_value(json!({
+            "productId": product_id, "name": format!("Producto {}", product_id),
+            "listPrice": list_price, "allocatedPrice": 0.0,
+        }))
+        .unwrap()
+    }
+
+    fn product(id: i64, category: &str, price: f64) -> Product {
+        serde_json::from_value(json!({
+            "id": id, "name": format!("Producto {}", id), "price": price, "category": category,
+            "brand": "", "iconType": "", "selectedIcon": "", "uploadedImage": null,
+        }))
+        .unwrap()
+    }
+
+    fn slot(id: i64, name: &str, category: &str, required: bool) -> BundleSlot {
+        BundleSlot { id, name: name.to_string(), required, categories: vec![category.to_string()], product_ids: vec![] }
+    }
+
+    fn allocated(components: &[BundleComponent]) -> Vec<f64> {
+        components.iter().map(|component| component.allocated_price).collect()
+    }
+
+    #[test]
+    fn allocates_in_proportion_to_list_prices() {
+        let mut components = vec![component(1, 10.0), component(2, 5.0), component(3, 5.0)];
+        allocate(12.0, &mut components);
+        assert_eq!(allocated(&components), [6.0, 3.0, 3.0]);
+    }
+
+    #[test]
+    fn the_last_component_takes_the_rounding_difference() {
+        let mut components = vec![component(1, 1.0), component(2, 1.0), component(3, 1.0)];
+        allocate(10.0, &mut components);
+        assert_eq!(allocated(&components), [3.33, 3.33, 3.34]);
+
+        let mut free = vec![component(1, 0.0), component(2, 0.0)];
+        allocate(9.0, &mut free);
+        assert_eq!(allocated(&free), [4.5, 4.5]);
+    }
+
+    #[test]
+    fn build_checks_the_choices() {
+        let slots = [slot(1, "Primero", "Entrantes", true), slot(2, "Postre", "Postres", false)];
+        let products = [product(10, "Entrantes", 8.0), product(20, "Postres", 4.0)];
+        let choose = |choices: &[(i64, i64)]| -> Vec<BundleChoice> {
+            choices.iter().map(|(slot_id, product_id)| BundleChoice { slot_id: *slot_id, product_id: *product_id }).collect()
+        };
+
+        let components = build(&slots, &choose(&[(1, 10), (2, 20)]), &products, 9.0).unwrap();
+        assert_eq!(allocated(&components), [6.0, 3.0]);
+        assert_eq!(build(&slots, &choose(&[(1, 10)]), &products, 9.0).unwrap().len(), 1);
+        assert_eq!(build(&slots, &choose(&[(2, 20)]), &products, 9.0).unwrap_err(), "Elige Primero");
+        assert!(build(&slots, &choose(&[(1, 20)]), &products, 9.0).unwrap_err().contains("no se puede elegir"));
+        assert!(build(&slots, &choose(&[(3, 10)]), &products, 9.0).is_err());
+    }
+
+    #[test]
+    fn expanded_bundles_keep_notes_and_modifiers_once() {
+        let mut menu: OrderItem = serde_json::from_value(json!({
+            "id": 9, "name": "Menú", "price": 12.0, "quantity": 2.0, "notes": "sin gluten",
+            "modifiers": [{ "name": "Pan extra", "priceDelta": 0.0 }],
+        }))
+        .unwrap();
+        menu.components = vec![component(1, 10.0), component(2, 2.0)];
+        let expanded = expand_items(&[menu]);
+
+        assert_eq!(expanded.iter().map(|item| item.price).collect::<Vec<_>>(), [10.0, 2.0]);
+        assert_eq!(expanded.iter().map(|item| item.quantity).collect::<Vec<_>>(), [2.0, 2.0]);
+        assert_eq!(expanded[0].notes.as_deref(), Some("sin gluten"));
+        assert_eq!(expanded[0].modifiers.len(), 1);
+        assert_eq!(expanded[1].notes, None);
+        assert!(expanded[1].modifiers.is_empty());
+    }
+}
@@ -0,0 +1,374 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use p12_keystore::{Certificate, KeyStore, KeyStoreEntry, PrivateKeyChain};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::tax_id::validate_tax_id;
+
+/// Days before `not_after` at which a certificate starts raising expiry warnings
+pub const EXPIRY_WARNING_DAYS: i64 = 30;
+
+const KEY_FILE: &str = "store.key";
+// Earlier versions kept a persistent "signing.p12"; the prefix covers it too
+const EXPORT_PREFIX: &str = "signing";
+const NONCE_LEN: usize = 12;
+
+// X.520 attributes used by FNMT and other Spanish CAs to carry the holder's NIF
+const OID_SERIAL_NUMBER: &str = "2.5.4.5";
+const OID_ORGANIZATION_IDENTIFIER: &str = "2.5.4.97";
+
+pub struct ParsedCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    pub fingerprint: String,
+    pub nif: Option<String>,
+    pub not_before: i64,
+    pub not_after: i64,
+}
+
+/// Private key (PKCS#8 DER) and certificate chain (DER, leaf first) of an
+/// imported certificate.
+struct Identity {
+    key: Vec<u8>,
+    chain: Vec<Vec<u8>>,
+}
+
+impl Identity {
+    fn leaf(&self) -> &[u8] {
+        &self.chain[0]
+    }
+}
+
+fn pfx_identity(data: &[u8], password: &str) -> Result<Identity, String> {
+    let keystore = KeyStore::from_pkcs12(data, password)
+        .map_err(|e| format!("Failed to open PFX (wrong password?): {}", e))?;
+
+    let (_, chain) = keystore
+        .private_key_chain()
+        .ok_or("PFX file does not contain a private key")?;
+    if chain.chain().is_empty() {
+        return Err("PFX file does not contain a certificate".to_string());
+    }
+
+    Ok(Identity {
+        key: chain.key().to_vec(),
+        chain: chain.chain().iter().map(|cert| cert.as_der().to_vec()).collect(),
+    })
+}
+
+// Accepts PKCS#8 keys (plain or encrypted with PBES2) and PKCS#1 RSA keys. The
+// CERTIFICATE blocks keep their order in the bundle, leaf first.
+fn pem_identity(data: &[u8], passphrase: &str) -> Result<Identity, String> {
+    let blocks = pem::parse_many(data).map_err(|e| format!("Invalid PEM: {}", e))?;
+
+    let key_block = blocks
+        .iter()
+        .find(|b| b.tag().ends_with("PRIVATE KEY"))
+        .ok_or("PEM bundle does not contain a private key")?;
+    let key = match key_block.tag() {
+        "PRIVATE KEY" => key_block.contents().to_vec(),
+        "ENCRYPTED PRIVATE KEY" => decrypt_pkcs8(key_block.contents(), passphrase)?,
+        "RSA PRIVATE KEY" if key_block.headers().get("Proc-Type").is_some() => {
+            return Err("Legacy encrypted RSA keys are not supported; convert the key to PKCS#8".to_string());
+        }
+        "RSA PRIVATE KEY" => wrap_rsa_pkcs1(key_block.contents()),
+        tag => return Err(format!("Unsupported private key type: {}", tag)),
+    };
+
+    let chain: Vec<Vec<u8>> = blocks
+        .iter()
+        .filter(|b| b.tag() == "CERTIFICATE")
+        .map(|b| b.contents().to_vec())
+        .collect();
+    if chain.is_empty() {
+        return Err("PEM bundle does not contain a certificate".to_string());
+    }
+
+    Ok(Identity { key, chain })
+}
+
+fn decrypt_pkcs8(der: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    use pkcs5::der::{asn1::OctetStringRef, Decode, Reader, SliceReader};
+
+    let invalid = |e: pkcs5::der::Error| format!("Invalid encrypted private key: {}", e);
+    let mut reader = SliceReader::new(der).map_err(invalid)?;
+    let (scheme, ciphertext) = reader
+        .sequence(|r| {
+            let scheme = pkcs5::EncryptionScheme::decode(r)?;
+            let ciphertext = OctetStringRef::decode(r)?;
+            Ok((scheme, ciphertext))
+        })
+        .map_err(invalid)?;
+
+    scheme
+        .decrypt(passphrase, ciphertext.as_bytes())
+        .map_err(|_| "Failed to decrypt the private key (wrong passphrase?)".to_string())
+}
+
+// PrivateKeyInfo { version 0, rsaEncryption, OCTET STRING pkcs1 }
+fn wrap_rsa_pkcs1(pkcs1: &[u8]) -> Vec<u8> {
+    const VERSION_AND_ALGORITHM: &[u8] = &[
+        0x02, 0x01, 0x00, 0x30, 0x0d, 0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01,
+        0x01, 0x05, 0x00,
+    ];
+
+    let mut body = VERSION_AND_ALGORITHM.to_vec();
+    body.extend(der_header(0x04, pkcs1.len()));
+    body.extend_from_slice(pkcs1);
+
+    let mut der = der_header(0x30, body.len());
+    der.extend(body);
+    der
+}
+
+fn der_header(tag: u8, len: usize) -> Vec<u8> {
+    let mut header = vec![tag];
+    if len < 0x80 {
+        header.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().into_iter().skip_while(|b| *b == 0).collect();
+        header.push(0x80 | bytes.len() as u8);
+        header.extend(bytes);
+    }
+    header
+}
+
+// Derives the public key from the private key and compares it with the one in
+// the certificate, so a key from another certificate is rejected at import
+fn check_key_matches(key: &[u8], cert_der: &[u8]) -> Result<(), String> {
+    use ring::signature::{self, EcdsaKeyPair, KeyPair, RsaKeyPair};
+
+    let (_, cert) = X509Certificate::from_der(cert_der)
+        .map_err(|e| format!("Invalid X.509 certificate: {}", e))?;
+    let certificate_key = cert.public_key().subject_public_key.data.as_ref();
+
+    let rng = ring::rand::SystemRandom::new();
+    let private_key = RsaKeyPair::from_pkcs8(key)
+        .map(|pair| pair.public().as_ref().to_vec())
+        .or_else(|_| {
+            EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P256_SHA256_ASN1_SIGNING, key, &rng)
+                .or_else(|_| EcdsaKeyPair::from_pkcs8(&signature::ECDSA_P384_SHA384_ASN1_SIGNING, key, &rng))
+                .map(|pair| pair.public_key().as_ref().to_vec())
+        })
+        .map_err(|e| format!("Unsupported or invalid private key: {}", e))?;
+
+    if private_key != certificate_key {
+        return Err("La clave privada no corresponde al certificado".to_string());
+    }
+    Ok(())
+}
+
+fn parse_identity(identity: &Identity) -> Result<ParsedCertificate, String> {
+    check_key_matches(&identity.key, identity.leaf())?;
+    parse_der(identity.leaf())
+}
+
+pub fn parse_pfx(data: &[u8], password: &str) -> Result<ParsedCertificate, String> {
+    parse_identity(&pfx_identity(data, password)?)
+}
+
+/// Parses a PEM bundle, decrypting the private key with `passphrase` when it
+/// is encrypted.
+pub fn parse_pem(data: &[u8], passphrase: &str) -> Result<ParsedCertificate, String> {
+    parse_identity(&pem_identity(data, passphrase)?)
+}
+
+/// Re-packs a stored certificate as a PKCS#12 file protected by `password`,
+/// the form the signing service loads.
+pub fn to_pkcs12(format: &str, data: &[u8], password: &str) -> Result<Vec<u8>, String> {
+    if format == "pfx" {
+        return Ok(data.to_vec());
+    }
+
+    let identity = pem_identity(data, password)?;
+    let chain = identity
+        .chain
+        .iter()
+        .map(|der| Certificate::from_der(der).map_err(|e| format!("Invalid certificate: {}", e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let local_key_id = Sha256::digest(identity.leaf());
+
+    let mut keystore = KeyStore::new();
+    keystore.add_entry(
+        "signing",
+        KeyStoreEntry::PrivateKeyChain(PrivateKeyChain::new(&identity.key, local_key_id, chain)),
+    );
+    keystore
+        .writer(password)
+        .write()
+        .map_err(|e| format!("Failed to build PKCS#12 file: {}", e))
+}
+
+fn parse_der(der: &[u8]) -> Result<ParsedCertificate, String> {
+    let (_, cert) = X509Certificate::from_der(der)
+        .map_err(|e| format!("Invalid X.509 certificate: {}", e))?;
+
+    let validity = cert.validity();
+
+    Ok(ParsedCertificate {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        serial_number: cert.raw_serial_as_string(),
+        fingerprint: hex::encode(Sha256::digest(der)),
+        nif: extract_nif(&cert),
+        not_before: validity.not_before.timestamp(),
+        not_after: validity.not_after.timestamp(),
+    })
+}
+
+// Seal/representative certificates carry the entity NIF in organizationIdentifier
+// ("VATES-B12345678"); personal ones carry it in serialNumber ("IDCES-12345678Z")
+fn extract_nif(cert: &X509Certificate) -> Option<String> {
+    let attribute = |oid: &str| {
+        cert.subject()
+            .iter_attributes()
+            .find(|attr| attr.attr_type().to_id_string() == oid)
+            .and_then(|attr| attr.as_str().ok())
+    };
+
+    [OID_ORGANIZATION_IDENTIFIER, OID_SERIAL_NUMBER]
+        .iter()
+        .filter_map(|oid| attribute(oid))
+        .map(|value| {
+            value
+                .trim_start_matches("VATES-")
+                .trim_start_matches("IDCES-")
+                .to_string()
+        })
+        .find_map(|value| validate_tax_id(&value).ok().map(|tax_id| tax_id.value))
+}
+
+// Creates the file readable and writable only by the current user
+/// Writes `data` readable by the owner only.
+pub(crate) fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    restrict_permissions(path)?;
+    file.write_all(data)
+}
+
+pub(crate) fn restrict_permissions(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Encrypted file storage for imported certificates. The AES-256-GCM key is
+/// derived from a random per-install secret kept next to the files.
+pub struct CertificateStore {
+    dir: PathBuf,
+    cipher: Aes256Gcm,
+}
+
+impl CertificateStore {
+    pub fn open(dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create certificates directory: {}", e))?;
+
+        let key_path = dir.join(KEY_FILE);
+        let secret = match fs::read(&key_path) {
+            Ok(secret) => {
+                // Keys written by earlier versions were world-readable
+                restrict_permissions(&key_path)
+                    .map_err(|e| format!("Failed to restrict certificate store key permissions: {}", e))?;
+                secret
+            }
+            Err(_) => {
+                let secret = Aes256Gcm::generate_key(OsRng).to_vec();
+                write_private(&key_path, &secret)
+                    .map_err(|e| format!("Failed to write certificate store key: {}", e))?;
+                secret
+            }
+        };
+
+        let key = Sha256::digest(&secret);
+
+        Ok(CertificateStore {
+            dir,
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key)),
+        })
+    }
+
+    pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, data)
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Failed to decrypt certificate (store key changed?)".to_string())
+    }
+
+    pub fn write(&self, file_name: &str, data: &[u8]) -> Result<(), String> {
+        let sealed = self.encrypt(data)?;
+        fs::write(self.dir.join(file_name), sealed)
+            .map_err(|e| format!("Failed to write certificate: {}", e))
+    }
+
+    pub fn read(&self, file_name: &str) -> Result<Vec<u8>, String> {
+        let sealed = fs::read(self.dir.join(file_name))
+            .map_err(|e| format!("Failed to read certificate: {}", e))?;
+        self.decrypt(&sealed)
+    }
+
+    /// Writes the PKCS#12 handed to the signing service to a new file readable
+    /// only by the current user and returns its path. The caller removes it
+    /// once the service has loaded it; copies left behind by an earlier run
+    /// are cleaned up here.
+    pub fn export_temporary(&self, data: &[u8]) -> Result<PathBuf, String> {
+        for entry in fs::read_dir(&self.dir).map_err(|e| format!("Failed to read certificates directory: {}", e))? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let is_export = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(EXPORT_PREFIX) && name.ends_with(".p12"));
+            if is_export {
+                fs::remove_file(&path).map_err(|e| format!("Failed to remove certificate: {}", e))?;
+            }
+        }
+
+        let mut suffix = [0u8; 8];
+        OsRng.fill_bytes(&mut suffix);
+        let path = self.dir.join(format!("{}-{}.p12", EXPORT_PREFIX, hex::encode(suffix)));
+        write_private(&path, data).map_err(|e| format!("Failed to write certificate: {}", e))?;
+        Ok(path)
+    }
+
+    pub fn remove(&self, file_name: &str) -> Result<(), String> {
+        let path = self.dir.join(file_name);
+        if path.exists() {
+            fs::remove_file(path).map_err(|e| format!("Failed to remove certificate: {}", e))?;
+        }
+        Ok(())
+    }
+}
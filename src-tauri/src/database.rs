@@ -1,9 +1,48 @@
-use rusqlite::{Connection, Result, params};
+use rusqlite::{Connection, OptionalExtension, Result, params};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
-use crate::models::{Product, Category, Order, OrderItem, Table, User, ExportData, ImportData};
+use crate::models::{Product, SaleUnit, EmbeddedBarcodeLayout, Category, Order, OrderItem, Table, User, Customer, Supplier, BusinessProfile, ExportData, ImportData};
+use crate::bundle;
+use crate::kitchen::{compute_delta, route_items};
+use crate::order_line::{assign_line_ids, line_key};
+use crate::prebill::describe_changes;
+use crate::models::bundle::BundleSlot;
+use crate::models::certificate::CertificateInfo;
+use crate::models::modifier::{ModifierGroup, ModifierOption};
+use crate::models::kitchen::{Comanda, ComandaItem, ComandaPrint, Station};
 use crate::models::license::LicenseKey;
+use crate::models::prebill::{PrebillAlert, PrebillInfo};
+use crate::models::printer::{CashDrawerEvent, ReceiptTemplate};
+use crate::models::purchasing::{GoodsReceiptLine, PurchaseOrder, PurchaseOrderLine, SuggestedPurchaseLine};
+use crate::purchasing::status_after_receipt;
+use crate::models::recipe::{CostPriceChange, MarginGrouping, MarginLine, ProductCost, RecipeItem};
+use crate::recipe::{self, LostItem, SoldItem};
+use crate::models::stock::{
+    LossSummary, LowStockAlert, LowStockItem, NewStockMovement, StockDiscrepancy, StockLot, StockMovement, Stocktake, StocktakeCount, StocktakeLine,
+};
+use crate::stock::{self, consumes_stock, crossed_minimum, is_loss, suggested_quantity};
+
+// Read by product_from_row, with the products table aliased as p
+const PRODUCT_COLUMNS: &str =
+    "p.id, p.name, p.price, p.category, p.brand, p.icon_type, p.selected_icon, p.uploaded_image, p.stock,
+     p.barcode, p.allergens, p.net_quantity, p.net_unit, p.min_stock, p.reorder_quantity, p.supplier_id,
+     p.ingredient, p.cost_price, p.sale_unit, p.tax_rate";
+
+// Order dates are UTC timestamps from the frontend: reports group them by
+// local day. Older orders only stored the day and are taken as they are.
+const LOCAL_ORDER_DATE: &str =
+    "CASE WHEN length(o.date) > 10 THEN datetime(o.date, 'localtime') ELSE o.date END";
+
+/// What saving an order produced, handed back so the caller can notify and
+/// print once the transaction is committed.
+#[derive(Debug, Default)]
+pub struct OrderSaved {
+    pub comandas: Vec<Comanda>,
+    pub low_stock: Vec<LowStockAlert>,
+    pub prebill_alert: Option<PrebillAlert>,
+}
 
 pub struct Database {
     conn: Mutex<Connection>,
@@ -34,7 +73,8 @@ impl Database {
                 icon_type TEXT,
                 selected_icon TEXT,
                 uploaded_image TEXT,
-                stock INTEGER DEFAULT 0
+                stock REAL DEFAULT 0,
+                barcode TEXT
             );
 
             -- Categories table
@@ -66,8 +106,9 @@ impl Database {
                 product_id INTEGER NOT NULL,
                 name TEXT NOT NULL,
                 price REAL NOT NULL,
-                quantity INTEGER DEFAULT 1,
+                quantity REAL DEFAULT 1,
                 category TEXT,
+                kitchen_status TEXT,
                 FOREIGN KEY (order_id) REFERENCES orders(id) ON DELETE CASCADE
             );
 
@@ -88,6 +129,63 @@ impl Database {
                 pinned_product_ids TEXT
             );
 
+            -- Customers table
+            CREATE TABLE IF NOT EXISTS customers (
+                id INTEGER PRIMARY KEY,
+                cif_nif TEXT NOT NULL,
+                nombre_fiscal TEXT NOT NULL,
+                nombre_comercial TEXT NOT NULL DEFAULT '',
+                direccion TEXT NOT NULL DEFAULT '',
+                codigo_postal TEXT NOT NULL DEFAULT '',
+                poblacion TEXT NOT NULL DEFAULT '',
+                telefono TEXT NOT NULL DEFAULT '',
+                email TEXT NOT NULL DEFAULT '',
+                activo INTEGER DEFAULT 1,
+                created_at TEXT,
+                updated_at TEXT
+            );
+
+            -- Suppliers table
+            CREATE TABLE IF NOT EXISTS suppliers (
+                id INTEGER PRIMARY KEY,
+                cif_nif TEXT NOT NULL DEFAULT '',
+                nombre_fiscal TEXT NOT NULL,
+                nombre_comercial TEXT NOT NULL DEFAULT '',
+                direccion TEXT NOT NULL DEFAULT '',
+                telefono TEXT NOT NULL DEFAULT '',
+                email TEXT NOT NULL DEFAULT '',
+                contacto TEXT NOT NULL DEFAULT '',
+                activo INTEGER DEFAULT 1,
+                created_at TEXT,
+                updated_at TEXT
+            );
+
+            -- Business profile (issuer data), single row
+            CREATE TABLE IF NOT EXISTS business_profile (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                nif TEXT NOT NULL,
+                nombre_razon TEXT NOT NULL,
+                nombre_comercial TEXT,
+                direccion TEXT NOT NULL DEFAULT '',
+                codigo_postal TEXT NOT NULL DEFAULT '',
+                poblacion TEXT NOT NULL DEFAULT '',
+                provincia TEXT,
+                telefono TEXT,
+                email TEXT,
+                serie_factura TEXT NOT NULL,
+                tipo_factura TEXT NOT NULL DEFAULT 'F1',
+                descripcion_operacion TEXT NOT NULL DEFAULT '',
+                updated_at TEXT
+            );
+
+            -- Invoice counters per series and year
+            CREATE TABLE IF NOT EXISTS invoice_counters (
+                serie TEXT NOT NULL,
+                year INTEGER NOT NULL,
+                last_number INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (serie, year)
+            );
+
             -- Licenses table
             CREATE TABLE IF NOT EXISTS licenses (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -100,45 +198,680 @@ impl Database {
                 license_type TEXT NOT NULL
             );
 
+            -- Certificates table (payload lives encrypted in the certificates dir)
+            CREATE TABLE IF NOT EXISTS certificates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                alias TEXT NOT NULL,
+                format TEXT NOT NULL,
+                certificate_type TEXT NOT NULL DEFAULT 'personal',
+                subject TEXT NOT NULL,
+                issuer TEXT NOT NULL,
+                serial_number TEXT NOT NULL,
+                fingerprint TEXT NOT NULL UNIQUE,
+                nif TEXT,
+                not_before INTEGER NOT NULL,
+                not_after INTEGER NOT NULL,
+                imported_at INTEGER NOT NULL,
+                file_name TEXT NOT NULL,
+                encrypted_password TEXT NOT NULL
+            );
+
+            -- Preparation stations (kitchen, bar) and their routing rules
+            CREATE TABLE IF NOT EXISTS stations (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                printer_interface TEXT,
+                active INTEGER DEFAULT 1
+            );
+
+            CREATE TABLE IF NOT EXISTS station_routes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                station_id INTEGER NOT NULL,
+                category TEXT,
+                product_id INTEGER,
+                FOREIGN KEY (station_id) REFERENCES stations(id) ON DELETE CASCADE
+            );
+
+            -- Comandas (per-station kitchen tickets) and their print history
+            CREATE TABLE IF NOT EXISTS comandas (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id INTEGER NOT NULL,
+                station_id INTEGER NOT NULL,
+                station_name TEXT NOT NULL,
+                table_number INTEGER DEFAULT 0,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS comanda_items (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                comanda_id INTEGER NOT NULL,
+                product_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                updated_at TEXT,
+                FOREIGN KEY (comanda_id) REFERENCES comandas(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS comanda_prints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                comanda_id INTEGER NOT NULL,
+                printed_at TEXT NOT NULL,
+                reprint INTEGER NOT NULL DEFAULT 0,
+                error TEXT,
+                FOREIGN KEY (comanda_id) REFERENCES comandas(id) ON DELETE CASCADE
+            );
+
+            -- Receipt templates table
+            CREATE TABLE IF NOT EXISTS receipt_templates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                kind TEXT NOT NULL,
+                printer_name TEXT,
+                content TEXT NOT NULL,
+                updated_at TEXT
+            );
+
+            -- Cash drawer audit table
+            CREATE TABLE IF NOT EXISTS cash_drawer_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                opened_at TEXT NOT NULL,
+                user_id INTEGER,
+                reason TEXT NOT NULL,
+                order_id INTEGER,
+                success INTEGER NOT NULL DEFAULT 1,
+                error TEXT
+            );
+
+            -- Pre-bill tables
+            CREATE TABLE IF NOT EXISTS prebill_prints (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id INTEGER NOT NULL,
+                printed_at TEXT NOT NULL,
+                user_id INTEGER,
+                total REAL NOT NULL,
+                items TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS prebill_alerts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id INTEGER NOT NULL,
+                detected_at TEXT NOT NULL,
+                prebill_total REAL NOT NULL,
+                new_total REAL,
+                details TEXT NOT NULL,
+                reviewed INTEGER NOT NULL DEFAULT 0,
+                reviewed_by INTEGER,
+                reviewed_at TEXT
+            );
+
+            -- Stock ledger: products.stock caches the sum of each product's movements
+            CREATE TABLE IF NOT EXISTS stock_movements (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                product_id INTEGER NOT NULL,
+                movement_type TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                order_id INTEGER,
+                user_id INTEGER,
+                reason TEXT,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_stock_movements_product ON stock_movements(product_id);
+            CREATE INDEX IF NOT EXISTS idx_stock_movements_order ON stock_movements(order_id);
+
+            -- Purchase orders to suppliers
+            CREATE TABLE IF NOT EXISTS purchase_orders (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                supplier_id INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'draft',
+                created_at TEXT NOT NULL,
+                expected_at TEXT,
+                received_at TEXT,
+                notes TEXT,
+                created_by INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS purchase_order_lines (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                purchase_order_id INTEGER NOT NULL,
+                product_id INTEGER NOT NULL,
+                product_name TEXT NOT NULL,
+                quantity REAL NOT NULL,
+                received_quantity REAL NOT NULL DEFAULT 0,
+                cost_price REAL NOT NULL DEFAULT 0,
+                FOREIGN KEY (purchase_order_id) REFERENCES purchase_orders(id) ON DELETE CASCADE
+            );
+
+            -- Cost price history: products.cost_price holds the latest entry
+            CREATE TABLE IF NOT EXISTS product_costs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                product_id INTEGER NOT NULL,
+                cost_price REAL NOT NULL,
+                source TEXT NOT NULL,
+                purchase_order_id INTEGER,
+                user_id INTEGER,
+                created_at TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_product_costs_product ON product_costs(product_id);
+
+            -- Every barcode of a product, the main one included
+            CREATE TABLE IF NOT EXISTS product_barcodes (
+                barcode TEXT PRIMARY KEY,
+                product_id INTEGER NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_product_barcodes_product ON product_barcodes(product_id);
+
+            -- Layout of the in-store codes printed by the scales, single row
+            CREATE TABLE IF NOT EXISTS barcode_layout (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                price_types TEXT NOT NULL,
+                weight_types TEXT NOT NULL,
+                item_digits INTEGER NOT NULL,
+                price_check_digit INTEGER NOT NULL DEFAULT 0
+            );
+
+            -- Variant and modifier groups offered when selling a product
+            CREATE TABLE IF NOT EXISTS modifier_groups (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                variant INTEGER NOT NULL DEFAULT 0,
+                required INTEGER NOT NULL DEFAULT 0,
+                min_select INTEGER NOT NULL DEFAULT 0,
+                max_select INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS modifier_options (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                group_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                price_delta REAL NOT NULL DEFAULT 0,
+                product_id INTEGER,
+                quantity REAL NOT NULL DEFAULT 1,
+                position INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (group_id) REFERENCES modifier_groups(id) ON DELETE CASCADE
+            );
+
+            CREATE TABLE IF NOT EXISTS product_modifier_groups (
+                product_id INTEGER NOT NULL,
+                group_id INTEGER NOT NULL,
+                position INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (product_id, group_id)
+            );
+
+            -- Bundles (menú del día): slots filled with a product of the
+            -- listed categories or products
+            CREATE TABLE IF NOT EXISTS bundle_slots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                bundle_id INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                required INTEGER NOT NULL DEFAULT 1,
+                position INTEGER NOT NULL DEFAULT 0
+            );
+
+            CREATE TABLE IF NOT EXISTS bundle_slot_routes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                slot_id INTEGER NOT NULL,
+                category TEXT,
+                product_id INTEGER,
+                FOREIGN KEY (slot_id) REFERENCES bundle_slots(id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_bundle_slots_bundle ON bundle_slots(bundle_id);
+
+            -- Recipes (escandallos): ingredients consumed by one unit of a product
+            CREATE TABLE IF NOT EXISTS recipe_items (
+                product_id INTEGER NOT NULL,
+                ingredient_id INTEGER NOT NULL,
+                quantity REAL NOT NULL,
+                PRIMARY KEY (product_id, ingredient_id)
+            );
+
+            -- Recipes as they were when an order first sold each product, so
+            -- editing a recipe does not rewrite the stock taken by past orders
+            CREATE TABLE IF NOT EXISTS order_recipes (
+                order_id INTEGER NOT NULL,
+                product_id INTEGER NOT NULL,
+                ingredients TEXT NOT NULL,
+                PRIMARY KEY (order_id, product_id)
+            );
+
+            CREATE TABLE IF NOT EXISTS stocktakes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT,
+                category TEXT,
+                status TEXT NOT NULL DEFAULT 'open',
+                opened_at TEXT NOT NULL,
+                opened_by INTEGER,
+                closed_at TEXT,
+                closed_by INTEGER
+            );
+
+            CREATE TABLE IF NOT EXISTS stocktake_counts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                stocktake_id INTEGER NOT NULL,
+                product_id INTEGER NOT NULL,
+                device TEXT NOT NULL DEFAULT '',
+                counted REAL NOT NULL,
+                user_id INTEGER,
+                counted_at TEXT NOT NULL,
+                UNIQUE(stocktake_id, product_id, device),
+                FOREIGN KEY (stocktake_id) REFERENCES stocktakes(id) ON DELETE CASCADE
+            );
+
+            -- Lots of perishable products, created on goods receipt
+            CREATE TABLE IF NOT EXISTS stock_lots (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                product_id INTEGER NOT NULL,
+                lot_number TEXT,
+                expiry_date TEXT,
+                quantity REAL NOT NULL,
+                remaining REAL NOT NULL,
+                received_at TEXT NOT NULL,
+                purchase_order_id INTEGER
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_stock_lots_product ON stock_lots(product_id);
+
+            -- Quantity each movement took from (or returned to) each lot
+            CREATE TABLE IF NOT EXISTS stock_lot_allocations (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                movement_id INTEGER NOT NULL,
+                lot_id INTEGER NOT NULL,
+                quantity REAL NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_stock_lot_allocations_lot ON stock_lot_allocations(lot_id);
+            CREATE INDEX IF NOT EXISTS idx_stock_lot_allocations_movement ON stock_lot_allocations(movement_id);
+
+            CREATE TABLE IF NOT EXISTS low_stock_alerts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                product_id INTEGER NOT NULL,
+                product_name TEXT NOT NULL,
+                stock REAL NOT NULL,
+                min_stock REAL NOT NULL,
+                created_at TEXT NOT NULL,
+                acknowledged INTEGER NOT NULL DEFAULT 0,
+                acknowledged_by INTEGER,
+                acknowledged_at TEXT
+            );
+
             -- Enable foreign keys
             PRAGMA foreign_keys = ON;
             "
         )?;
 
+        // Columns added after the first release
+        Self::add_column_if_missing(&conn, "products", "barcode", "TEXT")?;
+        Self::add_column_if_missing(&conn, "products", "allergens", "TEXT")?;
+        Self::add_column_if_missing(&conn, "products", "net_quantity", "REAL")?;
+        Self::add_column_if_missing(&conn, "products", "net_unit", "TEXT")?;
+        Self::add_column_if_missing(&conn, "products", "min_stock", "REAL")?;
+        Self::add_column_if_missing(&conn, "products", "reorder_quantity", "REAL")?;
+        Self::add_column_if_missing(&conn, "products", "supplier_id", "INTEGER")?;
+        Self::add_column_if_missing(&conn, "products", "ingredient", "INTEGER NOT NULL DEFAULT 0")?;
+        Self::add_column_if_missing(&conn, "products", "cost_price", "REAL")?;
+        Self::add_column_if_missing(&conn, "products", "sale_unit", "TEXT NOT NULL DEFAULT 'unit'")?;
+        Self::add_column_if_missing(&conn, "products", "tax_rate", "REAL")?;
+        Self::add_column_if_missing(&conn, "order_items", "kitchen_status", "TEXT")?;
+        Self::add_column_if_missing(&conn, "order_items", "cost_price", "REAL")?;
+        Self::add_column_if_missing(&conn, "order_items", "modifiers", "TEXT")?;
+        Self::add_column_if_missing(&conn, "order_items", "components", "TEXT")?;
+        Self::add_column_if_missing(&conn, "order_items", "line_id", "TEXT")?;
+        Self::add_column_if_missing(&conn, "order_items", "notes", "TEXT")?;
+        Self::add_column_if_missing(&conn, "order_items", "unit", "TEXT NOT NULL DEFAULT 'unit'")?;
+        Self::add_column_if_missing(&conn, "order_items", "tax_rate", "REAL")?;
+        Self::add_column_if_missing(&conn, "stock_movements", "reason_code", "TEXT")?;
+        Self::add_column_if_missing(&conn, "stock_movements", "unit_cost", "REAL")?;
+        Self::add_column_if_missing(&conn, "stocktake_counts", "expected", "REAL")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "status", "TEXT NOT NULL DEFAULT 'pending'")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "updated_at", "TEXT")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "modifiers", "TEXT")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "line_id", "TEXT")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "notes", "TEXT")?;
+        Self::add_column_if_missing(&conn, "comanda_items", "unit", "TEXT NOT NULL DEFAULT 'unit'")?;
+
+        // Quantities were whole units before products could be sold by weight
+        Self::retype_column_if_needed(&conn, "order_items", "quantity", "REAL")?;
+        Self::retype_column_if_needed(&conn, "comanda_items", "quantity", "REAL")?;
+
+        // Lines saved before line ids existed
+        conn.execute(
+            "UPDATE order_items SET line_id = lower(hex(randomblob(8))) WHERE line_id IS NULL",
+            [],
+        )?;
+
+        // Stock entered before the ledger existed becomes an opening adjustment
+        let movements: i64 = conn.query_row("SELECT COUNT(*) FROM stock_movements", [], |row| row.get(0))?;
+        if movements == 0 {
+            let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            conn.execute(
+                "INSERT INTO stock_movements (product_id, movement_type, quantity, reason, created_at)
+                 SELECT id, 'adjustment', stock, 'Stock inicial', ?1 FROM products WHERE COALESCE(stock, 0) != 0",
+                params![created_at],
+            )?;
+        }
+
+        // Product search index, accent-insensitive and with prefix indexes
+        // for short words. Triggers keep it in step with products and barcodes.
+        conn.execute_batch(
+            "
+            CREATE VIRTUAL TABLE IF NOT EXISTS products_fts USING fts5(
+                name, brand, category, barcodes,
+                tokenize = 'unicode61 remove_diacritics 2',
+                prefix = '2 3'
+            );
+
+            -- INSERT OR REPLACE on products fires no delete trigger, so the
+            -- insert trigger clears any previous row itself
+            CREATE TRIGGER IF NOT EXISTS products_fts_insert AFTER INSERT ON products BEGIN
+                DELETE FROM products_fts WHERE rowid = NEW.id;
+                INSERT INTO products_fts (rowid, name, brand, category, barcodes)
+                VALUES (
+                    NEW.id, NEW.name, COALESCE(NEW.brand, ''), NEW.category,
+                    (SELECT COALESCE(group_concat(barcode, ' '), '') FROM product_barcodes WHERE product_id = NEW.id)
+                );
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS products_fts_update AFTER UPDATE OF name, brand, category ON products BEGIN
+                UPDATE products_fts SET name = NEW.name, brand = COALESCE(NEW.brand, ''), category = NEW.category
+                WHERE rowid = NEW.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS products_fts_delete AFTER DELETE ON products BEGIN
+                DELETE FROM products_fts WHERE rowid = OLD.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS product_barcodes_fts_insert AFTER INSERT ON product_barcodes BEGIN
+                UPDATE products_fts
+                SET barcodes = (SELECT COALESCE(group_concat(barcode, ' '), '') FROM product_barcodes WHERE product_id = NEW.product_id)
+                WHERE rowid = NEW.product_id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS product_barcodes_fts_delete AFTER DELETE ON product_barcodes BEGIN
+                UPDATE products_fts
+                SET barcodes = (SELECT COALESCE(group_concat(barcode, ' '), '') FROM product_barcodes WHERE product_id = OLD.product_id)
+                WHERE rowid = OLD.product_id;
+            END;
+            "
+        )?;
+
+        // Products saved before the index existed
+        let indexed: i64 = conn.query_row("SELECT COUNT(*) FROM products_fts", [], |row| row.get(0))?;
+        let products: i64 = conn.query_row("SELECT COUNT(*) FROM products", [], |row| row.get(0))?;
+        if indexed != products {
+            conn.execute_batch(
+                "DELETE FROM products_fts;
+                 INSERT INTO products_fts (rowid, name, brand, category, barcodes)
+                 SELECT p.id, p.name, COALESCE(p.brand, ''), p.category,
+                        (SELECT COALESCE(group_concat(b.barcode, ' '), '') FROM product_barcodes b WHERE b.product_id = p.id)
+                 FROM products p",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+        let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+        let exists = stmt
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<_>>>()?
+            .iter()
+            .any(|name| name == column);
+
+        if !exists {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))?;
+        }
         Ok(())
     }
 
+    // SQLite can't change a column type, so the table is rebuilt from its own
+    // CREATE statement with the new type, keeping its rows, indexes and triggers
+    fn retype_column_if_needed(conn: &Connection, table: &str, column: &str, column_type: &str) -> Result<()> {
+        let declared = conn
+            .prepare(&format!("PRAGMA table_info({})", table))?
+            .query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .find(|(name, _)| name == column)
+            .map(|(_, declared)| declared);
+        let Some(declared) = declared.filter(|declared| !declared.eq_ignore_ascii_case(column_type)) else {
+            return Ok(());
+        };
+
+        let create: String = conn.query_row(
+            "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?1",
+            params![table],
+            |row| row.get(0),
+        )?;
+        let create = create.replacen(
+            &format!("{} {}", column, declared),
+            &format!("{} {}", column, column_type),
+            1,
+        );
+        let dependents = conn
+            .prepare("SELECT sql FROM sqlite_master WHERE type IN ('index', 'trigger') AND tbl_name = ?1 AND sql IS NOT NULL")?
+            .query_map(params![table], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>>>()?;
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute_batch(&format!(
+            "ALTER TABLE {table} RENAME TO {table}_old;
+             {create};
+             INSERT INTO {table} SELECT * FROM {table}_old;
+             DROP TABLE {table}_old;"
+        ))?;
+        for sql in dependents {
+            tx.execute_batch(&sql)?;
+        }
+        tx.commit()
+    }
+
     // ==================== Products ====================
 
+    // Barcodes are filled in by set_barcodes
+    fn product_from_row(row: &rusqlite::Row) -> Result<Product> {
+        let allergens_json: Option<String> = row.get(10)?;
+        let allergens = allergens_json.and_then(|json| {
+            serde_json::from_str(&json).ok()
+        });
+
+        Ok(Product {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            price: row.get(2)?,
+            category: row.get(3)?,
+            brand: row.get(4)?,
+            icon_type: row.get(5)?,
+            selected_icon: row.get(6)?,
+            uploaded_image: row.get(7)?,
+            stock: row.get(8)?,
+            barcode: row.get(9)?,
+            allergens,
+            net_quantity: row.get(11)?,
+            net_unit: row.get(12)?,
+            min_stock: row.get(13)?,
+            reorder_quantity: row.get(14)?,
+            supplier_id: row.get(15)?,
+            ingredient: row.get::<_, i32>(16)? != 0,
+            cost_price: row.get(17)?,
+            sale_unit: SaleUnit::from_name(&row.get::<_, String>(18)?),
+            tax_rate: row.get(19)?,
+            barcodes: Vec::new(),
+        })
+    }
+
+    // `codes` holds every code of the product, the main one included
+    fn set_barcodes(product: &mut Product, codes: Vec<String>) {
+        let main = product.barcode.as_deref().map(str::trim);
+        product.barcodes = codes
+            .into_iter()
+            .filter(|code| Some(code.as_str()) != main)
+            .collect();
+    }
+
     pub fn get_products(&self) -> Result<Vec<Product>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, price, category, brand, icon_type, selected_icon, uploaded_image, stock
-             FROM products"
-        )?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM products p", PRODUCT_COLUMNS))?;
+        let products = stmt.query_map([], Self::product_from_row)?.collect::<Result<Vec<_>>>()?;
 
-        let products = stmt.query_map([], |row| {
-            Ok(Product {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                price: row.get(2)?,
-                category: row.get(3)?,
-                brand: row.get(4)?,
-                icon_type: row.get(5)?,
-                selected_icon: row.get(6)?,
-                uploaded_image: row.get(7)?,
-                stock: row.get(8)?,
-            })
-        })?.collect::<Result<Vec<_>>>()?;
+        let mut barcodes: HashMap<i64, Vec<String>> = HashMap::new();
+        let mut stmt = conn.prepare("SELECT product_id, barcode FROM product_barcodes ORDER BY position, barcode")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?;
+        for row in rows {
+            let (product_id, barcode) = row?;
+            barcodes.entry(product_id).or_default().push(barcode);
+        }
+
+        let mut products = products;
+        for product in &mut products {
+            Self::set_barcodes(product, barcodes.remove(&product.id).unwrap_or_default());
+        }
+
+        Ok(products)
+    }
+
+    // The main barcode first. A code already taken by another product is
+    // left with it; the commands reject those before saving.
+    fn replace_product_barcodes_internal(conn: &Connection, product: &Product) -> Result<()> {
+        conn.execute("DELETE FROM product_barcodes WHERE product_id = ?1", params![product.id])?;
+        let codes = product.barcode.iter().chain(&product.barcodes).map(|code| code.trim());
+        for (position, code) in codes.filter(|code| !code.is_empty()).enumerate() {
+            conn.execute(
+                "INSERT OR IGNORE INTO product_barcodes (barcode, product_id, position) VALUES (?1, ?2, ?3)",
+                params![code, product.id, position as i64],
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Products matching an FTS5 query (see search::fts_query), best first.
+    /// Matches on the name weigh more than on the brand, category or barcodes.
+    pub fn search_products(&self, fts_query: &str, limit: u32) -> Result<Vec<Product>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM products_fts
+             JOIN products p ON p.id = products_fts.rowid
+             WHERE products_fts MATCH ?1
+             ORDER BY bm25(products_fts, 10.0, 4.0, 2.0, 1.0), p.name
+             LIMIT ?2",
+            PRODUCT_COLUMNS
+        ))?;
+        let mut products = stmt.query_map(params![fts_query, limit], Self::product_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+        for product in &mut products {
+            let codes = Self::get_product_codes_internal(&conn, product.id)?;
+            Self::set_barcodes(product, codes);
+        }
 
         Ok(products)
     }
 
-    pub fn create_product(&self, product: &Product) -> Result<()> {
+    /// The saved layout, or the usual one (types 0-4 price, 5-9 weight,
+    /// 5-digit item numbers) until one is saved.
+    pub fn get_barcode_layout(&self) -> Result<EmbeddedBarcodeLayout> {
+        let conn = self.conn.lock().unwrap();
+        let layout = conn.query_row(
+            "SELECT price_types, weight_types, item_digits, price_check_digit FROM barcode_layout WHERE id = 1",
+            [],
+            |row| {
+                Ok(EmbeddedBarcodeLayout {
+                    price_types: row.get(0)?,
+                    weight_types: row.get(1)?,
+                    item_digits: row.get::<_, i64>(2)? as usize,
+                    price_check_digit: row.get::<_, i32>(3)? != 0,
+                })
+            },
+        ).optional()?;
+        Ok(layout.unwrap_or_default())
+    }
+
+    pub fn save_barcode_layout(&self, layout: &EmbeddedBarcodeLayout) -> Result<()> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT OR REPLACE INTO products (id, name, price, category, brand, icon_type, selected_icon, uploaded_image, stock)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            "INSERT OR REPLACE INTO barcode_layout (id, price_types, weight_types, item_digits, price_check_digit)
+             VALUES (1, ?1, ?2, ?3, ?4)",
+            params![
+                layout.price_types,
+                layout.weight_types,
+                layout.item_digits as i64,
+                layout.price_check_digit as i32
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_product_codes_internal(conn: &Connection, product_id: i64) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT barcode FROM product_barcodes WHERE product_id = ?1 ORDER BY position, barcode"
+        )?;
+        let codes = stmt.query_map(params![product_id], |row| row.get(0))?.collect();
+        codes
+    }
+
+    pub fn get_product(&self, id: i64) -> Result<Option<Product>> {
+        let conn = self.conn.lock().unwrap();
+        let Some(mut product) = conn.query_row(
+            &format!("SELECT {} FROM products p WHERE p.id = ?1", PRODUCT_COLUMNS),
+            params![id],
+            Self::product_from_row,
+        ).optional()? else {
+            return Ok(None);
+        };
+        let codes = Self::get_product_codes_internal(&conn, product.id)?;
+        Self::set_barcodes(&mut product, codes);
+        Ok(Some(product))
+    }
+
+    /// Product a barcode is registered to, exactly as stored.
+    pub fn get_product_by_barcode(&self, barcode: &str) -> Result<Option<Product>> {
+        let product_id: Option<i64> = {
+            let conn = self.conn.lock().unwrap();
+            conn.query_row(
+                "SELECT product_id FROM product_barcodes WHERE barcode = ?1",
+                params![barcode],
+                |row| row.get(0),
+            ).optional()?
+        };
+        match product_id {
+            Some(id) => self.get_product(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Stock only changes through stock movements: saving an existing product
+    /// keeps its stored stock, and a new one starts with the stock it is
+    /// created with.
+    pub fn create_product(&self, product: &Product) -> Result<Option<LowStockAlert>> {
+        self.create_product_internal(product, true)
+    }
+
+    // Imports restore the exported stock, logging the difference as an
+    // adjustment
+    fn create_product_internal(&self, product: &Product, live: bool) -> Result<Option<LowStockAlert>> {
+        let mut conn = self.conn.lock().unwrap();
+        let allergens_json = product.allergens.as_ref()
+            .map(|allergens| serde_json::to_string(allergens).unwrap_or_default());
+        let tx = conn.transaction()?;
+        let stored_stock: Option<f64> = tx.query_row(
+            "SELECT COALESCE(stock, 0) FROM products WHERE id = ?1",
+            params![product.id],
+            |row| row.get(0),
+        ).ok();
+        let stock = match stored_stock {
+            Some(stored) if live => Some(stored),
+            _ => product.stock,
+        };
+        tx.execute(
+            "INSERT OR REPLACE INTO products (id, name, price, category, brand, icon_type, selected_icon, uploaded_image, stock,
+                                               barcode, allergens, net_quantity, net_unit, min_stock, reorder_quantity,
+                                               supplier_id, ingredient, cost_price, sale_unit, tax_rate)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                     COALESCE(?18, (SELECT cost_price FROM products WHERE id = ?1)), ?19, ?20)",
             params![
                 product.id,
                 product.name,
@@ -148,17 +881,49 @@ impl Database {
                 product.icon_type,
                 product.selected_icon,
                 product.uploaded_image,
-                product.stock
+                stock,
+                product.barcode,
+                allergens_json,
+                product.net_quantity,
+                product.net_unit,
+                product.min_stock,
+                product.reorder_quantity,
+                product.supplier_id,
+                product.ingredient as i32,
+                product.cost_price,
+                product.sale_unit.as_str(),
+                product.tax_rate
             ],
         )?;
-        Ok(())
+        if let Some(cost_price) = product.cost_price {
+            Self::record_cost_change_internal(&tx, product.id, cost_price, "manual", None, None)?;
+        }
+        Self::replace_product_barcodes_internal(&tx, product)?;
+        let reason = if stored_stock.is_some() { "Importación de datos" } else { "Stock inicial" };
+        let alert = Self::record_stock_edit_internal(
+            &tx,
+            product.id,
+            stock.unwrap_or(0.0) - stored_stock.unwrap_or(0.0),
+            reason,
+            stored_stock.unwrap_or(0.0),
+        )?;
+        tx.commit()?;
+        Ok(alert)
     }
 
+    /// A product sent without a cost price keeps the stored one. The stock is
+    /// left alone: a form opened before a sale would otherwise undo it.
     pub fn update_product(&self, product: &Product) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute(
+        let mut conn = self.conn.lock().unwrap();
+        let allergens_json = product.allergens.as_ref()
+            .map(|allergens| serde_json::to_string(allergens).unwrap_or_default());
+        let tx = conn.transaction()?;
+        tx.execute(
             "UPDATE products SET name = ?2, price = ?3, category = ?4, brand = ?5,
-             icon_type = ?6, selected_icon = ?7, uploaded_image = ?8, stock = ?9
+             icon_type = ?6, selected_icon = ?7, uploaded_image = ?8,
+             barcode = ?9, allergens = ?10, net_quantity = ?11, net_unit = ?12,
+             min_stock = ?13, reorder_quantity = ?14, supplier_id = ?15, ingredient = ?16,
+             cost_price = COALESCE(?17, cost_price), sale_unit = ?18, tax_rate = ?19
              WHERE id = ?1",
             params![
                 product.id,
@@ -169,16 +934,48 @@ impl Database {
                 product.icon_type,
                 product.selected_icon,
                 product.uploaded_image,
-                product.stock
+                product.barcode,
+                allergens_json,
+                product.net_quantity,
+                product.net_unit,
+                product.min_stock,
+                product.reorder_quantity,
+                product.supplier_id,
+                product.ingredient as i32,
+                product.cost_price,
+                product.sale_unit.as_str(),
+                product.tax_rate
             ],
         )?;
-        Ok(())
+        if let Some(cost_price) = product.cost_price {
+            Self::record_cost_change_internal(&tx, product.id, cost_price, "manual", None, None)?;
+        }
+        Self::replace_product_barcodes_internal(&tx, product)?;
+        tx.commit()
     }
 
+    /// Deletes a product with its barcodes, modifiers, bundle slots, recipes,
+    /// station routes and lots. Its stock movements stay in the ledger.
     pub fn delete_product(&self, id: i64) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM products WHERE id = ?1", params![id])?;
-        Ok(())
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM product_modifier_groups WHERE product_id = ?1", params![id])?;
+        tx.execute("DELETE FROM product_barcodes WHERE product_id = ?1", params![id])?;
+        tx.execute(
+            "DELETE FROM bundle_slot_routes WHERE slot_id IN (SELECT id FROM bundle_slots WHERE bundle_id = ?1)
+                OR product_id = ?1",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM bundle_slots WHERE bundle_id = ?1", params![id])?;
+        tx.execute("DELETE FROM recipe_items WHERE product_id = ?1 OR ingredient_id = ?1", params![id])?;
+        tx.execute("DELETE FROM station_routes WHERE product_id = ?1", params![id])?;
+        tx.execute(
+            "DELETE FROM stock_lot_allocations WHERE lot_id IN (SELECT id FROM stock_lots WHERE product_id = ?1)",
+            params![id],
+        )?;
+        tx.execute("DELETE FROM stock_lots WHERE product_id = ?1", params![id])?;
+        tx.execute("DELETE FROM products WHERE id = ?1", params![id])?;
+        tx.commit()
     }
 
     // ==================== Categories ====================
@@ -257,60 +1054,139 @@ impl Database {
         Ok(orders)
     }
 
+    pub fn get_order_status(&self, id: i64) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_order_status_internal(&conn, id)
+    }
+
+    fn get_order_status_internal(conn: &Connection, id: i64) -> Result<Option<String>> {
+        let mut stmt = conn.prepare("SELECT status FROM orders WHERE id = ?1")?;
+        let mut rows = stmt.query_map(params![id], |row| row.get::<_, Option<String>>(0))?;
+
+        match rows.next() {
+            Some(status) => status,
+            None => Ok(None),
+        }
+    }
+
     fn get_order_items_internal(&self, conn: &Connection, order_id: i64) -> Result<Vec<OrderItem>> {
         let mut stmt = conn.prepare(
-            "SELECT product_id, name, price, quantity, category
-             FROM order_items WHERE order_id = ?1"
+            "SELECT product_id, name, price, quantity, category, kitchen_status, cost_price, modifiers, components,
+                    line_id, notes, unit, tax_rate
+             FROM order_items WHERE order_id = ?1 ORDER BY id"
         )?;
 
         let items = stmt.query_map(params![order_id], |row| {
+            let modifiers_json: Option<String> = row.get(7)?;
+            let components_json: Option<String> = row.get(8)?;
             Ok(OrderItem {
                 id: row.get(0)?,
                 name: row.get(1)?,
                 price: row.get(2)?,
                 quantity: row.get(3)?,
                 category: row.get(4)?,
+                kitchen_status: row.get(5)?,
+                cost_price: row.get(6)?,
+                modifiers: modifiers_json
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                components: components_json
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                line_id: row.get(9)?,
+                notes: row.get(10)?,
+                unit: SaleUnit::from_name(&row.get::<_, String>(11)?),
+                tax_rate: row.get(12)?,
             })
         })?.collect::<Result<Vec<_>>>()?;
 
         Ok(items)
     }
 
-    pub fn create_order(&self, order: &Order) -> Result<()> {
+    /// `order` with a line id on every item and the stored notes of the
+    /// lines sent without them. Run before comandas are created so they
+    /// refer to the same lines that are saved.
+    pub fn assign_line_ids(&self, order: &Order) -> Result<Order> {
         let conn = self.conn.lock().unwrap();
+        let previous = self.get_order_items_internal(&conn, order.id)?;
+        Ok(Order {
+            items: assign_line_ids(&previous, &order.items),
+            ..order.clone()
+        })
+    }
 
-        conn.execute(
-            "INSERT OR REPLACE INTO orders (id, date, total, change, total_paid, item_count,
-             table_number, payment_method, ticket_path, status)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-            params![
-                order.id,
-                order.date,
-                order.total,
-                order.change,
-                order.total_paid,
-                order.item_count,
-                order.table_number,
-                order.payment_method,
-                order.ticket_path,
-                order.status
-            ],
-        )?;
+    // The frontend doesn't send kitchen statuses or costs back, so keep the
+    // stored ones. New items take the current unit cost of the product and
+    // of the stock taken by its modifiers or bundle components.
+    // Item costs are taken when the order is saved until it is paid; from then
+    // on (`costs_frozen`) lines keep the cost they had, and only lines added
+    // afterwards are costed. Lines keep the IVA rate they were first saved with.
+    fn replace_order_items_internal(&self, conn: &Connection, order: &Order, costs_frozen: bool) -> Result<()> {
+        let previous = self.get_order_items_internal(conn, order.id)?;
+        // Orders saved through the commands already went through assign_line_ids
+        let items = if order.items.iter().all(|item| item.line_id.is_some()) {
+            order.items.clone()
+        } else {
+            assign_line_ids(&previous, &order.items)
+        };
+        let previous_statuses: HashMap<(i64, String), String> = previous
+            .iter()
+            .filter_map(|item| item.kitchen_status.clone().map(|status| (line_key(item), status)))
+            .collect();
+        let previous_costs: HashMap<(i64, String), f64> = previous
+            .iter()
+            .filter_map(|item| item.cost_price.map(|cost| (line_key(item), cost)))
+            .collect();
+        let previous_rates: HashMap<(i64, String), f64> = previous
+            .iter()
+            .filter_map(|item| item.tax_rate.map(|rate| (line_key(item), rate)))
+            .collect();
+        let unit_costs = Self::get_unit_costs_internal(conn)?;
+        let product_rates: HashMap<i64, f64> = {
+            let mut stmt = conn.prepare("SELECT id, tax_rate FROM products WHERE tax_rate IS NOT NULL")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
 
-        // Delete existing items and insert new ones
         conn.execute("DELETE FROM order_items WHERE order_id = ?1", params![order.id])?;
 
-        for item in &order.items {
+        for item in &items {
+            let kitchen_status = item.kitchen_status.clone()
+                .or_else(|| previous_statuses.get(&line_key(item)).cloned());
+            let cost_price = if costs_frozen {
+                previous_costs.get(&line_key(item)).copied()
+                    .or(item.cost_price)
+                    .or_else(|| recipe::item_unit_cost(item, &unit_costs))
+            } else {
+                recipe::item_unit_cost(item, &unit_costs)
+            };
+            let tax_rate = previous_rates.get(&line_key(item)).copied()
+                .or(item.tax_rate)
+                .or_else(|| product_rates.get(&item.id).copied());
+            let modifiers_json = (!item.modifiers.is_empty())
+                .then(|| serde_json::to_string(&item.modifiers).unwrap_or_default());
+            let components_json = (!item.components.is_empty())
+                .then(|| serde_json::to_string(&item.components).unwrap_or_default());
+
             conn.execute(
-                "INSERT INTO order_items (order_id, product_id, name, price, quantity, category)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                "INSERT INTO order_items (order_id, product_id, name, price, quantity, category, kitchen_status,
+                                          cost_price, modifiers, components, line_id, notes, unit, tax_rate)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
                 params![
                     order.id,
                     item.id,
                     item.name,
                     item.price,
                     item.quantity,
-                    item.category
+                    item.category,
+                    kitchen_status,
+                    cost_price,
+                    modifiers_json,
+                    components_json,
+                    item.line_id,
+                    item.notes,
+                    item.unit.as_str(),
+                    tax_rate
                 ],
             )?;
         }
@@ -318,10 +1194,60 @@ impl Database {
         Ok(())
     }
 
-    pub fn update_order(&self, order: &Order) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
+    /// Saves the order with the comandas for its new items and, once paid,
+    /// takes its items out of stock.
+    pub fn create_order(&self, order: &Order) -> Result<OrderSaved> {
+        self.create_order_internal(order, true)
+    }
 
-        conn.execute(
+    // Imported orders were already counted in the imported stock and sent to
+    // the kitchen
+    fn create_order_internal(&self, order: &Order, live: bool) -> Result<OrderSaved> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let mut saved = OrderSaved::default();
+        // Imported orders keep the costs they were exported with
+        let costs_frozen = !live || Self::get_order_status_internal(&tx, order.id)?.as_deref() == Some("paid");
+        if live {
+            saved.prebill_alert = self.check_prebill_modification_internal(&tx, order)?;
+            saved.comandas = self.create_comandas_internal(&tx, order)?;
+        }
+
+        tx.execute(
+            "INSERT OR REPLACE INTO orders (id, date, total, change, total_paid, item_count,
+             table_number, payment_method, ticket_path, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                order.id,
+                order.date,
+                order.total,
+                order.change,
+                order.total_paid,
+                order.item_count,
+                order.table_number,
+                order.payment_method,
+                order.ticket_path,
+                order.status
+            ],
+        )?;
+
+        // Delete existing items and insert new ones
+        self.replace_order_items_internal(&tx, order, costs_frozen)?;
+        if live {
+            saved.low_stock = Self::sync_order_stock_internal(&tx, order)?;
+        }
+        tx.commit()?;
+        Ok(saved)
+    }
+
+    pub fn update_order(&self, order: &Order) -> Result<OrderSaved> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let costs_frozen = Self::get_order_status_internal(&tx, order.id)?.as_deref() == Some("paid");
+        let prebill_alert = self.check_prebill_modification_internal(&tx, order)?;
+        let comandas = self.create_comandas_internal(&tx, order)?;
+
+        tx.execute(
             "UPDATE orders SET date = ?2, total = ?3, change = ?4, total_paid = ?5,
              item_count = ?6, table_number = ?7, payment_method = ?8, ticket_path = ?9, status = ?10
              WHERE id = ?1",
@@ -340,31 +1266,33 @@ impl Database {
         )?;
 
         // Delete existing items and insert new ones
-        conn.execute("DELETE FROM order_items WHERE order_id = ?1", params![order.id])?;
-
-        for item in &order.items {
-            conn.execute(
-                "INSERT INTO order_items (order_id, product_id, name, price, quantity, category)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-                params![
-                    order.id,
-                    item.id,
-                    item.name,
-                    item.price,
-                    item.quantity,
-                    item.category
-                ],
-            )?;
-        }
-
-        Ok(())
+        self.replace_order_items_internal(&tx, order, costs_frozen)?;
+        let low_stock = Self::sync_order_stock_internal(&tx, order)?;
+        tx.commit()?;
+        Ok(OrderSaved { comandas, low_stock, prebill_alert })
     }
 
-    pub fn delete_order(&self, id: i64) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("DELETE FROM order_items WHERE order_id = ?1", params![id])?;
-        conn.execute("DELETE FROM orders WHERE id = ?1", params![id])?;
-        Ok(())
+    /// Deletes the order, returning to stock whatever it had sold. Deleting a
+    /// pre-billed order raises an alert, returned for the caller to notify.
+    pub fn delete_order(&self, id: i64) -> Result<Option<PrebillAlert>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let alert = match Self::get_last_prebill_internal(&tx, id)? {
+            Some((prebill_total, _)) => Some(Self::create_prebill_alert_internal(
+                &tx,
+                id,
+                prebill_total,
+                None,
+                "Pedido eliminado tras imprimir la cuenta",
+            )?),
+            None => None,
+        };
+        Self::apply_order_stock_internal(&tx, id, &[])?;
+        tx.execute("DELETE FROM order_recipes WHERE order_id = ?1", params![id])?;
+        tx.execute("DELETE FROM order_items WHERE order_id = ?1", params![id])?;
+        tx.execute("DELETE FROM orders WHERE id = ?1", params![id])?;
+        tx.commit()?;
+        Ok(alert)
     }
 
     // ==================== Tables ====================
@@ -465,6 +1393,302 @@ impl Database {
         Ok(())
     }
 
+    // ==================== Customers ====================
+
+    pub fn get_customers(&self) -> Result<Vec<Customer>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, cif_nif, nombre_fiscal, nombre_comercial, direccion, codigo_postal,
+                    poblacion, telefono, email, activo, created_at, updated_at
+             FROM customers"
+        )?;
+
+        let customers = stmt.query_map([], |row| {
+            let activo: i32 = row.get(9)?;
+            Ok(Customer {
+                id: row.get(0)?,
+                cif_nif: row.get(1)?,
+                nombre_fiscal: row.get(2)?,
+                nombre_comercial: row.get(3)?,
+                direccion: row.get(4)?,
+                codigo_postal: row.get(5)?,
+                poblacion: row.get(6)?,
+                telefono: row.get(7)?,
+                email: row.get(8)?,
+                activo: activo != 0,
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(customers)
+    }
+
+    pub fn get_customer(&self, id: i64) -> Result<Option<Customer>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, cif_nif, nombre_fiscal, nombre_comercial, direccion, codigo_postal,
+                    poblacion, telefono, email, activo, created_at, updated_at
+             FROM customers WHERE id = ?1"
+        )?;
+
+        let mut rows = stmt.query_map(params![id], |row| {
+            let activo: i32 = row.get(9)?;
+            Ok(Customer {
+                id: row.get(0)?,
+                cif_nif: row.get(1)?,
+                nombre_fiscal: row.get(2)?,
+                nombre_comercial: row.get(3)?,
+                direccion: row.get(4)?,
+                codigo_postal: row.get(5)?,
+                poblacion: row.get(6)?,
+                telefono: row.get(7)?,
+                email: row.get(8)?,
+                activo: activo != 0,
+                created_at: row.get(10)?,
+                updated_at: row.get(11)?,
+            })
+        })?;
+
+        match rows.next() {
+            Some(customer) => Ok(Some(customer?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn create_customer(&self, customer: &Customer) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO customers (id, cif_nif, nombre_fiscal, nombre_comercial, direccion,
+             codigo_postal, poblacion, telefono, email, activo, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                customer.id,
+                customer.cif_nif,
+                customer.nombre_fiscal,
+                customer.nombre_comercial,
+                customer.direccion,
+                customer.codigo_postal,
+                customer.poblacion,
+                customer.telefono,
+                customer.email,
+                customer.activo as i32,
+                customer.created_at,
+                customer.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_customer(&self, customer: &Customer) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE customers SET cif_nif = ?2, nombre_fiscal = ?3, nombre_comercial = ?4,
+             direccion = ?5, codigo_postal = ?6, poblacion = ?7, telefono = ?8, email = ?9,
+             activo = ?10, updated_at = ?11
+             WHERE id = ?1",
+            params![
+                customer.id,
+                customer.cif_nif,
+                customer.nombre_fiscal,
+                customer.nombre_comercial,
+                customer.direccion,
+                customer.codigo_postal,
+                customer.poblacion,
+                customer.telefono,
+                customer.email,
+                customer.activo as i32,
+                customer.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_customer(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM customers WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ==================== Suppliers ====================
+
+    pub fn get_suppliers(&self) -> Result<Vec<Supplier>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, cif_nif, nombre_fiscal, nombre_comercial, direccion, telefono, email,
+                    contacto, activo, created_at, updated_at
+             FROM suppliers"
+        )?;
+
+        let suppliers = stmt.query_map([], |row| {
+            let activo: i32 = row.get(8)?;
+            Ok(Supplier {
+                id: row.get(0)?,
+                cif_nif: row.get(1)?,
+                nombre_fiscal: row.get(2)?,
+                nombre_comercial: row.get(3)?,
+                direccion: row.get(4)?,
+                telefono: row.get(5)?,
+                email: row.get(6)?,
+                contacto: row.get(7)?,
+                activo: activo != 0,
+                created_at: row.get(9)?,
+                updated_at: row.get(10)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(suppliers)
+    }
+
+    pub fn create_supplier(&self, supplier: &Supplier) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO suppliers (id, cif_nif, nombre_fiscal, nombre_comercial, direccion,
+             telefono, email, contacto, activo, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                supplier.id,
+                supplier.cif_nif,
+                supplier.nombre_fiscal,
+                supplier.nombre_comercial,
+                supplier.direccion,
+                supplier.telefono,
+                supplier.email,
+                supplier.contacto,
+                supplier.activo as i32,
+                supplier.created_at,
+                supplier.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn update_supplier(&self, supplier: &Supplier) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE suppliers SET cif_nif = ?2, nombre_fiscal = ?3, nombre_comercial = ?4,
+             direccion = ?5, telefono = ?6, email = ?7, contacto = ?8, activo = ?9, updated_at = ?10
+             WHERE id = ?1",
+            params![
+                supplier.id,
+                supplier.cif_nif,
+                supplier.nombre_fiscal,
+                supplier.nombre_comercial,
+                supplier.direccion,
+                supplier.telefono,
+                supplier.email,
+                supplier.contacto,
+                supplier.activo as i32,
+                supplier.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Deletes the supplier and unlinks its products.
+    pub fn delete_supplier(&self, id: i64) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("UPDATE products SET supplier_id = NULL WHERE supplier_id = ?1", params![id])?;
+        tx.execute("DELETE FROM suppliers WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    pub fn count_supplier_purchase_orders(&self, id: i64) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COUNT(*) FROM purchase_orders WHERE supplier_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+    }
+
+    // ==================== Business Profile ====================
+
+    pub fn get_business_profile(&self) -> Result<Option<BusinessProfile>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT nif, nombre_razon, nombre_comercial, direccion, codigo_postal, poblacion,
+                    provincia, telefono, email, serie_factura, tipo_factura, descripcion_operacion,
+                    updated_at
+             FROM business_profile WHERE id = 1"
+        )?;
+
+        let mut rows = stmt.query_map([], |row| {
+            Ok(BusinessProfile {
+                nif: row.get(0)?,
+                nombre_razon: row.get(1)?,
+                nombre_comercial: row.get(2)?,
+                direccion: row.get(3)?,
+                codigo_postal: row.get(4)?,
+                poblacion: row.get(5)?,
+                provincia: row.get(6)?,
+                telefono: row.get(7)?,
+                email: row.get(8)?,
+                serie_factura: row.get(9)?,
+                tipo_factura: row.get(10)?,
+                descripcion_operacion: row.get(11)?,
+                updated_at: row.get(12)?,
+            })
+        })?;
+
+        match rows.next() {
+            Some(profile) => Ok(Some(profile?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_business_profile(&self, profile: &BusinessProfile) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO business_profile (id, nif, nombre_razon, nombre_comercial,
+             direccion, codigo_postal, poblacion, provincia, telefono, email, serie_factura,
+             tipo_factura, descripcion_operacion, updated_at)
+             VALUES (1, ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                profile.nif,
+                profile.nombre_razon,
+                profile.nombre_comercial,
+                profile.direccion,
+                profile.codigo_postal,
+                profile.poblacion,
+                profile.provincia,
+                profile.telefono,
+                profile.email,
+                profile.serie_factura,
+                profile.tipo_factura,
+                profile.descripcion_operacion,
+                profile.updated_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    // Format: SERIE + YYYY-NNNNNN (e.g. TPV-2024-000001), same as the frontend builder
+    /// `issued` is the highest number already issued elsewhere for the series
+    /// and year (e.g. by the old browser counter), so the series never goes back.
+    pub fn next_invoice_number(&self, serie: &str, year: i32, consume: bool, issued: i64) -> Result<String> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let last: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(last_number), 0) FROM invoice_counters WHERE serie = ?1 AND year = ?2",
+            params![serie, year],
+            |row| row.get(0),
+        )?;
+        let next = last.max(issued) + 1;
+
+        if consume {
+            tx.execute(
+                "INSERT OR REPLACE INTO invoice_counters (serie, year, last_number) VALUES (?1, ?2, ?3)",
+                params![serie, year, next],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(format!("{}{}-{:06}", serie, year, next))
+    }
+
     // ==================== Utility ====================
 
     pub fn export_data(&self) -> Result<ExportData> {
@@ -474,13 +1698,15 @@ impl Database {
             orders: self.get_orders()?,
             tables: self.get_tables()?,
             users: self.get_users()?,
+            customers: self.get_customers()?,
+            business_profile: self.get_business_profile()?,
         })
     }
 
     pub fn import_data(&self, data: &ImportData) -> Result<()> {
         // Import products
         for product in &data.products {
-            self.create_product(product)?;
+            self.create_product_internal(product, false)?;
         }
 
         // Import categories
@@ -490,7 +1716,7 @@ impl Database {
 
         // Import orders
         for order in &data.orders {
-            self.create_order(order)?;
+            self.create_order_internal(order, false)?;
         }
 
         // Import tables if provided
@@ -507,6 +1733,18 @@ impl Database {
             }
         }
 
+        // Import customers if provided
+        if let Some(customers) = &data.customers {
+            for customer in customers {
+                self.create_customer(customer)?;
+            }
+        }
+
+        // Import business profile if provided
+        if let Some(profile) = &data.business_profile {
+            self.save_business_profile(profile)?;
+        }
+
         Ok(())
     }
 
@@ -520,6 +1758,29 @@ impl Database {
             DELETE FROM categories;
             DELETE FROM tables;
             DELETE FROM users;
+            DELETE FROM customers;
+            DELETE FROM comanda_prints;
+            DELETE FROM comanda_items;
+            DELETE FROM comandas;
+            DELETE FROM prebill_prints;
+            DELETE FROM prebill_alerts;
+            DELETE FROM stock_movements;
+            DELETE FROM stocktake_counts;
+            DELETE FROM stocktakes;
+            DELETE FROM purchase_order_lines;
+            DELETE FROM purchase_orders;
+            DELETE FROM suppliers;
+            DELETE FROM recipe_items;
+            DELETE FROM order_recipes;
+            DELETE FROM product_costs;
+            DELETE FROM stock_lot_allocations;
+            DELETE FROM stock_lots;
+            DELETE FROM product_modifier_groups;
+            DELETE FROM modifier_options;
+            DELETE FROM modifier_groups;
+            DELETE FROM bundle_slot_routes;
+            DELETE FROM bundle_slots;
+            DELETE FROM product_barcodes;
             "
         )?;
         Ok(())
@@ -584,4 +1845,2277 @@ impl Database {
         conn.execute("DELETE FROM licenses", [])?;
         Ok(())
     }
+
+    // ==================== Certificates ====================
+
+    pub fn save_certificate(&self, cert: &CertificateInfo, file_name: &str, encrypted_password: &str) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO certificates (alias, format, certificate_type, subject, issuer, serial_number,
+             fingerprint, nif, not_before, not_after, imported_at, file_name, encrypted_password)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            params![
+                cert.alias,
+                cert.format,
+                cert.certificate_type,
+                cert.subject,
+                cert.issuer,
+                cert.serial_number,
+                cert.fingerprint,
+                cert.nif,
+                cert.not_before,
+                cert.not_after,
+                cert.imported_at,
+                file_name,
+                encrypted_password
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn get_certificates(&self) -> Result<Vec<CertificateInfo>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, alias, format, certificate_type, subject, issuer, serial_number, fingerprint,
+                    nif, not_before, not_after, imported_at
+             FROM certificates ORDER BY not_after"
+        )?;
+
+        let certificates = stmt.query_map([], |row| {
+            Ok(CertificateInfo {
+                id: row.get(0)?,
+                alias: row.get(1)?,
+                format: row.get(2)?,
+                certificate_type: row.get(3)?,
+                subject: row.get(4)?,
+                issuer: row.get(5)?,
+                serial_number: row.get(6)?,
+                fingerprint: row.get(7)?,
+                nif: row.get(8)?,
+                not_before: row.get(9)?,
+                not_after: row.get(10)?,
+                imported_at: row.get(11)?,
+                days_remaining: None,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(certificates)
+    }
+
+    /// Returns the stored file name and the hex-encoded encrypted password
+    pub fn get_certificate_secrets(&self, id: i64) -> Result<Option<(String, String)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT file_name, encrypted_password FROM certificates WHERE id = ?1"
+        )?;
+
+        let mut rows = stmt.query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        match rows.next() {
+            Some(secrets) => Ok(Some(secrets?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn delete_certificate(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM certificates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ==================== Stations ====================
+
+    pub fn get_stations(&self) -> Result<Vec<Station>> {
+        let conn = self.conn.lock().unwrap();
+        self.get_stations_internal(&conn)
+    }
+
+    fn get_stations_internal(&self, conn: &Connection) -> Result<Vec<Station>> {
+        let mut stmt = conn.prepare("SELECT id, name, printer_interface, active FROM stations")?;
+
+        let mut stations = stmt.query_map([], |row| {
+            let active: i32 = row.get(3)?;
+            Ok(Station {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                printer_interface: row.get(2)?,
+                active: active != 0,
+                categories: Vec::new(),
+                product_ids: Vec::new(),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT category, product_id FROM station_routes WHERE station_id = ?1 ORDER BY id"
+        )?;
+        for station in &mut stations {
+            let routes = stmt.query_map(params![station.id], |row| {
+                Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<i64>>(1)?))
+            })?.collect::<Result<Vec<_>>>()?;
+
+            for (category, product_id) in routes {
+                if let Some(category) = category {
+                    station.categories.push(category);
+                }
+                if let Some(product_id) = product_id {
+                    station.product_ids.push(product_id);
+                }
+            }
+        }
+
+        Ok(stations)
+    }
+
+    fn replace_station_routes_internal(&self, conn: &Connection, station: &Station) -> Result<()> {
+        conn.execute("DELETE FROM station_routes WHERE station_id = ?1", params![station.id])?;
+
+        for category in &station.categories {
+            conn.execute(
+                "INSERT INTO station_routes (station_id, category) VALUES (?1, ?2)",
+                params![station.id, category],
+            )?;
+        }
+        for product_id in &station.product_ids {
+            conn.execute(
+                "INSERT INTO station_routes (station_id, product_id) VALUES (?1, ?2)",
+                params![station.id, product_id],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn create_station(&self, station: &Station) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO stations (id, name, printer_interface, active) VALUES (?1, ?2, ?3, ?4)",
+            params![station.id, station.name, station.printer_interface, station.active as i32],
+        )?;
+        self.replace_station_routes_internal(&conn, station)
+    }
+
+    pub fn update_station(&self, station: &Station) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE stations SET name = ?2, printer_interface = ?3, active = ?4 WHERE id = ?1",
+            params![station.id, station.name, station.printer_interface, station.active as i32],
+        )?;
+        self.replace_station_routes_internal(&conn, station)
+    }
+
+    pub fn delete_station(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM station_routes WHERE station_id = ?1", params![id])?;
+        conn.execute("DELETE FROM stations WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ==================== Comandas ====================
+
+    // Compares `order` with the stored version and records one comanda per
+    // station for the items added or removed. Runs inside the order save
+    // transaction, before the items are replaced.
+    fn create_comandas_internal(&self, conn: &Connection, order: &Order) -> Result<Vec<Comanda>> {
+        // The kitchen prepares the products inside bundles, not the bundles
+        let previous = bundle::expand_items(&self.get_order_items_internal(conn, order.id)?);
+        let deltas = compute_delta(&previous, &bundle::expand_items(&order.items));
+        if deltas.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let stations = self.get_stations_internal(conn)?;
+        let product_categories: HashMap<i64, String> = {
+            let mut stmt = conn.prepare("SELECT id, category FROM products")?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
+        let routed = route_items(&deltas, &stations, &product_categories);
+
+        let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let mut comandas = Vec::new();
+
+        for (station_id, items) in routed {
+            let station_name = stations.iter()
+                .find(|s| s.id == station_id)
+                .map(|s| s.name.clone())
+                .unwrap_or_default();
+
+            conn.execute(
+                "INSERT INTO comandas (order_id, station_id, station_name, table_number, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![order.id, station_id, station_name, order.table_number, created_at],
+            )?;
+            let comanda_id = conn.last_insert_rowid();
+
+            let mut items = items;
+            for item in &mut items {
+                conn.execute(
+                    "INSERT INTO comanda_items (comanda_id, product_id, name, quantity, status, modifiers, line_id, notes, unit)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        comanda_id,
+                        item.product_id,
+                        item.name,
+                        item.quantity,
+                        item.status,
+                        (!item.modifiers.is_empty()).then(|| serde_json::to_string(&item.modifiers).unwrap_or_default()),
+                        item.line_id,
+                        item.notes,
+                        item.unit.as_str()
+                    ],
+                )?;
+                item.id = conn.last_insert_rowid();
+            }
+
+            comandas.push(Comanda {
+                id: comanda_id,
+                order_id: order.id,
+                station_id,
+                station_name,
+                table_number: order.table_number,
+                created_at: created_at.clone(),
+                items,
+                prints: Vec::new(),
+            });
+        }
+
+        Ok(comandas)
+    }
+
+    pub fn get_comandas(&self, order_id: Option<i64>) -> Result<Vec<Comanda>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, order_id, station_id, station_name, table_number, created_at
+             FROM comandas WHERE ?1 IS NULL OR order_id = ?1 ORDER BY id"
+        )?;
+
+        let mut comandas = stmt.query_map(params![order_id], |row| {
+            Ok(Comanda {
+                id: row.get(0)?,
+                order_id: row.get(1)?,
+                station_id: row.get(2)?,
+                station_name: row.get(3)?,
+                table_number: row.get(4)?,
+                created_at: row.get(5)?,
+                items: Vec::new(),
+                prints: Vec::new(),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        for comanda in &mut comandas {
+            comanda.items = self.get_comanda_items_internal(&conn, comanda.id)?;
+            comanda.prints = self.get_comanda_prints_internal(&conn, comanda.id)?;
+        }
+
+        Ok(comandas)
+    }
+
+    pub fn get_comanda(&self, id: i64) -> Result<Option<Comanda>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, order_id, station_id, station_name, table_number, created_at
+             FROM comandas WHERE id = ?1"
+        )?;
+
+        let mut rows = stmt.query_map(params![id], |row| {
+            Ok(Comanda {
+                id: row.get(0)?,
+                order_id: row.get(1)?,
+                station_id: row.get(2)?,
+                station_name: row.get(3)?,
+                table_number: row.get(4)?,
+                created_at: row.get(5)?,
+                items: Vec::new(),
+                prints: Vec::new(),
+            })
+        })?;
+
+        match rows.next() {
+            Some(comanda) => {
+                let mut comanda = comanda?;
+                comanda.items = self.get_comanda_items_internal(&conn, comanda.id)?;
+                comanda.prints = self.get_comanda_prints_internal(&conn, comanda.id)?;
+                Ok(Some(comanda))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_comanda_items_internal(&self, conn: &Connection, comanda_id: i64) -> Result<Vec<ComandaItem>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, name, quantity, status, modifiers, line_id, notes, unit
+             FROM comanda_items WHERE comanda_id = ?1 ORDER BY id"
+        )?;
+
+        let items = stmt.query_map(params![comanda_id], |row| {
+            let modifiers_json: Option<String> = row.get(5)?;
+            Ok(ComandaItem {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                quantity: row.get(3)?,
+                status: row.get(4)?,
+                modifiers: modifiers_json
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                line_id: row.get(6)?,
+                notes: row.get(7)?,
+                unit: SaleUnit::from_name(&row.get::<_, String>(8)?),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(items)
+    }
+
+    fn get_comanda_prints_internal(&self, conn: &Connection, comanda_id: i64) -> Result<Vec<ComandaPrint>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, comanda_id, printed_at, reprint, error FROM comanda_prints WHERE comanda_id = ?1 ORDER BY id"
+        )?;
+
+        let prints = stmt.query_map(params![comanda_id], |row| {
+            let reprint: i32 = row.get(3)?;
+            Ok(ComandaPrint {
+                id: row.get(0)?,
+                comanda_id: row.get(1)?,
+                printed_at: row.get(2)?,
+                reprint: reprint != 0,
+                error: row.get(4)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(prints)
+    }
+
+    pub fn record_comanda_print(&self, comanda_id: i64, reprint: bool, error: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let printed_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO comanda_prints (comanda_id, printed_at, reprint, error) VALUES (?1, ?2, ?3, ?4)",
+            params![comanda_id, printed_at, reprint as i32, error],
+        )?;
+        Ok(())
+    }
+
+    /// Comandas with items still to be served, oldest first
+    pub fn get_open_comandas(&self, station_id: Option<i64>) -> Result<Vec<Comanda>> {
+        let ids: Vec<i64> = {
+            let conn = self.conn.lock().unwrap();
+            let mut stmt = conn.prepare(
+                "SELECT DISTINCT c.id FROM comandas c
+                 JOIN comanda_items ci ON ci.comanda_id = c.id
+                 WHERE (?1 IS NULL OR c.station_id = ?1) AND ci.quantity > 0 AND ci.status != 'served'
+                 ORDER BY c.id"
+            )?;
+            let rows = stmt.query_map(params![station_id], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        let mut comandas = Vec::new();
+        for id in ids {
+            if let Some(comanda) = self.get_comanda(id)? {
+                comandas.push(comanda);
+            }
+        }
+        Ok(comandas)
+    }
+
+    /// Updates one item of a comanda (or all of them when `comanda_item_id` is
+    /// None) and mirrors the status onto the matching order items.
+    pub fn set_kitchen_status(&self, comanda_id: i64, comanda_item_id: Option<i64>, status: &str) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let updated_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "UPDATE comanda_items SET status = ?3, updated_at = ?4
+             WHERE comanda_id = ?1 AND (?2 IS NULL OR id = ?2) AND quantity > 0",
+            params![comanda_id, comanda_item_id, status, updated_at],
+        )?;
+        // Comanda items from before line ids existed match by product
+        tx.execute(
+            "UPDATE order_items SET kitchen_status = ?3
+             WHERE order_id = (SELECT order_id FROM comandas WHERE id = ?1)
+               AND (line_id IN (
+                        SELECT line_id FROM comanda_items
+                        WHERE comanda_id = ?1 AND (?2 IS NULL OR id = ?2) AND quantity > 0
+                    )
+                    OR product_id IN (
+                        SELECT product_id FROM comanda_items
+                        WHERE comanda_id = ?1 AND (?2 IS NULL OR id = ?2) AND quantity > 0 AND line_id IS NULL
+                    ))",
+            params![comanda_id, comanda_item_id, status],
+        )?;
+
+        tx.commit()
+    }
+
+    // ==================== Receipt Templates ====================
+
+    pub fn get_templates(&self) -> Result<Vec<ReceiptTemplate>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, kind, printer_name, content, updated_at
+             FROM receipt_templates ORDER BY kind, name"
+        )?;
+
+        let templates = stmt.query_map([], |row| {
+            Ok(ReceiptTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                kind: row.get(2)?,
+                printer_name: row.get(3)?,
+                content: row.get(4)?,
+                updated_at: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(templates)
+    }
+
+    /// Template content for a printer, falling back to the shared template of
+    /// the same kind. None means the built-in default applies.
+    pub fn get_template_for(&self, kind: &str, printer_name: Option<&str>) -> Result<Option<String>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT content FROM receipt_templates
+             WHERE kind = ?1 AND (printer_name IS NULL OR printer_name = ?2)
+             ORDER BY printer_name IS NULL, updated_at DESC
+             LIMIT 1"
+        )?;
+
+        let mut rows = stmt.query_map(params![kind, printer_name], |row| row.get(0))?;
+
+        match rows.next() {
+            Some(content) => Ok(Some(content?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn save_template(&self, template: &ReceiptTemplate) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        let updated_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+        if template.id > 0 {
+            conn.execute(
+                "UPDATE receipt_templates SET name = ?2, kind = ?3, printer_name = ?4, content = ?5, updated_at = ?6
+                 WHERE id = ?1",
+                params![
+                    template.id,
+                    template.name,
+                    template.kind,
+                    template.printer_name,
+                    template.content,
+                    updated_at
+                ],
+            )?;
+            return Ok(template.id);
+        }
+
+        conn.execute(
+            "INSERT INTO receipt_templates (name, kind, printer_name, content, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                template.name,
+                template.kind,
+                template.printer_name,
+                template.content,
+                updated_at
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn delete_template(&self, id: i64) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM receipt_templates WHERE id = ?1", params![id])?;
+        Ok(())
+    }
+
+    // ==================== Cash Drawer ====================
+
+    pub fn record_cash_drawer_event(&self, user_id: Option<i64>, reason: &str, order_id: Option<i64>, error: Option<&str>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let opened_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO cash_drawer_events (opened_at, user_id, reason, order_id, success, error)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![opened_at, user_id, reason, order_id, error.is_none() as i32, error],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_cash_drawer_events(&self, limit: i64) -> Result<Vec<CashDrawerEvent>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT e.id, e.opened_at, e.user_id, u.name, e.reason, e.order_id, e.success, e.error
+             FROM cash_drawer_events e
+             LEFT JOIN users u ON u.id = e.user_id
+             ORDER BY e.id DESC
+             LIMIT ?1"
+        )?;
+
+        let events = stmt.query_map(params![limit], |row| {
+            Ok(CashDrawerEvent {
+                id: row.get(0)?,
+                opened_at: row.get(1)?,
+                user_id: row.get(2)?,
+                user_name: row.get(3)?,
+                reason: row.get(4)?,
+                order_id: row.get(5)?,
+                success: row.get::<_, i32>(6)? != 0,
+                error: row.get(7)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(events)
+    }
+
+    // ==================== Pre-bills ====================
+
+    pub fn record_prebill_print(&self, order: &Order, user_id: Option<i64>) -> Result<PrebillInfo> {
+        {
+            let conn = self.conn.lock().unwrap();
+            let printed_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let items = serde_json::to_string(&order.items).unwrap_or_else(|_| "[]".to_string());
+            conn.execute(
+                "INSERT INTO prebill_prints (order_id, printed_at, user_id, total, items) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![order.id, printed_at, user_id, order.total, items],
+            )?;
+        }
+        self.get_prebill_info(order.id)
+    }
+
+    pub fn get_prebill_info(&self, order_id: i64) -> Result<PrebillInfo> {
+        let conn = self.conn.lock().unwrap();
+        let (print_count, last_printed_at): (i64, Option<String>) = conn.query_row(
+            "SELECT COUNT(*), MAX(printed_at) FROM prebill_prints WHERE order_id = ?1",
+            params![order_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        // Compares contents rather than timestamps, so an edit in the same
+        // second as the print still counts
+        let last_prebill = Self::get_last_prebill_internal(&conn, order_id)?;
+        let modified_after_print = match &last_prebill {
+            Some((prebill_total, prebill_items)) => match self.get_stored_order_internal(&conn, order_id)? {
+                Some((items, total)) => describe_changes(prebill_items, *prebill_total, &items, total).is_some(),
+                None => false,
+            },
+            None => false,
+        };
+
+        Ok(PrebillInfo {
+            order_id,
+            print_count,
+            last_printed_at,
+            last_total: last_prebill.map(|(total, _)| total),
+            modified_after_print,
+        })
+    }
+
+    // Total and items of the last pre-bill printed for the order
+    fn get_last_prebill_internal(conn: &Connection, order_id: i64) -> Result<Option<(f64, Vec<OrderItem>)>> {
+        let mut stmt = conn.prepare(
+            "SELECT total, items FROM prebill_prints WHERE order_id = ?1 ORDER BY id DESC LIMIT 1"
+        )?;
+        let mut rows = stmt.query_map(params![order_id], |row| {
+            Ok((row.get::<_, f64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        match rows.next() {
+            Some(prebill) => {
+                let (total, items) = prebill?;
+                Ok(Some((total, serde_json::from_str(&items).unwrap_or_default())))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Items and total of the order as currently saved
+    fn get_stored_order_internal(&self, conn: &Connection, order_id: i64) -> Result<Option<(Vec<OrderItem>, f64)>> {
+        let total: Option<f64> = conn.query_row(
+            "SELECT total FROM orders WHERE id = ?1",
+            params![order_id],
+            |row| row.get(0),
+        ).ok();
+        match total {
+            Some(total) => Ok(Some((self.get_order_items_internal(conn, order_id)?, total))),
+            None => Ok(None),
+        }
+    }
+
+    // Runs inside the order transaction, before the order is written: flags
+    // changes to a pre-billed order that make it differ from the last
+    // printed pre-bill.
+    fn check_prebill_modification_internal(&self, conn: &Connection, order: &Order) -> Result<Option<PrebillAlert>> {
+        let Some((prebill_total, prebill_items)) = Self::get_last_prebill_internal(conn, order.id)? else {
+            return Ok(None);
+        };
+
+        // Saving the order unchanged (e.g. to mark it as paid) is not a modification
+        if let Some((stored_items, stored_total)) = self.get_stored_order_internal(conn, order.id)? {
+            if describe_changes(&stored_items, stored_total, &order.items, order.total).is_none() {
+                return Ok(None);
+            }
+        }
+
+        match describe_changes(&prebill_items, prebill_total, &order.items, order.total) {
+            Some(details) => Self::create_prebill_alert_internal(conn, order.id, prebill_total, Some(order.total), &details).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn create_prebill_alert_internal(
+        conn: &Connection,
+        order_id: i64,
+        prebill_total: f64,
+        new_total: Option<f64>,
+        details: &str,
+    ) -> Result<PrebillAlert> {
+        let detected_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO prebill_alerts (order_id, detected_at, prebill_total, new_total, details)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![order_id, detected_at, prebill_total, new_total, details],
+        )?;
+
+        Ok(PrebillAlert {
+            id: conn.last_insert_rowid(),
+            order_id,
+            detected_at,
+            prebill_total,
+            new_total,
+            details: details.to_string(),
+            reviewed: false,
+            reviewed_by: None,
+            reviewed_at: None,
+        })
+    }
+
+    pub fn get_prebill_alerts(&self, reviewed: Option<bool>) -> Result<Vec<PrebillAlert>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, order_id, detected_at, prebill_total, new_total, details, reviewed, reviewed_by, reviewed_at
+             FROM prebill_alerts
+             WHERE ?1 IS NULL OR reviewed = ?1
+             ORDER BY id DESC"
+        )?;
+
+        let alerts = stmt.query_map(params![reviewed.map(|r| r as i32)], |row| {
+            Ok(PrebillAlert {
+                id: row.get(0)?,
+                order_id: row.get(1)?,
+                detected_at: row.get(2)?,
+                prebill_total: row.get(3)?,
+                new_total: row.get(4)?,
+                details: row.get(5)?,
+                reviewed: row.get::<_, i32>(6)? != 0,
+                reviewed_by: row.get(7)?,
+                reviewed_at: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(alerts)
+    }
+
+    pub fn review_prebill_alert(&self, id: i64, user_id: Option<i64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let reviewed_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "UPDATE prebill_alerts SET reviewed = 1, reviewed_by = ?2, reviewed_at = ?3 WHERE id = ?1",
+            params![id, user_id, reviewed_at],
+        )?;
+        Ok(())
+    }
+
+    // ==================== Stock ====================
+
+    fn get_stock_internal(conn: &Connection, product_id: i64) -> Result<f64> {
+        let mut stmt = conn.prepare("SELECT COALESCE(stock, 0) FROM products WHERE id = ?1")?;
+        let mut rows = stmt.query_map(params![product_id], |row| row.get::<_, f64>(0))?;
+
+        match rows.next() {
+            Some(stock) => stock,
+            None => Ok(0.0),
+        }
+    }
+
+    /// Inserts the movement and updates the product stock. Returns the
+    /// movement id and the alert raised if the stock fell to its minimum.
+    // Losses keep the cost of the moment so later cost changes don't alter them
+    fn insert_stock_movement_internal(conn: &Connection, movement: &NewStockMovement) -> Result<(i64, Option<LowStockAlert>)> {
+        let previous_stock = Self::get_stock_internal(conn, movement.product_id)?;
+        let unit_cost: Option<f64> = if is_loss(&movement.movement_type) {
+            let mut stmt = conn.prepare("SELECT cost_price FROM products WHERE id = ?1")?;
+            let cost: Option<Option<f64>> = stmt.query_map(params![movement.product_id], |row| row.get(0))?.next().transpose()?;
+            cost.flatten()
+        } else {
+            None
+        };
+        let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, movement_type, quantity, order_id, user_id, reason,
+                                          reason_code, unit_cost, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                movement.product_id,
+                movement.movement_type,
+                movement.quantity,
+                movement.order_id,
+                movement.user_id,
+                movement.reason,
+                movement.reason_code,
+                unit_cost,
+                created_at
+            ],
+        )?;
+        let id = conn.last_insert_rowid();
+
+        conn.execute(
+            "UPDATE products SET stock = COALESCE(stock, 0) + ?2 WHERE id = ?1",
+            params![movement.product_id, movement.quantity],
+        )?;
+        Self::allocate_lots_internal(conn, id, movement)?;
+
+        let alert = Self::check_low_stock_internal(conn, movement.product_id, previous_stock)?;
+        Ok((id, alert))
+    }
+
+    // A new or imported product sets the stock directly: log the difference
+    // as an adjustment
+    fn record_stock_edit_internal(
+        conn: &Connection,
+        product_id: i64,
+        difference: f64,
+        reason: &str,
+        previous_stock: f64,
+    ) -> Result<Option<LowStockAlert>> {
+        if difference == 0.0 {
+            return Ok(None);
+        }
+
+        let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO stock_movements (product_id, movement_type, quantity, reason, created_at)
+             VALUES (?1, 'adjustment', ?2, ?3, ?4)",
+            params![product_id, difference, reason, created_at],
+        )?;
+        Self::check_low_stock_internal(conn, product_id, previous_stock)
+    }
+
+    // Persists an alert when the product just reached its minimum stock
+    fn check_low_stock_internal(conn: &Connection, product_id: i64, previous_stock: f64) -> Result<Option<LowStockAlert>> {
+        let product = {
+            let mut stmt = conn.prepare(
+                "SELECT name, COALESCE(stock, 0), min_stock FROM products WHERE id = ?1"
+            )?;
+            let mut rows = stmt.query_map(params![product_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?, row.get::<_, Option<f64>>(2)?))
+            })?;
+            match rows.next() {
+                Some(product) => product?,
+                None => return Ok(None),
+            }
+        };
+
+        let (product_name, stock, min_stock) = match product {
+            (name, stock, Some(min_stock)) if crossed_minimum(previous_stock, stock, min_stock) => (name, stock, min_stock),
+            _ => return Ok(None),
+        };
+
+        let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO low_stock_alerts (product_id, product_name, stock, min_stock, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![product_id, product_name, stock, min_stock, created_at],
+        )?;
+
+        Ok(Some(LowStockAlert {
+            id: conn.last_insert_rowid(),
+            product_id,
+            product_name,
+            stock,
+            min_stock,
+            created_at,
+            acknowledged: false,
+            acknowledged_by: None,
+            acknowledged_at: None,
+        }))
+    }
+
+    fn sync_order_stock_internal(conn: &Connection, order: &Order) -> Result<Vec<LowStockAlert>> {
+        let items = if consumes_stock(&order.status) { &order.items[..] } else { &[] };
+        Self::apply_order_stock_internal(conn, order.id, items)
+    }
+
+    /// Brings the sale movements of an order in line with `items`: sales for
+    /// what is new, returns for what was sold and is no longer there.
+    /// Recipes are applied as they were when the order first sold the product.
+    fn apply_order_stock_internal(conn: &Connection, order_id: i64, items: &[OrderItem]) -> Result<Vec<LowStockAlert>> {
+        let mut applied: HashMap<i64, f64> = {
+            let mut stmt = conn.prepare(
+                "SELECT product_id, SUM(quantity) FROM stock_movements
+                 WHERE order_id = ?1 AND movement_type IN ('sale', 'return')
+                 GROUP BY product_id"
+            )?;
+            let rows = stmt.query_map(params![order_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
+
+        // Products with a recipe consume their ingredients instead
+        let recipes = Self::get_order_recipes_internal(conn, order_id, items)?;
+        let target: HashMap<i64, f64> = recipe::consumption(items, &recipes)
+            .into_iter()
+            .map(|(product_id, quantity)| (product_id, -quantity))
+            .collect();
+
+        let mut product_ids: Vec<i64> = applied.keys().chain(target.keys()).copied().collect();
+        product_ids.sort_unstable();
+        product_ids.dedup();
+
+        let mut alerts = Vec::new();
+        for product_id in product_ids {
+            let change = target.get(&product_id).copied().unwrap_or(0.0)
+                - applied.remove(&product_id).unwrap_or(0.0);
+            if change == 0.0 {
+                continue;
+            }
+            let exists: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM products WHERE id = ?1)",
+                params![product_id],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                continue;
+            }
+
+            let movement = NewStockMovement {
+                product_id,
+                movement_type: if change < 0.0 { "sale" } else { "return" }.to_string(),
+                quantity: change,
+                reason: None,
+                reason_code: None,
+                user_id: None,
+                order_id: Some(order_id),
+            };
+            let (_, alert) = Self::insert_stock_movement_internal(conn, &movement)?;
+            alerts.extend(alert);
+        }
+
+        Ok(alerts)
+    }
+
+    // Recipes of the products sold by `items`, snapshotting the current one
+    // for products the order had not sold before
+    fn get_order_recipes_internal(conn: &Connection, order_id: i64, items: &[OrderItem]) -> Result<HashMap<i64, Vec<RecipeItem>>> {
+        let mut recipes: HashMap<i64, Vec<RecipeItem>> = {
+            let mut stmt = conn.prepare("SELECT product_id, ingredients FROM order_recipes WHERE order_id = ?1")?;
+            let rows = stmt.query_map(params![order_id], |row| {
+                let ingredients: String = row.get(1)?;
+                Ok((row.get(0)?, serde_json::from_str(&ingredients).unwrap_or_default()))
+            })?;
+            rows.collect::<Result<HashMap<_, _>>>()?
+        };
+
+        let sold: Vec<i64> = recipe::sold_products(items)
+            .into_iter()
+            .filter(|product_id| !recipes.contains_key(product_id))
+            .collect();
+        if sold.is_empty() {
+            return Ok(recipes);
+        }
+
+        let mut current = Self::get_recipes_internal(conn)?;
+        for product_id in sold {
+            let recipe = current.remove(&product_id).unwrap_or_default();
+            let ingredients = serde_json::to_string(&recipe).unwrap_or_else(|_| "[]".to_string());
+            conn.execute(
+                "INSERT INTO order_recipes (order_id, product_id, ingredients) VALUES (?1, ?2, ?3)",
+                params![order_id, product_id, ingredients],
+            )?;
+            recipes.insert(product_id, recipe);
+        }
+        Ok(recipes)
+    }
+
+    /// Records a movement entered by hand. `quantity` must already carry its
+    /// sign. Waste and comp of a product with a recipe are booked on its
+    /// ingredients, one movement each.
+    pub fn record_stock_movement(&self, movement: &NewStockMovement) -> Result<(Vec<i64>, Vec<LowStockAlert>)> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let mut movements = vec![movement.clone()];
+        if is_loss(&movement.movement_type) {
+            let recipes = Self::get_recipes_internal(&tx)?;
+            if recipes.get(&movement.product_id).is_some_and(|recipe| !recipe.is_empty()) {
+                let product_name: String = tx.query_row(
+                    "SELECT name FROM products WHERE id = ?1",
+                    params![movement.product_id],
+                    |row| row.get(0),
+                )?;
+                let reason = match &movement.reason {
+                    Some(reason) => format!("{}: {}", product_name, reason),
+                    None => product_name,
+                };
+                movements = recipe::expand(movement.product_id, movement.quantity, &recipes)
+                    .into_iter()
+                    .map(|(product_id, quantity)| NewStockMovement {
+                        product_id,
+                        quantity,
+                        reason: Some(reason.clone()),
+                        ..movement.clone()
+                    })
+                    .collect();
+            }
+        }
+
+        let mut ids = Vec::new();
+        let mut alerts = Vec::new();
+        for movement in &movements {
+            let (id, alert) = Self::insert_stock_movement_internal(&tx, movement)?;
+            ids.push(id);
+            alerts.extend(alert);
+        }
+        tx.commit()?;
+        Ok((ids, alerts))
+    }
+
+    pub fn get_stock_movements(&self, product_id: Option<i64>, limit: i64) -> Result<Vec<StockMovement>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.product_id, p.name, m.movement_type, m.quantity, m.order_id, m.user_id, u.name,
+                    m.reason, m.created_at, m.reason_code, m.unit_cost
+             FROM stock_movements m
+             LEFT JOIN products p ON p.id = m.product_id
+             LEFT JOIN users u ON u.id = m.user_id
+             WHERE ?1 IS NULL OR m.product_id = ?1
+             ORDER BY m.id DESC
+             LIMIT ?2"
+        )?;
+
+        let movements = stmt.query_map(params![product_id, limit], Self::stock_movement_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(movements)
+    }
+
+    fn stock_movement_from_row(row: &rusqlite::Row) -> Result<StockMovement> {
+        Ok(StockMovement {
+            id: row.get(0)?,
+            product_id: row.get(1)?,
+            product_name: row.get(2)?,
+            movement_type: row.get(3)?,
+            quantity: row.get(4)?,
+            order_id: row.get(5)?,
+            user_id: row.get(6)?,
+            user_name: row.get(7)?,
+            reason: row.get(8)?,
+            created_at: row.get(9)?,
+            reason_code: row.get(10)?,
+            unit_cost: row.get(11)?,
+        })
+    }
+
+    /// Waste and comp movements recorded between `from` and `to` (inclusive, YYYY-MM-DD).
+    pub fn get_loss_summary(&self, from: &str, to: &str) -> Result<LossSummary> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.product_id, p.name, m.movement_type, m.quantity, m.order_id, m.user_id, u.name,
+                    m.reason, m.created_at, m.reason_code, m.unit_cost
+             FROM stock_movements m
+             LEFT JOIN products p ON p.id = m.product_id
+             LEFT JOIN users u ON u.id = m.user_id
+             WHERE m.movement_type IN ('waste', 'comp') AND substr(m.created_at, 1, 10) BETWEEN ?1 AND ?2"
+        )?;
+
+        let movements = stmt.query_map(params![from, to], Self::stock_movement_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(stock::loss_summary(&movements))
+    }
+
+    /// Products whose stored stock differs from their ledger. With `apply` the
+    /// stored stock is reset to the ledger value.
+    pub fn reconcile_stock(&self, apply: bool) -> Result<Vec<StockDiscrepancy>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let discrepancies = {
+            let mut stmt = tx.prepare(
+                "SELECT p.id, p.name, COALESCE(p.stock, 0), COALESCE(SUM(m.quantity), 0)
+                 FROM products p
+                 LEFT JOIN stock_movements m ON m.product_id = p.id
+                 GROUP BY p.id
+                 HAVING ABS(COALESCE(p.stock, 0) - COALESCE(SUM(m.quantity), 0)) > 0.000001
+                 ORDER BY p.name"
+            )?;
+            let rows = stmt.query_map([], |row| {
+                Ok(StockDiscrepancy {
+                    product_id: row.get(0)?,
+                    product_name: row.get(1)?,
+                    recorded_stock: row.get(2)?,
+                    ledger_stock: row.get(3)?,
+                })
+            })?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+
+        if apply {
+            for discrepancy in &discrepancies {
+                tx.execute(
+                    "UPDATE products SET stock = ?2 WHERE id = ?1",
+                    params![discrepancy.product_id, discrepancy.ledger_stock],
+                )?;
+            }
+        }
+        tx.commit()?;
+
+        Ok(discrepancies)
+    }
+
+    // ==================== Low Stock ====================
+
+    pub fn get_low_stock_report(&self) -> Result<Vec<LowStockItem>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, category, COALESCE(stock, 0), min_stock, reorder_quantity
+             FROM products
+             WHERE min_stock IS NOT NULL AND COALESCE(stock, 0) <= min_stock
+             ORDER BY category, name"
+        )?;
+
+        let items = stmt.query_map([], |row| {
+            let stock: f64 = row.get(3)?;
+            let min_stock: f64 = row.get(4)?;
+            let reorder_quantity: Option<f64> = row.get(5)?;
+            Ok(LowStockItem {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                category: row.get(2)?,
+                stock,
+                min_stock,
+                reorder_quantity,
+                suggested_quantity: suggested_quantity(stock, min_stock, reorder_quantity),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(items)
+    }
+
+    pub fn get_low_stock_alerts(&self, acknowledged: Option<bool>) -> Result<Vec<LowStockAlert>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, product_name, stock, min_stock, created_at, acknowledged,
+                    acknowledged_by, acknowledged_at
+             FROM low_stock_alerts
+             WHERE ?1 IS NULL OR acknowledged = ?1
+             ORDER BY id DESC"
+        )?;
+
+        let alerts = stmt.query_map(params![acknowledged.map(|a| a as i32)], |row| {
+            Ok(LowStockAlert {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                product_name: row.get(2)?,
+                stock: row.get(3)?,
+                min_stock: row.get(4)?,
+                created_at: row.get(5)?,
+                acknowledged: row.get::<_, i32>(6)? != 0,
+                acknowledged_by: row.get(7)?,
+                acknowledged_at: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(alerts)
+    }
+
+    pub fn acknowledge_low_stock_alert(&self, id: i64, user_id: Option<i64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let acknowledged_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "UPDATE low_stock_alerts SET acknowledged = 1, acknowledged_by = ?2, acknowledged_at = ?3 WHERE id = ?1",
+            params![id, user_id, acknowledged_at],
+        )?;
+        Ok(())
+    }
+
+    // ==================== Stocktakes ====================
+
+    pub fn open_stocktake(&self, name: Option<&str>, category: Option<&str>, user_id: Option<i64>) -> Result<Stocktake> {
+        let conn = self.conn.lock().unwrap();
+        let opened_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO stocktakes (name, category, status, opened_at, opened_by) VALUES (?1, ?2, 'open', ?3, ?4)",
+            params![name, category, opened_at, user_id],
+        )?;
+
+        Ok(Stocktake {
+            id: conn.last_insert_rowid(),
+            name: name.map(str::to_string),
+            category: category.map(str::to_string),
+            status: "open".to_string(),
+            opened_at,
+            opened_by: user_id,
+            closed_at: None,
+            closed_by: None,
+        })
+    }
+
+    fn stocktake_from_row(row: &rusqlite::Row) -> Result<Stocktake> {
+        Ok(Stocktake {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            category: row.get(2)?,
+            status: row.get(3)?,
+            opened_at: row.get(4)?,
+            opened_by: row.get(5)?,
+            closed_at: row.get(6)?,
+            closed_by: row.get(7)?,
+        })
+    }
+
+    pub fn get_stocktakes(&self) -> Result<Vec<Stocktake>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, category, status, opened_at, opened_by, closed_at, closed_by
+             FROM stocktakes ORDER BY id DESC"
+        )?;
+
+        let stocktakes = stmt.query_map([], Self::stocktake_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(stocktakes)
+    }
+
+    pub fn get_stocktake(&self, id: i64) -> Result<Option<Stocktake>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, category, status, opened_at, opened_by, closed_at, closed_by
+             FROM stocktakes WHERE id = ?1"
+        )?;
+        let mut rows = stmt.query_map(params![id], Self::stocktake_from_row)?;
+
+        match rows.next() {
+            Some(stocktake) => stocktake.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Records a count with the ledger stock at that moment, so sales made
+    /// before the session is closed are not taken as variance. Fails with
+    /// `QueryReturnedNoRows` unless the session is open.
+    pub fn record_stocktake_count(&self, count: &StocktakeCount) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let counted_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let recorded = conn.execute(
+            "INSERT INTO stocktake_counts (stocktake_id, product_id, device, counted, user_id, counted_at, expected)
+             SELECT ?1, ?2, ?3, ?4, ?5, ?6,
+                    (SELECT COALESCE(SUM(quantity), 0) FROM stock_movements WHERE product_id = ?2)
+             WHERE EXISTS (SELECT 1 FROM stocktakes WHERE id = ?1 AND status = 'open')
+             ON CONFLICT(stocktake_id, product_id, device)
+             DO UPDATE SET counted = excluded.counted, user_id = excluded.user_id, counted_at = excluded.counted_at,
+                           expected = excluded.expected",
+            params![count.stocktake_id, count.product_id, count.device, count.counted, count.user_id, counted_at],
+        )?;
+        if recorded == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    // The expected stock is the one snapshotted by the product's latest count;
+    // counts recorded before the snapshot existed fall back to the ledger
+    fn get_stocktake_lines_internal(conn: &Connection, stocktake_id: i64) -> Result<Vec<StocktakeLine>> {
+        let mut stmt = conn.prepare(
+            "SELECT c.product_id, COALESCE(p.name, ''), SUM(c.counted), GROUP_CONCAT(c.device, char(31)),
+                    COALESCE(
+                        (SELECT l.expected FROM stocktake_counts l
+                         WHERE l.stocktake_id = c.stocktake_id AND l.product_id = c.product_id
+                         ORDER BY l.counted_at DESC, l.id DESC LIMIT 1),
+                        (SELECT COALESCE(SUM(m.quantity), 0) FROM stock_movements m WHERE m.product_id = c.product_id)
+                    )
+             FROM stocktake_counts c
+             LEFT JOIN products p ON p.id = c.product_id
+             WHERE c.stocktake_id = ?1
+             GROUP BY c.product_id
+             ORDER BY p.name"
+        )?;
+
+        let lines = stmt.query_map(params![stocktake_id], |row| {
+            let counted: f64 = row.get(2)?;
+            let devices: String = row.get(3)?;
+            let expected: f64 = row.get(4)?;
+            Ok(StocktakeLine {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                expected,
+                counted,
+                variance: counted - expected,
+                devices: devices.split('\u{1f}').map(str::to_string).collect(),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(lines)
+    }
+
+    /// Counted products with their variance against the stock expected when
+    /// they were counted.
+    pub fn get_stocktake_variance(&self, stocktake_id: i64) -> Result<Vec<StocktakeLine>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_stocktake_lines_internal(&conn, stocktake_id)
+    }
+
+    /// Posts every variance as an adjustment and closes the session. Products
+    /// that were not counted keep their stock. Fails with
+    /// `QueryReturnedNoRows`, posting nothing, unless the session is open.
+    pub fn close_stocktake(&self, stocktake_id: i64, user_id: Option<i64>) -> Result<(Vec<StocktakeLine>, Vec<LowStockAlert>)> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let closed_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let closed = tx.execute(
+            "UPDATE stocktakes SET status = 'closed', closed_at = ?2, closed_by = ?3 WHERE id = ?1 AND status = 'open'",
+            params![stocktake_id, closed_at, user_id],
+        )?;
+        if closed == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        let lines = Self::get_stocktake_lines_internal(&tx, stocktake_id)?;
+        let mut alerts = Vec::new();
+        for line in lines.iter().filter(|line| line.variance.abs() > 0.000001) {
+            let movement = NewStockMovement {
+                product_id: line.product_id,
+                movement_type: "adjustment".to_string(),
+                quantity: line.variance,
+                reason: Some(format!("Recuento de inventario #{}", stocktake_id)),
+                reason_code: None,
+                user_id,
+                order_id: None,
+            };
+            let (_, alert) = Self::insert_stock_movement_internal(&tx, &movement)?;
+            alerts.extend(alert);
+        }
+        tx.commit()?;
+
+        Ok((lines, alerts))
+    }
+
+    pub fn cancel_stocktake(&self, stocktake_id: i64, user_id: Option<i64>) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        let closed_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let cancelled = conn.execute(
+            "UPDATE stocktakes SET status = 'cancelled', closed_at = ?2, closed_by = ?3 WHERE id = ?1 AND status = 'open'",
+            params![stocktake_id, closed_at, user_id],
+        )?;
+        if cancelled == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    // ==================== Purchase Orders ====================
+
+    fn get_purchase_order_lines_internal(conn: &Connection, purchase_order_id: i64) -> Result<Vec<PurchaseOrderLine>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, product_name, quantity, received_quantity, cost_price
+             FROM purchase_order_lines WHERE purchase_order_id = ?1 ORDER BY id"
+        )?;
+
+        let lines = stmt.query_map(params![purchase_order_id], |row| {
+            Ok(PurchaseOrderLine {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                product_name: row.get(2)?,
+                quantity: row.get(3)?,
+                received_quantity: row.get(4)?,
+                cost_price: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(lines)
+    }
+
+    fn get_purchase_orders_internal(conn: &Connection, id: Option<i64>, status: Option<&str>) -> Result<Vec<PurchaseOrder>> {
+        let mut stmt = conn.prepare(
+            "SELECT o.id, o.supplier_id, s.nombre_comercial, s.nombre_fiscal, o.status, o.created_at,
+                    o.expected_at, o.received_at, o.notes, o.created_by
+             FROM purchase_orders o
+             LEFT JOIN suppliers s ON s.id = o.supplier_id
+             WHERE (?1 IS NULL OR o.id = ?1) AND (?2 IS NULL OR o.status = ?2)
+             ORDER BY o.id DESC"
+        )?;
+
+        let mut orders = stmt.query_map(params![id, status], |row| {
+            let nombre_comercial: Option<String> = row.get(2)?;
+            let nombre_fiscal: Option<String> = row.get(3)?;
+            Ok(PurchaseOrder {
+                id: row.get(0)?,
+                supplier_id: row.get(1)?,
+                supplier_name: nombre_comercial.filter(|name| !name.is_empty()).or(nombre_fiscal),
+                status: row.get(4)?,
+                created_at: row.get(5)?,
+                expected_at: row.get(6)?,
+                received_at: row.get(7)?,
+                notes: row.get(8)?,
+                created_by: row.get(9)?,
+                lines: Vec::new(),
+                total: 0.0,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        for order in &mut orders {
+            order.lines = Self::get_purchase_order_lines_internal(conn, order.id)?;
+            order.total = order.lines.iter().map(|line| line.quantity * line.cost_price).sum();
+        }
+
+        Ok(orders)
+    }
+
+    pub fn get_purchase_orders(&self, status: Option<&str>) -> Result<Vec<PurchaseOrder>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_purchase_orders_internal(&conn, None, status)
+    }
+
+    pub fn get_purchase_order(&self, id: i64) -> Result<Option<PurchaseOrder>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(Self::get_purchase_orders_internal(&conn, Some(id), None)?.pop())
+    }
+
+    /// Inserts the order when `id` is 0, otherwise replaces its header and lines.
+    pub fn save_purchase_order(&self, order: &PurchaseOrder) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let id = if order.id > 0 {
+            tx.execute(
+                "UPDATE purchase_orders SET supplier_id = ?2, status = ?3, expected_at = ?4, notes = ?5 WHERE id = ?1",
+                params![order.id, order.supplier_id, order.status, order.expected_at, order.notes],
+            )?;
+            tx.execute("DELETE FROM purchase_order_lines WHERE purchase_order_id = ?1", params![order.id])?;
+            order.id
+        } else {
+            let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            tx.execute(
+                "INSERT INTO purchase_orders (supplier_id, status, created_at, expected_at, notes, created_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![order.supplier_id, order.status, created_at, order.expected_at, order.notes, order.created_by],
+            )?;
+            tx.last_insert_rowid()
+        };
+
+        for line in &order.lines {
+            tx.execute(
+                "INSERT INTO purchase_order_lines (purchase_order_id, product_id, product_name, quantity, cost_price)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, line.product_id, line.product_name, line.quantity, line.cost_price],
+            )?;
+        }
+
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn set_purchase_order_status(&self, id: i64, status: &str) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE purchase_orders SET status = ?2 WHERE id = ?1", params![id, status])?;
+        Ok(())
+    }
+
+    /// Books a (possibly partial) delivery: adds the received quantities to the
+    /// lines and to stock as purchase movements, then updates the order status.
+    pub fn receive_purchase_order(&self, id: i64, receipt: &[GoodsReceiptLine], user_id: Option<i64>) -> Result<PurchaseOrder> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let lines = Self::get_purchase_order_lines_internal(&tx, id)?;
+
+        for received in receipt {
+            let Some(line) = lines.iter().find(|line| line.id == received.line_id) else {
+                continue;
+            };
+            tx.execute(
+                "UPDATE purchase_order_lines SET received_quantity = received_quantity + ?2 WHERE id = ?1",
+                params![line.id, received.quantity],
+            )?;
+
+            let movement = NewStockMovement {
+                product_id: line.product_id,
+                movement_type: "purchase".to_string(),
+                quantity: received.quantity,
+                reason: Some(format!("Pedido a proveedor #{}", id)),
+                reason_code: None,
+                user_id,
+                order_id: None,
+            };
+            let (movement_id, _) = Self::insert_stock_movement_internal(&tx, &movement)?;
+            if received.lot_number.is_some() || received.expiry_date.is_some() {
+                tx.execute(
+                    "INSERT INTO stock_lots (product_id, lot_number, expiry_date, quantity, remaining, received_at, purchase_order_id)
+                     VALUES (?1, ?2, ?3, ?4, ?4, ?5, ?6)",
+                    params![
+                        line.product_id,
+                        received.lot_number,
+                        received.expiry_date,
+                        received.quantity,
+                        chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+                        id
+                    ],
+                )?;
+                tx.execute(
+                    "INSERT INTO stock_lot_allocations (movement_id, lot_id, quantity) VALUES (?1, ?2, ?3)",
+                    params![movement_id, tx.last_insert_rowid(), received.quantity],
+                )?;
+            }
+            if received.quantity > 0.0 && line.cost_price > 0.0 {
+                Self::record_cost_change_internal(&tx, line.product_id, line.cost_price, "purchase", Some(id), user_id)?;
+            }
+        }
+
+        let lines = Self::get_purchase_order_lines_internal(&tx, id)?;
+        let status = status_after_receipt(&lines);
+        let received_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        tx.execute(
+            "UPDATE purchase_orders SET status = ?2, received_at = ?3 WHERE id = ?1",
+            params![id, status, received_at],
+        )?;
+
+        let order = Self::get_purchase_orders_internal(&tx, Some(id), None)?.pop();
+        tx.commit()?;
+        order.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    /// Reorder candidates: stock, pending deliveries, minimum stock and the
+    /// average daily sales of the last `days` days.
+    pub fn get_purchase_suggestion_data(&self, supplier_id: Option<i64>, days: i64) -> Result<Vec<SuggestedPurchaseLine>> {
+        let conn = self.conn.lock().unwrap();
+        let since = (chrono::Local::now() - chrono::Duration::days(days))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+
+        let mut stmt = conn.prepare(
+            "SELECT p.id, p.name, p.supplier_id, COALESCE(p.stock, 0),
+                    (SELECT COALESCE(SUM(l.quantity - l.received_quantity), 0)
+                     FROM purchase_order_lines l
+                     JOIN purchase_orders o ON o.id = l.purchase_order_id
+                     WHERE l.product_id = p.id AND o.status IN ('sent', 'partial')
+                       AND l.received_quantity < l.quantity),
+                    p.min_stock, p.reorder_quantity,
+                    (SELECT COALESCE(-SUM(m.quantity), 0) FROM stock_movements m
+                     WHERE m.product_id = p.id AND m.movement_type IN ('sale', 'return') AND m.created_at >= ?2),
+                    p.cost_price
+             FROM products p
+             WHERE ?1 IS NULL OR p.supplier_id = ?1
+             ORDER BY p.name"
+        )?;
+
+        let lines = stmt.query_map(params![supplier_id, since], |row| {
+            let sold: f64 = row.get(7)?;
+            Ok(SuggestedPurchaseLine {
+                product_id: row.get(0)?,
+                product_name: row.get(1)?,
+                supplier_id: row.get(2)?,
+                stock: row.get(3)?,
+                pending: row.get(4)?,
+                min_stock: row.get(5)?,
+                reorder_quantity: row.get(6)?,
+                daily_sales: sold.max(0.0) / days.max(1) as f64,
+                suggested_quantity: 0.0,
+                cost_price: row.get(8)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(lines)
+    }
+
+    // ==================== Recipes ====================
+
+    /// Current cost price of every product that has one.
+    fn get_current_costs_internal(conn: &Connection) -> Result<HashMap<i64, f64>> {
+        let mut stmt = conn.prepare("SELECT id, cost_price FROM products WHERE cost_price IS NOT NULL")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Unit cost of every product with a known cost, through its recipe when it has one.
+    fn get_unit_costs_internal(conn: &Connection) -> Result<HashMap<i64, f64>> {
+        let costs = Self::get_current_costs_internal(conn)?;
+        let recipes = Self::get_recipes_internal(conn)?;
+        let mut stmt = conn.prepare("SELECT id FROM products")?;
+        let ids = stmt.query_map([], |row| row.get::<_, i64>(0))?.collect::<Result<Vec<_>>>()?;
+
+        Ok(ids
+            .into_iter()
+            .filter_map(|id| {
+                recipe::unit_cost(costs.get(&id).copied(), recipes.get(&id)).map(|cost| (id, cost))
+            })
+            .collect())
+    }
+
+    fn get_recipes_internal(conn: &Connection) -> Result<HashMap<i64, Vec<RecipeItem>>> {
+        let costs = Self::get_current_costs_internal(conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT r.product_id, r.ingredient_id, COALESCE(p.name, ''), r.quantity
+             FROM recipe_items r
+             LEFT JOIN products p ON p.id = r.ingredient_id
+             ORDER BY r.product_id, p.name"
+        )?;
+
+        let mut recipes: HashMap<i64, Vec<RecipeItem>> = HashMap::new();
+        let rows = stmt.query_map([], |row| {
+            let ingredient_id: i64 = row.get(1)?;
+            Ok((row.get::<_, i64>(0)?, RecipeItem {
+                ingredient_id,
+                ingredient_name: row.get(2)?,
+                quantity: row.get(3)?,
+                unit_cost: costs.get(&ingredient_id).copied(),
+            }))
+        })?;
+        for row in rows {
+            let (product_id, item) = row?;
+            recipes.entry(product_id).or_default().push(item);
+        }
+
+        Ok(recipes)
+    }
+
+    pub fn get_recipe(&self, product_id: i64) -> Result<Vec<RecipeItem>> {
+        let conn = self.conn.lock().unwrap();
+        Ok(Self::get_recipes_internal(&conn)?.remove(&product_id).unwrap_or_default())
+    }
+
+    /// (product id, ingredient id) of every recipe line.
+    pub fn get_recipe_links(&self) -> Result<Vec<(i64, i64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare("SELECT product_id, ingredient_id FROM recipe_items")?;
+        let links = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+        Ok(links)
+    }
+
+    /// Replaces the recipe of a product. An empty list removes it.
+    pub fn save_recipe(&self, product_id: i64, items: &[RecipeItem]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM recipe_items WHERE product_id = ?1", params![product_id])?;
+        for item in items {
+            tx.execute(
+                "INSERT INTO recipe_items (product_id, ingredient_id, quantity) VALUES (?1, ?2, ?3)",
+                params![product_id, item.ingredient_id, item.quantity],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Theoretical unit cost and margin of every sellable product. `tax_rate`
+    /// is the IVA included in the sale prices.
+    pub fn get_product_costs(&self, tax_rate: f64) -> Result<Vec<ProductCost>> {
+        let products = self.get_products()?;
+        let conn = self.conn.lock().unwrap();
+        let recipes = Self::get_recipes_internal(&conn)?;
+        let costs = Self::get_current_costs_internal(&conn)?;
+
+        let mut report: Vec<ProductCost> = products
+            .into_iter()
+            .filter(|product| !product.ingredient)
+            .map(|product| {
+                let recipe = recipes.get(&product.id).filter(|recipe| !recipe.is_empty());
+                let (cost, missing_costs) = match recipe {
+                    Some(recipe) => {
+                        let (cost, missing) = recipe::recipe_cost(recipe);
+                        (Some(cost), missing)
+                    }
+                    None => (costs.get(&product.id).copied(), false),
+                };
+                let net_price = recipe::net_price(product.price, tax_rate);
+                let margin = cost.map(|cost| net_price - cost);
+
+                ProductCost {
+                    product_id: product.id,
+                    product_name: product.name,
+                    category: product.category,
+                    price: product.price,
+                    net_price,
+                    cost,
+                    margin,
+                    margin_percent: margin.filter(|_| net_price > 0.0).map(|margin| margin / net_price * 100.0),
+                    has_recipe: recipe.is_some(),
+                    missing_costs,
+                }
+            })
+            .collect();
+
+        report.sort_by(|a, b| a.category.cmp(&b.category).then_with(|| a.product_name.cmp(&b.product_name)));
+        Ok(report)
+    }
+
+    // ==================== Costs ====================
+
+    // Adds a history entry when the cost differs from the current one
+    fn record_cost_change_internal(
+        conn: &Connection,
+        product_id: i64,
+        cost_price: f64,
+        source: &str,
+        purchase_order_id: Option<i64>,
+        user_id: Option<i64>,
+    ) -> Result<()> {
+        let mut stmt = conn.prepare(
+            "SELECT cost_price FROM product_costs WHERE product_id = ?1 ORDER BY id DESC LIMIT 1"
+        )?;
+        let latest: Option<f64> = stmt.query_map(params![product_id], |row| row.get(0))?.next().transpose()?;
+        if latest.is_some_and(|latest| (latest - cost_price).abs() < 0.000_1) {
+            return Ok(());
+        }
+
+        let created_at = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        conn.execute(
+            "INSERT INTO product_costs (product_id, cost_price, source, purchase_order_id, user_id, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![product_id, cost_price, source, purchase_order_id, user_id, created_at],
+        )?;
+        conn.execute("UPDATE products SET cost_price = ?2 WHERE id = ?1", params![product_id, cost_price])?;
+        Ok(())
+    }
+
+    pub fn get_cost_history(&self, product_id: i64) -> Result<Vec<CostPriceChange>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, product_id, cost_price, source, purchase_order_id, user_id, created_at
+             FROM product_costs WHERE product_id = ?1 ORDER BY id DESC"
+        )?;
+
+        let history = stmt.query_map(params![product_id], |row| {
+            Ok(CostPriceChange {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                cost_price: row.get(2)?,
+                source: row.get(3)?,
+                purchase_order_id: row.get(4)?,
+                user_id: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(history)
+    }
+
+    /// Margins of the paid orders dated between `from` and `to` (inclusive,
+    /// YYYY-MM-DD), from the costs snapshotted when the items were sold, and
+    /// the cost of the waste and comp movements of the same days.
+    pub fn get_margin_report(&self, from: &str, to: &str, grouping: MarginGrouping, tax_rate: f64) -> Result<Vec<MarginLine>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {date}, i.product_id, i.name, COALESCE(NULLIF(i.category, ''), p.category, 'Sin categoría'),
+                        i.price, i.quantity, i.cost_price
+                 FROM order_items i
+                 JOIN orders o ON o.id = i.order_id
+                 LEFT JOIN products p ON p.id = i.product_id
+                 WHERE o.status = 'paid' AND substr({date}, 1, 10) BETWEEN ?1 AND ?2",
+                date = LOCAL_ORDER_DATE
+            )
+        )?;
+
+        let items = stmt.query_map(params![from, to], |row| {
+            Ok(SoldItem {
+                date: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                category: row.get(3)?,
+                price: row.get(4)?,
+                quantity: row.get(5)?,
+                cost_price: row.get(6)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT m.created_at, m.product_id, COALESCE(p.name, ''), COALESCE(p.category, 'Sin categoría'),
+                    -m.quantity, m.unit_cost
+             FROM stock_movements m
+             LEFT JOIN products p ON p.id = m.product_id
+             WHERE m.movement_type IN ('waste', 'comp') AND substr(m.created_at, 1, 10) BETWEEN ?1 AND ?2"
+        )?;
+
+        let losses = stmt.query_map(params![from, to], |row| {
+            Ok(LostItem {
+                date: row.get(0)?,
+                product_id: row.get(1)?,
+                name: row.get(2)?,
+                category: row.get(3)?,
+                quantity: row.get(4)?,
+                unit_cost: row.get(5)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(recipe::margin_lines(&items, &losses, grouping, tax_rate))
+    }
+
+    // ==================== Lots ====================
+
+    // Outgoing stock is taken from the lots that expire first. Returns of an
+    // order go back to the lots the order took from, latest expiry first.
+    // Stock received without a lot is not tracked.
+    fn allocate_lots_internal(conn: &Connection, movement_id: i64, movement: &NewStockMovement) -> Result<()> {
+        let lots: Vec<(i64, f64)> = if movement.quantity < 0.0 {
+            let mut stmt = conn.prepare(
+                "SELECT id, remaining FROM stock_lots
+                 WHERE product_id = ?1 AND remaining > 0
+                 ORDER BY expiry_date IS NULL, expiry_date, id"
+            )?;
+            let rows = stmt.query_map(params![movement.product_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        } else if movement.movement_type == "return" && movement.order_id.is_some() {
+            let mut stmt = conn.prepare(
+                "SELECT l.id, -SUM(a.quantity)
+                 FROM stock_lot_allocations a
+                 JOIN stock_movements m ON m.id = a.movement_id
+                 JOIN stock_lots l ON l.id = a.lot_id
+                 WHERE m.order_id = ?1 AND m.product_id = ?2
+                 GROUP BY l.id
+                 HAVING -SUM(a.quantity) > 0
+                 ORDER BY l.expiry_date IS NULL DESC, l.expiry_date DESC, l.id DESC"
+            )?;
+            let rows = stmt.query_map(params![movement.order_id, movement.product_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>>>()?
+        } else {
+            return Ok(());
+        };
+
+        let sign = movement.quantity.signum();
+        let mut left = movement.quantity.abs();
+        for (lot_id, available) in lots {
+            if left <= 0.000_1 {
+                break;
+            }
+            let taken = left.min(available);
+            conn.execute(
+                "UPDATE stock_lots SET remaining = remaining + ?2 WHERE id = ?1",
+                params![lot_id, sign * taken],
+            )?;
+            conn.execute(
+                "INSERT INTO stock_lot_allocations (movement_id, lot_id, quantity) VALUES (?1, ?2, ?3)",
+                params![movement_id, lot_id, sign * taken],
+            )?;
+            left -= taken;
+        }
+        Ok(())
+    }
+
+    fn get_stock_lots_internal(conn: &Connection, filter: &str, params: &[&dyn rusqlite::ToSql]) -> Result<Vec<StockLot>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT l.id, l.product_id, COALESCE(p.name, ''), l.lot_number, l.expiry_date, l.quantity, l.remaining,
+                    l.received_at, l.purchase_order_id,
+                    CAST(julianday(l.expiry_date) - julianday(date('now', 'localtime')) AS INTEGER)
+             FROM stock_lots l
+             LEFT JOIN products p ON p.id = l.product_id
+             WHERE {}
+             ORDER BY l.expiry_date IS NULL, l.expiry_date, l.id",
+            filter
+        ))?;
+
+        let lots = stmt.query_map(params, |row| {
+            Ok(StockLot {
+                id: row.get(0)?,
+                product_id: row.get(1)?,
+                product_name: row.get(2)?,
+                lot_number: row.get(3)?,
+                expiry_date: row.get(4)?,
+                quantity: row.get(5)?,
+                remaining: row.get(6)?,
+                received_at: row.get(7)?,
+                purchase_order_id: row.get(8)?,
+                days_left: row.get(9)?,
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        Ok(lots)
+    }
+
+    /// Lots of a product, or of every product, leaving out the used up ones
+    /// unless `include_empty`.
+    pub fn get_stock_lots(&self, product_id: Option<i64>, include_empty: bool) -> Result<Vec<StockLot>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_stock_lots_internal(
+            &conn,
+            "(?1 IS NULL OR l.product_id = ?1) AND (?2 OR l.remaining > 0)",
+            &[&product_id, &include_empty],
+        )
+    }
+
+    /// Lots with stock left that expire within `days` days, expired ones included.
+    pub fn get_expiring_lots(&self, days: i64) -> Result<Vec<StockLot>> {
+        let conn = self.conn.lock().unwrap();
+        let until = (chrono::Local::now() + chrono::Duration::days(days)).format("%Y-%m-%d").to_string();
+        Self::get_stock_lots_internal(
+            &conn,
+            "l.remaining > 0 AND l.expiry_date IS NOT NULL AND l.expiry_date <= ?1",
+            &[&until],
+        )
+    }
+
+    /// Movements that took stock from or returned it to a lot, with the
+    /// quantity of that lot they moved.
+    pub fn get_lot_movements(&self, lot_id: i64) -> Result<Vec<StockMovement>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT m.id, m.product_id, p.name, m.movement_type, a.quantity, m.order_id, m.user_id, u.name,
+                    m.reason, m.created_at, m.reason_code, m.unit_cost
+             FROM stock_lot_allocations a
+             JOIN stock_movements m ON m.id = a.movement_id
+             LEFT JOIN products p ON p.id = m.product_id
+             LEFT JOIN users u ON u.id = m.user_id
+             WHERE a.lot_id = ?1
+             ORDER BY m.id"
+        )?;
+
+        let movements = stmt.query_map(params![lot_id], Self::stock_movement_from_row)?
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(movements)
+    }
+
+    // ==================== Modifiers ====================
+
+    // Every group, or the groups of one product in their display order
+    fn get_modifier_groups_internal(conn: &Connection, product_id: Option<i64>) -> Result<Vec<ModifierGroup>> {
+        let mut stmt = conn.prepare(
+            "SELECT g.id, g.name, g.variant, g.required, g.min_select, g.max_select
+             FROM modifier_groups g
+             LEFT JOIN product_modifier_groups pg ON pg.group_id = g.id AND pg.product_id = ?1
+             WHERE ?1 IS NULL OR pg.product_id IS NOT NULL
+             ORDER BY pg.position, g.name"
+        )?;
+
+        let mut groups = stmt.query_map(params![product_id], |row| {
+            Ok(ModifierGroup {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                variant: row.get::<_, i32>(2)? != 0,
+                required: row.get::<_, i32>(3)? != 0,
+                min_select: row.get(4)?,
+                max_select: row.get(5)?,
+                options: Vec::new(),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT id, name, price_delta, product_id, quantity
+             FROM modifier_options WHERE group_id = ?1 ORDER BY position, id"
+        )?;
+        for group in &mut groups {
+            group.options = stmt.query_map(params![group.id], |row| {
+                Ok(ModifierOption {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    price_delta: row.get(2)?,
+                    product_id: row.get(3)?,
+                    quantity: row.get(4)?,
+                })
+            })?.collect::<Result<Vec<_>>>()?;
+        }
+
+        Ok(groups)
+    }
+
+    pub fn get_modifier_groups(&self) -> Result<Vec<ModifierGroup>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_modifier_groups_internal(&conn, None)
+    }
+
+    pub fn get_product_modifier_groups(&self, product_id: i64) -> Result<Vec<ModifierGroup>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_modifier_groups_internal(&conn, Some(product_id))
+    }
+
+    /// Inserts the group when `id` is 0. Options keep their ids so the
+    /// selections on screen stay valid; the ones left out are removed.
+    pub fn save_modifier_group(&self, group: &ModifierGroup) -> Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let id = if group.id > 0 {
+            tx.execute(
+                "UPDATE modifier_groups SET name = ?2, variant = ?3, required = ?4, min_select = ?5, max_select = ?6
+                 WHERE id = ?1",
+                params![group.id, group.name, group.variant as i32, group.required as i32, group.min_select, group.max_select],
+            )?;
+            group.id
+        } else {
+            tx.execute(
+                "INSERT INTO modifier_groups (name, variant, required, min_select, max_select) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![group.name, group.variant as i32, group.required as i32, group.min_select, group.max_select],
+            )?;
+            tx.last_insert_rowid()
+        };
+
+        let mut kept = Vec::new();
+        for (position, option) in group.options.iter().enumerate() {
+            let updated = option.id > 0 && tx.execute(
+                "UPDATE modifier_options SET name = ?3, price_delta = ?4, product_id = ?5, quantity = ?6, position = ?7
+                 WHERE id = ?1 AND group_id = ?2",
+                params![option.id, id, option.name, option.price_delta, option.product_id, option.quantity, position as i64],
+            )? > 0;
+            if updated {
+                kept.push(option.id);
+            } else {
+                tx.execute(
+                    "INSERT INTO modifier_options (group_id, name, price_delta, product_id, quantity, position)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![id, option.name, option.price_delta, option.product_id, option.quantity, position as i64],
+                )?;
+                kept.push(tx.last_insert_rowid());
+            }
+        }
+
+        let existing: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT id FROM modifier_options WHERE group_id = ?1")?;
+            let rows = stmt.query_map(params![id], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for option_id in existing.into_iter().filter(|option_id| !kept.contains(option_id)) {
+            tx.execute("DELETE FROM modifier_options WHERE id = ?1", params![option_id])?;
+        }
+
+        tx.commit()?;
+        Ok(id)
+    }
+
+    pub fn delete_modifier_group(&self, id: i64) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM product_modifier_groups WHERE group_id = ?1", params![id])?;
+        tx.execute("DELETE FROM modifier_options WHERE group_id = ?1", params![id])?;
+        tx.execute("DELETE FROM modifier_groups WHERE id = ?1", params![id])?;
+        tx.commit()
+    }
+
+    /// Replaces the groups offered for a product, in the given order.
+    pub fn set_product_modifier_groups(&self, product_id: i64, group_ids: &[i64]) -> Result<()> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM product_modifier_groups WHERE product_id = ?1", params![product_id])?;
+        for (position, group_id) in group_ids.iter().enumerate() {
+            tx.execute(
+                "INSERT OR IGNORE INTO product_modifier_groups (product_id, group_id, position) VALUES (?1, ?2, ?3)",
+                params![product_id, group_id, position as i64],
+            )?;
+        }
+        tx.commit()
+    }
+
+    // ==================== Bundles ====================
+
+    fn get_bundle_slots_internal(conn: &Connection, bundle_id: i64) -> Result<Vec<BundleSlot>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, required FROM bundle_slots WHERE bundle_id = ?1 ORDER BY position, id"
+        )?;
+        let mut slots = stmt.query_map(params![bundle_id], |row| {
+            Ok(BundleSlot {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                required: row.get::<_, i32>(2)? != 0,
+                categories: Vec::new(),
+                product_ids: Vec::new(),
+            })
+        })?.collect::<Result<Vec<_>>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT category, product_id FROM bundle_slot_routes WHERE slot_id = ?1 ORDER BY id"
+        )?;
+        for slot in &mut slots {
+            let routes = stmt.query_map(params![slot.id], |row| {
+                Ok((row.get::<_, Option<String>>(0)?, row.get::<_, Option<i64>>(1)?))
+            })?.collect::<Result<Vec<_>>>()?;
+
+            for (category, product_id) in routes {
+                if let Some(category) = category {
+                    slot.categories.push(category);
+                }
+                if let Some(product_id) = product_id {
+                    slot.product_ids.push(product_id);
+                }
+            }
+        }
+
+        Ok(slots)
+    }
+
+    /// Slots of a bundle product, empty for a regular product.
+    pub fn get_bundle_slots(&self, bundle_id: i64) -> Result<Vec<BundleSlot>> {
+        let conn = self.conn.lock().unwrap();
+        Self::get_bundle_slots_internal(&conn, bundle_id)
+    }
+
+    /// Replaces the slots of a bundle; no slots turns it back into a regular
+    /// product. Slots keep their ids so choices on screen stay valid.
+    pub fn save_bundle_slots(&self, bundle_id: i64, slots: &[BundleSlot]) -> Result<Vec<BundleSlot>> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+
+        let mut kept = Vec::new();
+        for (position, slot) in slots.iter().enumerate() {
+            let updated = slot.id > 0 && tx.execute(
+                "UPDATE bundle_slots SET name = ?3, required = ?4, position = ?5 WHERE id = ?1 AND bundle_id = ?2",
+                params![slot.id, bundle_id, slot.name, slot.required as i32, position as i64],
+            )? > 0;
+            let slot_id = if updated {
+                slot.id
+            } else {
+                tx.execute(
+                    "INSERT INTO bundle_slots (bundle_id, name, required, position) VALUES (?1, ?2, ?3, ?4)",
+                    params![bundle_id, slot.name, slot.required as i32, position as i64],
+                )?;
+                tx.last_insert_rowid()
+            };
+            kept.push(slot_id);
+
+            tx.execute("DELETE FROM bundle_slot_routes WHERE slot_id = ?1", params![slot_id])?;
+            for category in &slot.categories {
+                tx.execute(
+                    "INSERT INTO bundle_slot_routes (slot_id, category) VALUES (?1, ?2)",
+                    params![slot_id, category],
+                )?;
+            }
+            for product_id in &slot.product_ids {
+                tx.execute(
+                    "INSERT INTO bundle_slot_routes (slot_id, product_id) VALUES (?1, ?2)",
+                    params![slot_id, product_id],
+                )?;
+            }
+        }
+
+        let existing: Vec<i64> = {
+            let mut stmt = tx.prepare("SELECT id FROM bundle_slots WHERE bundle_id = ?1")?;
+            let rows = stmt.query_map(params![bundle_id], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>>>()?
+        };
+        for slot_id in existing.into_iter().filter(|slot_id| !kept.contains(slot_id)) {
+            tx.execute("DELETE FROM bundle_slot_routes WHERE slot_id = ?1", params![slot_id])?;
+            tx.execute("DELETE FROM bundle_slots WHERE id = ?1", params![slot_id])?;
+        }
+
+        let saved = Self::get_bundle_slots_internal(&tx, bundle_id)?;
+        tx.commit()?;
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn database() -> Database {
+        Database::new(PathBuf::from(":memory:")).unwrap()
+    }
+
+    fn product(id: i64, name: &str, stock: f64) -> Product {
+        serde_json::from_value(json!({
+            "id": id, "name": name, "price": 2.0, "category": "Bebidas", "brand": "",
+            "iconType": "", "selectedIcon": "", "uploadedImage": null, "stock": stock
+        }))
+        .unwrap()
+    }
+
+    fn order(status: &str, items: &[(i64, f64)]) -> Order {
+        let items: Vec<_> = items
+            .iter()
+            .map(|(id, quantity)| json!({ "id": id, "name": format!("P{}", id), "price": 2.0, "quantity": quantity }))
+            .collect();
+        serde_json::from_value(json!({ "id": 7, "date": "2026-10-18 12:00", "total": 0.0, "status": status, "items": items }))
+            .unwrap()
+    }
+
+    fn stock(db: &Database) -> Vec<(i64, Option<f64>)> {
+        db.get_products().unwrap().iter().map(|product| (product.id, product.stock)).collect()
+    }
+
+    // (product, type, quantity) of the movements of order 7, oldest first
+    fn order_movements(db: &Database) -> Vec<(i64, String, f64)> {
+        let mut movements: Vec<_> = db
+            .get_stock_movements(None, 100)
+            .unwrap()
+            .into_iter()
+            .filter(|movement| movement.order_id == Some(7))
+            .map(|movement| (movement.product_id, movement.movement_type, movement.quantity))
+            .collect();
+        movements.reverse();
+        movements
+    }
+
+    #[test]
+    fn order_stock_follows_payment_edits_and_deletion() {
+        let db = database();
+        db.create_product(&product(1, "Caña", 10.0)).unwrap();
+        db.create_product(&product(2, "Tercio", 5.0)).unwrap();
+
+        // Open orders don't touch stock
+        db.create_order(&order("inProgress", &[(1, 3.0)])).unwrap();
+        assert_eq!(stock(&db), [(1, Some(10.0)), (2, Some(5.0))]);
+        assert!(order_movements(&db).is_empty());
+
+        db.update_order(&order("paid", &[(1, 3.0)])).unwrap();
+        assert_eq!(stock(&db), [(1, Some(7.0)), (2, Some(5.0))]);
+
+        // Saving the same order again changes nothing
+        db.update_order(&order("paid", &[(1, 3.0)])).unwrap();
+        assert_eq!(order_movements(&db), [(1, "sale".to_string(), -3.0)]);
+
+        db.update_order(&order("paid", &[(1, 1.0), (2, 2.0)])).unwrap();
+        assert_eq!(stock(&db), [(1, Some(9.0)), (2, Some(3.0))]);
+
+        db.delete_order(7).unwrap();
+        assert_eq!(stock(&db), [(1, Some(10.0)), (2, Some(5.0))]);
+        assert_eq!(
+            order_movements(&db),
+            [
+                (1, "sale".to_string(), -3.0),
+                (1, "return".to_string(), 2.0),
+                (2, "sale".to_string(), -2.0),
+                (1, "return".to_string(), 1.0),
+                (2, "return".to_string(), 2.0),
+            ]
+        );
+        assert!(db.reconcile_stock(false).unwrap().is_empty());
+    }
+
+    #[test]
+    fn waste_of_a_recipe_is_booked_on_its_ingredients() {
+        let db = database();
+        db.create_product(&product(1, "Gin tonic", 0.0)).unwrap();
+        db.create_product(&product(2, "Ginebra", 70.0)).unwrap();
+        db.create_product(&product(3, "Tónica", 24.0)).unwrap();
+        let recipe: Vec<RecipeItem> = serde_json::from_value(json!([
+            { "ingredientId": 2, "quantity": 5.0 },
+            { "ingredientId": 3, "quantity": 1.0 }
+        ]))
+        .unwrap();
+        db.save_recipe(1, &recipe).unwrap();
+
+        let waste = NewStockMovement {
+            product_id: 1,
+            movement_type: "waste".to_string(),
+            quantity: -2.0,
+            reason: Some("Se cayó".to_string()),
+            reason_code: None,
+            user_id: None,
+            order_id: None,
+        };
+        let (ids, _) = db.record_stock_movement(&waste).unwrap();
+
+        assert_eq!(ids.len(), 2);
+        assert_eq!(stock(&db), [(1, Some(0.0)), (2, Some(60.0)), (3, Some(22.0))]);
+        let reasons: Vec<_> = db.get_stock_movements(Some(2), 10).unwrap().into_iter().map(|m| m.reason).collect();
+        assert_eq!(reasons[0].as_deref(), Some("Gin tonic: Se cayó"));
+    }
+
+    #[test]
+    fn reconcile_resets_stock_to_the_ledger() {
+        let db = database();
+        db.create_product(&product(1, "Caña", 10.0)).unwrap();
+        db.conn.lock().unwrap().execute("UPDATE products SET stock = 4 WHERE id = 1", []).unwrap();
+
+        let discrepancies = db.reconcile_stock(false).unwrap();
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!((discrepancies[0].recorded_stock, discrepancies[0].ledger_stock), (4.0, 10.0));
+        assert_eq!(stock(&db), [(1, Some(4.0))]);
+
+        db.reconcile_stock(true).unwrap();
+        assert_eq!(stock(&db), [(1, Some(10.0))]);
+        assert!(db.reconcile_stock(false).unwrap().is_empty());
+    }
+
+    fn column_type(conn: &Connection, table: &str, column: &str) -> String {
+        conn.prepare(&format!("PRAGMA table_info({})", table))
+            .unwrap()
+            .query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .find(|(name, _)| name == column)
+            .unwrap()
+            .1
+    }
+
+    #[test]
+    fn deleting_a_product_removes_its_rows() {
+        let db = database();
+        db.create_product(&product(1, "Gin tonic", 0.0)).unwrap();
+        db.create_product(&product(2, "Ginebra", 70.0)).unwrap();
+        let recipe: Vec<RecipeItem> = serde_json::from_value(json!([{ "ingredientId": 2, "quantity": 5.0 }])).unwrap();
+        db.save_recipe(1, &recipe).unwrap();
+        {
+            let conn = db.conn.lock().unwrap();
+            conn.execute_batch(
+                "INSERT INTO product_barcodes (barcode, product_id) VALUES ('8410000000001', 2);
+                 INSERT INTO stations (id, name) VALUES (1, 'Barra');
+                 INSERT INTO station_routes (station_id, product_id) VALUES (1, 2);
+                 INSERT INTO product_modifier_groups (product_id, group_id) VALUES (2, 1);
+                 INSERT INTO stock_lots (product_id, quantity, remaining, received_at) VALUES (2, 6, 6, '2026-10-18');",
+            )
+            .unwrap();
+        }
+
+        db.delete_product(2).unwrap();
+
+        let conn = db.conn.lock().unwrap();
+        for table in ["recipe_items", "product_barcodes", "station_routes", "product_modifier_groups", "stock_lots"] {
+            let rows: i64 = conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap();
+            assert_eq!(rows, 0, "{}", table);
+        }
+    }
+
+    #[test]
+    fn closed_stocktakes_are_not_posted_again() {
+        let db = database();
+        db.create_product(&product(1, "Caña", 10.0)).unwrap();
+        let stocktake = db.open_stocktake(None, None, None).unwrap();
+        let count = StocktakeCount { stocktake_id: stocktake.id, product_id: 1, counted: 8.0, device: String::new(), user_id: None };
+        db.record_stocktake_count(&count).unwrap();
+
+        let (lines, _) = db.close_stocktake(stocktake.id, None).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(stock(&db), [(1, Some(8.0))]);
+
+        assert!(matches!(db.close_stocktake(stocktake.id, None), Err(rusqlite::Error::QueryReturnedNoRows)));
+        assert!(matches!(db.record_stocktake_count(&count), Err(rusqlite::Error::QueryReturnedNoRows)));
+        assert!(matches!(db.cancel_stocktake(stocktake.id, None), Err(rusqlite::Error::QueryReturnedNoRows)));
+        assert_eq!(stock(&db), [(1, Some(8.0))]);
+        assert_eq!(db.get_stocktake(stocktake.id).unwrap().unwrap().status, "closed");
+    }
+
+    #[test]
+    fn integer_quantities_become_real() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE comanda_items (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 name TEXT NOT NULL,
+                 quantity INTEGER NOT NULL
+             );
+             ALTER TABLE comanda_items ADD COLUMN unit TEXT NOT NULL DEFAULT 'unit';
+             CREATE INDEX idx_comanda_items_name ON comanda_items(name);
+             INSERT INTO comanda_items (name, quantity) VALUES ('Caña', 2);",
+        )
+        .unwrap();
+
+        Database::retype_column_if_needed(&conn, "comanda_items", "quantity", "REAL").unwrap();
+        conn.execute("INSERT INTO comanda_items (name, quantity, unit) VALUES ('Jamón', 0.25, 'kg')", []).unwrap();
+
+        assert_eq!(column_type(&conn, "comanda_items", "quantity"), "REAL");
+        let rows: Vec<(String, f64, String)> = conn
+            .prepare("SELECT name, quantity, unit FROM comanda_items ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .map(|row| row.unwrap())
+            .collect();
+        assert_eq!(rows, [("Caña".into(), 2.0, "unit".into()), ("Jamón".into(), 0.25, "kg".into())]);
+        let index: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master WHERE name = 'idx_comanda_items_name'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(index, 1);
+
+        // Already REAL: nothing to do
+        Database::retype_column_if_needed(&conn, "comanda_items", "quantity", "REAL").unwrap();
+    }
 }
@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::bundle;
+use crate::models::kitchen::{ComandaItem, Station};
+use crate::models::{OrderItem, SaleUnit};
+use crate::modifier;
+use crate::order_line::{content_key, line_key};
+
+pub const KITCHEN_STATUSES: [&str; 4] = ["pending", "started", "ready", "served"];
+
+pub fn is_valid_status(status: &str) -> bool {
+    KITCHEN_STATUSES.contains(&status)
+}
+
+/// Quantity change of an order line between the stored and the incoming
+/// items, zero when only its notes changed. Bundle lines list their products
+/// as modifiers.
+pub struct ItemDelta {
+    pub product_id: i64,
+    pub line_id: Option<String>,
+    pub name: String,
+    pub category: Option<String>,
+    pub quantity: f64,
+    pub unit: SaleUnit,
+    pub modifiers: Vec<String>,
+    pub notes: Option<String>,
+}
+
+/// Lines are matched by line id when every item has one, by content
+/// otherwise (e.g. against a pre-bill printed before line ids existed).
+/// A line kept with other notes is sent again so the kitchen gets them.
+pub fn compute_delta(previous: &[OrderItem], current: &[OrderItem]) -> Vec<ItemDelta> {
+    let by_line = previous.iter().chain(current).all(|item| item.line_id.is_some());
+    let key = |item: &OrderItem| if by_line { line_key(item) } else { content_key(item) };
+
+    let previous_notes: HashMap<(i64, String), Option<&str>> =
+        previous.iter().map(|item| (key(item), item.notes.as_deref())).collect();
+    let notes_changed: HashSet<(i64, String)> = current
+        .iter()
+        .filter(|item| previous_notes.get(&key(item)).is_some_and(|notes| *notes != item.notes.as_deref()))
+        .map(key)
+        .collect();
+
+    // BTreeMap keeps comanda lines in a stable order
+    let mut deltas: BTreeMap<(i64, String), ItemDelta> = BTreeMap::new();
+
+    for (items, sign) in [(previous, -1.0), (current, 1.0)] {
+        for item in items {
+            let delta = deltas.entry(key(item)).or_insert_with(|| ItemDelta {
+                product_id: item.id,
+                line_id: item.line_id.clone(),
+                name: item.name.clone(),
+                category: item.category.clone(),
+                quantity: 0.0,
+                unit: item.unit,
+                modifiers: modifier::extras(&item.modifiers)
+                    .into_iter()
+                    .chain(bundle::component_names(&item.components))
+                    .collect(),
+                notes: item.notes.clone(),
+            });
+            if sign > 0.0 {
+                delta.name = item.name.clone();
+                delta.category = item.category.clone().or(delta.category.take());
+                delta.notes = item.notes.clone();
+            }
+            delta.quantity += sign * item.quantity;
+        }
+    }
+
+    // Quantities by weight may not cancel out exactly
+    deltas
+        .into_iter()
+        .filter(|(key, d)| d.quantity.abs() > 1e-9 || notes_changed.contains(key))
+        .map(|(_, mut d)| {
+            if d.quantity.abs() <= 1e-9 {
+                d.quantity = 0.0;
+            }
+            d
+        })
+        .collect()
+}
+
+/// Groups the delta by station. `product_categories` resolves the category of
+/// items that were stored without one.
+pub fn route_items(
+    deltas: &[ItemDelta],
+    stations: &[Station],
+    product_categories: &HashMap<i64, String>,
+) -> BTreeMap<i64, Vec<ComandaItem>> {
+    let mut by_product: HashMap<i64, i64> = HashMap::new();
+    let mut by_category: HashMap<&str, i64> = HashMap::new();
+    for station in stations.iter().filter(|s| s.active) {
+        for product_id in &station.product_ids {
+            by_product.entry(*product_id).or_insert(station.id);
+        }
+        for category in &station.categories {
+            by_category.entry(category.as_str()).or_insert(station.id);
+        }
+    }
+
+    let mut routed: BTreeMap<i64, Vec<ComandaItem>> = BTreeMap::new();
+    for delta in deltas {
+        let category = delta
+            .category
+            .as_deref()
+            .or_else(|| product_categories.get(&delta.product_id).map(String::as_str));
+        let station_id = by_product
+            .get(&delta.product_id)
+            .or_else(|| category.and_then(|c| by_category.get(c)));
+
+        if let Some(station_id) = station_id {
+            routed.entry(*station_id).or_default().push(ComandaItem {
+                id: 0,
+                product_id: delta.product_id,
+                name: delta.name.clone(),
+                quantity: delta.quantity,
+                unit: delta.unit,
+                status: "pending".to_string(),
+                modifiers: delta.modifiers.clone(),
+                line_id: delta.line_id.clone(),
+                notes: delta.notes.clone(),
+            });
+        }
+    }
+
+    routed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn line(id: i64, line_id: &str, quantity: f64, notes: Option<&str>) -> OrderItem {
+        serde_json::from_value(json!({
+            "id": id, "name": format!("Producto {}", id), "price": 1.0, "quantity": quantity,
+            "category": "Comida", "lineId": line_id, "notes": notes,
+        }))
+        .unwrap()
+    }
+
+    fn station(id: i64, categories: &[&str], product_ids: &[i64], active: bool) -> Station {
+        Station {
+            id,
+            name: format!("Estación {}", id),
+            printer_interface: None,
+            active,
+            categories: categories.iter().map(|category| category.to_string()).collect(),
+            product_ids: product_ids.to_vec(),
+        }
+    }
+
+    #[test]
+    fn delta_has_added_and_removed_quantities() {
+        let previous = [line(1, "a", 2.0, None), line(2, "b", 1.0, None)];
+        let current = [line(1, "a", 3.0, None), line(3, "c", 1.0, None)];
+        let deltas = compute_delta(&previous, &current);
+
+        let quantities: Vec<(i64, f64)> = deltas.iter().map(|d| (d.product_id, d.quantity)).collect();
+        assert_eq!(quantities, [(1, 1.0), (2, -1.0), (3, 1.0)]);
+    }
+
+    #[test]
+    fn unchanged_lines_have_no_delta() {
+        let items = [line(1, "a", 2.0, Some("sin sal"))];
+        assert!(compute_delta(&items, &items).is_empty());
+    }
+
+    #[test]
+    fn a_notes_change_is_sent_with_zero_quantity() {
+        let deltas = compute_delta(&[line(1, "a", 2.0, None)], &[line(1, "a", 2.0, Some("poco hecha"))]);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].quantity, 0.0);
+        assert_eq!(deltas[0].notes.as_deref(), Some("poco hecha"));
+    }
+
+    #[test]
+    fn lines_without_ids_match_by_content() {
+        let mut stored = line(1, "a", 1.0, None);
+        stored.line_id = None;
+        let deltas = compute_delta(&[stored], &[line(1, "a", 2.0, None)]);
+        assert_eq!(deltas.len(), 1);
+        assert_eq!(deltas[0].quantity, 1.0);
+    }
+
+    #[test]
+    fn routes_by_product_before_category() {
+        let stations = [
+            station(1, &["Comida"], &[], true),
+            station(2, &[], &[2], true),
+            station(3, &["Bebidas"], &[], false),
+        ];
+        let mut drink = line(4, "d", 1.0, None);
+        drink.category = Some("Bebidas".to_string());
+        let deltas = compute_delta(&[], &[line(1, "a", 1.0, None), line(2, "b", 1.0, None), drink]);
+        let routed = route_items(&deltas, &stations, &HashMap::new());
+
+        assert_eq!(routed.keys().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(routed[&1][0].product_id, 1);
+        assert_eq!(routed[&2][0].product_id, 2);
+    }
+}
@@ -0,0 +1,352 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::certificates::{restrict_permissions, write_private};
+use crate::kitchen::is_valid_status;
+use crate::models::kitchen::{Comanda, KitchenDisplayInfo, KitchenStatusUpdate};
+use crate::DbState;
+
+pub const DEFAULT_PORT: u16 = 8787;
+const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+const PORT_ENV: &str = "KDS_PORT";
+const BIND_ENV: &str = "KDS_BIND";
+const TOKEN_ENV: &str = "KDS_TOKEN";
+const TOKEN_FILE: &str = "kds.token";
+const EVENT_BUFFER: usize = 256;
+const UNAUTHORIZED: &str = "Invalid or missing kitchen display token";
+
+/// Messages pushed to connected kitchen displays after the initial snapshot.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum KitchenEvent {
+    Comanda(Comanda),
+    Status(KitchenStatusUpdate),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum ClientMessage {
+    Ack(KitchenStatusUpdate),
+    Ping,
+}
+
+/// Managed state shared by the order commands and the display server.
+pub struct KitchenDisplay {
+    events: broadcast::Sender<KitchenEvent>,
+    bind_address: String,
+    port: u16,
+    token: String,
+    running: AtomicBool,
+}
+
+impl KitchenDisplay {
+    /// Port, interface and access token come from `KDS_PORT`, `KDS_BIND` and
+    /// `KDS_TOKEN`; without a token one is generated and kept in `app_dir`.
+    /// Only local displays can connect unless `KDS_BIND` opens another
+    /// interface, e.g. `0.0.0.0` for tablets on the LAN.
+    pub fn from_env(app_dir: &Path) -> Self {
+        let port = std::env::var(PORT_ENV)
+            .ok()
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        let bind_address = std::env::var(BIND_ENV)
+            .ok()
+            .filter(|address| !address.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_BIND_ADDRESS.to_string());
+        let token = std::env::var(TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.trim().is_empty())
+            .unwrap_or_else(|| load_or_create_token(app_dir));
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+
+        KitchenDisplay {
+            events,
+            bind_address,
+            port,
+            token,
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn publish_comandas(&self, comandas: &[Comanda]) {
+        for comanda in comandas {
+            // Fails only when no display is connected
+            let _ = self.events.send(KitchenEvent::Comanda(comanda.clone()));
+        }
+    }
+
+    pub fn info(&self) -> KitchenDisplayInfo {
+        KitchenDisplayInfo {
+            bind_address: self.bind_address.clone(),
+            port: self.port,
+            running: self.running.load(Ordering::SeqCst),
+            token: self.token.clone(),
+        }
+    }
+
+    // Same time for every wrong token of the right length
+    fn authorized(&self, token: Option<&str>) -> bool {
+        let Some(token) = token else {
+            return false;
+        };
+        token.len() == self.token.len()
+            && token.bytes().zip(self.token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+}
+
+fn load_or_create_token(app_dir: &Path) -> String {
+    let path = app_dir.join(TOKEN_FILE);
+    if let Ok(token) = fs::read_to_string(&path) {
+        if !token.trim().is_empty() {
+            if let Err(e) = restrict_permissions(&path) {
+                eprintln!("Failed to restrict kitchen display token permissions: {}", e);
+            }
+            return token.trim().to_string();
+        }
+    }
+
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+    if let Err(e) = fs::create_dir_all(app_dir).and_then(|_| write_private(&path, token.as_bytes())) {
+        eprintln!("Failed to store kitchen display token: {}", e);
+    }
+    token
+}
+
+/// Writes a status coming from a display (or the POS itself) to the comanda
+/// and order items, then notifies displays and the frontend.
+pub fn apply_status_update(app: &AppHandle, update: KitchenStatusUpdate) -> Result<Comanda, String> {
+    if !is_valid_status(&update.status) {
+        return Err(format!("Invalid kitchen status: {}", update.status));
+    }
+
+    let comanda = {
+        let state = app.state::<DbState>();
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+
+        let comanda = db.get_comanda(update.comanda_id).map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Comanda {} not found", update.comanda_id))?;
+        if let Some(item_id) = update.comanda_item_id {
+            if !comanda.items.iter().any(|item| item.id == item_id) {
+                return Err(format!("Item {} does not belong to comanda {}", item_id, comanda.id));
+            }
+        }
+
+        db.set_kitchen_status(update.comanda_id, update.comanda_item_id, &update.status)
+            .map_err(|e| e.to_string())?;
+        db.get_comanda(update.comanda_id).map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Comanda {} not found", update.comanda_id))?
+    };
+
+    let _ = app.state::<KitchenDisplay>().events.send(KitchenEvent::Status(update.clone()));
+    if let Err(e) = app.emit("kitchen-status-changed", &update) {
+        eprintln!("Failed to emit kitchen status: {}", e);
+    }
+
+    Ok(comanda)
+}
+
+fn open_comandas(app: &AppHandle, station_id: Option<i64>) -> Result<Vec<Comanda>, String> {
+    let state = app.state::<DbState>();
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_open_comandas(station_id).map_err(|e| e.to_string())
+}
+
+fn stations_json(app: &AppHandle) -> Result<String, String> {
+    let state = app.state::<DbState>();
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let stations = db.get_stations().map_err(|e| e.to_string())?;
+    serde_json::to_string(&stations).map_err(|e| e.to_string())
+}
+
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+// `station=N` from a query string; displays without it see every station
+fn station_from_query(query: Option<&str>) -> Option<i64> {
+    query_param(query, "station").and_then(|id| id.parse().ok())
+}
+
+// The token from `?token=` or an `Authorization: Bearer` header
+fn request_token<'a>(query: Option<&'a str>, authorization: Option<&'a str>) -> Option<&'a str> {
+    query_param(query, "token").or_else(|| authorization?.trim().strip_prefix("Bearer ").map(str::trim))
+}
+
+/// Accepts kitchen displays on the LAN. `ws://host:port/?station=N` streams
+/// comandas and takes acknowledgements; `GET /api/stations` and
+/// `GET /api/tickets?station=N` return JSON snapshots. Every request must
+/// carry the display token.
+pub async fn serve(app: AppHandle) {
+    let (bind_address, port) = {
+        let display = app.state::<KitchenDisplay>();
+        (display.bind_address.clone(), display.port)
+    };
+    let listener = match TcpListener::bind((bind_address.as_str(), port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Kitchen display server failed to listen on {}:{}: {}", bind_address, port, e);
+            return;
+        }
+    };
+
+    app.state::<KitchenDisplay>().running.store(true, Ordering::SeqCst);
+    println!("Kitchen display server listening on {}:{}", bind_address, port);
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = handle_connection(app, stream).await {
+                        eprintln!("Kitchen display connection error: {}", e);
+                    }
+                });
+            }
+            Err(e) => eprintln!("Kitchen display accept failed: {}", e),
+        }
+    }
+}
+
+async fn handle_connection(app: AppHandle, stream: TcpStream) -> Result<(), String> {
+    let mut head = [0u8; 2048];
+    let read = stream.peek(&mut head).await.map_err(|e| e.to_string())?;
+    let head = String::from_utf8_lossy(&head[..read]).to_ascii_lowercase();
+
+    if head.contains("upgrade: websocket") {
+        serve_websocket(app, stream).await
+    } else {
+        serve_http(app, stream).await
+    }
+}
+
+async fn serve_http(app: AppHandle, mut stream: TcpStream) -> Result<(), String> {
+    let mut buf = vec![0u8; 8192];
+    let read = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+    let request = String::from_utf8_lossy(&buf[..read]);
+
+    let mut request_line = request.lines().next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default();
+    let target = request_line.next().unwrap_or_default();
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (target, None),
+    };
+
+    let authorization = request
+        .lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("authorization").then_some(value)
+        });
+    let authorized = app.state::<KitchenDisplay>().authorized(request_token(query, authorization));
+
+    let result = match (method, path) {
+        _ if !authorized => Some(Err(UNAUTHORIZED.to_string())),
+        ("GET", "/api/stations") => Some(stations_json(&app)),
+        ("GET", "/api/tickets") => Some(
+            open_comandas(&app, station_from_query(query))
+                .and_then(|comandas| serde_json::to_string(&comandas).map_err(|e| e.to_string())),
+        ),
+        _ => None,
+    };
+
+    let (status, body) = match result {
+        Some(Ok(body)) => ("200 OK", body),
+        Some(Err(e)) if !authorized => ("401 Unauthorized", json!({ "error": e }).to_string()),
+        Some(Err(e)) => ("500 Internal Server Error", json!({ "error": e }).to_string()),
+        None => ("404 Not Found", json!({ "error": "Not found" }).to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await.map_err(|e| e.to_string())?;
+    stream.shutdown().await.map_err(|e| e.to_string())
+}
+
+// The handshake callback signature is fixed by tungstenite
+#[allow(clippy::result_large_err)]
+async fn serve_websocket(app: AppHandle, stream: TcpStream) -> Result<(), String> {
+    let mut station_id = None;
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        let query = request.uri().query();
+        let authorization = request.headers().get("authorization").and_then(|value| value.to_str().ok());
+        if !app.state::<KitchenDisplay>().authorized(request_token(query, authorization)) {
+            let mut rejection = ErrorResponse::new(Some(UNAUTHORIZED.to_string()));
+            *rejection.status_mut() = StatusCode::UNAUTHORIZED;
+            return Err(rejection);
+        }
+        station_id = station_from_query(query);
+        Ok(response)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    // Subscribe before the snapshot so nothing created in between is missed
+    let mut events = app.state::<KitchenDisplay>().events.subscribe();
+    let (mut sink, mut source) = ws.split();
+
+    let snapshot = json!({ "type": "snapshot", "comandas": open_comandas(&app, station_id)? });
+    sink.send(Message::Text(snapshot.to_string())).await.map_err(|e| e.to_string())?;
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let text = match event {
+                    Ok(KitchenEvent::Comanda(comanda)) if station_id.is_some_and(|id| id != comanda.station_id) => continue,
+                    Ok(event) => serde_json::to_string(&event).map_err(|e| e.to_string())?,
+                    // Too slow to keep up: start over from a fresh snapshot
+                    Err(broadcast::error::RecvError::Lagged(_)) => {
+                        json!({ "type": "snapshot", "comandas": open_comandas(&app, station_id)? }).to_string()
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                sink.send(Message::Text(text)).await.map_err(|e| e.to_string())?;
+            }
+            message = source.next() => {
+                let reply = match message {
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<ClientMessage>(&text) {
+                        Ok(ClientMessage::Ack(update)) => apply_status_update(&app, update)
+                            .err()
+                            .map(|e| json!({ "type": "error", "message": e })),
+                        Ok(ClientMessage::Ping) => Some(json!({ "type": "pong" })),
+                        Err(e) => Some(json!({ "type": "error", "message": e.to_string() })),
+                    },
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => None,
+                    Some(Err(e)) => return Err(e.to_string()),
+                };
+                if let Some(reply) = reply {
+                    sink.send(Message::Text(reply.to_string())).await.map_err(|e| e.to_string())?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
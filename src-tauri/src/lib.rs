@@ -1,21 +1,54 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
 
+mod barcode;
+mod bundle;
 mod database;
 mod models;
 mod license;
+mod certificates;
+mod aeat_sidecar;
+mod printer;
+mod kitchen;
+mod kitchen_display;
+mod modifier;
+mod order_line;
+mod prebill;
+mod pricing;
+mod purchasing;
+mod recipe;
+mod search;
 mod screenshot;
+mod stock;
+mod tax_id;
 
 use std::fs;
 use std::sync::Mutex;
+use std::time::Duration;
+use tauri::Emitter;
 use tauri::Manager;
 use tauri::State;
-use serde_json::Value;
 
 use database::Database;
-use models::{Product, Category, Order, Table, User, ExportData, ImportData};
+use models::{BarcodeMatch, EmbeddedBarcodeLayout, Product, SaleUnit, Category, Order, OrderItem, Table, User, Customer, Supplier, BusinessProfile, ExportData, ImportData};
+use models::bundle::{BundleChoice, BundleSlot};
 use models::license::{LicenseKey, LicenseStatus};
+use models::modifier::ModifierGroup;
+use models::prebill::{PrebillAlert, PrebillInfo};
+use models::purchasing::{GoodsReceiptLine, PurchaseOrder, SuggestedPurchaseLine};
+use models::recipe::{CostPriceChange, MarginGrouping, MarginLine, ProductCost, RecipeItem};
+use models::stock::{LossSummary, LowStockAlert, LowStockItem, NewStockMovement, StockDiscrepancy, StockLot, StockMovement, Stocktake, StocktakeCount, StocktakeLine};
+use models::kitchen::{Comanda, KitchenDisplayInfo, KitchenStatusUpdate, Station};
+use kitchen_display::KitchenDisplay;
+use models::certificate::{CertificateInfo, CertificateImportRequest, CertificateExpiryWarning};
+use certificates::{CertificateStore, EXPIRY_WARNING_DAYS, parse_pem, parse_pfx, to_pkcs12};
+use aeat_sidecar::{AeatSidecar, SigningCertificate};
+use models::printer::{CashDrawerEvent, InvoiceData, LabelLanguage, LabelRequest, LabelResult, PrinterConfig, ReceiptTemplate, TemplatePreview};
+use printer::label::{self, Label};
+use printer::template;
+use printer::transport::PrinterInterface;
 use license::{generate_machine_fingerprint, hash_license_key, validate_license_online};
 use screenshot::{save_screenshot_from_base64, get_screenshots_dir};
+use tax_id::{TaxId, validate_tax_id, validate_spanish_tax_id, validate_invoice_parties};
 
 // Database state
 struct DbState {
@@ -47,185 +80,1609 @@ async fn get_products(state: State<'_, DbState>) -> Result<Vec<Product>, String>
     db.get_products().map_err(|e| e.to_string())
 }
 
+// Normalizes the product's barcodes and checks that the new ones are valid
+// and not taken by another product under either their UPC-A or EAN-13 form.
+// Codes the product already had are kept as they are.
+fn check_barcodes(db: &Database, product: Product) -> Result<Product, String> {
+    let stored: Vec<String> = db.get_product(product.id).map_err(|e| e.to_string())?
+        .map(|stored| stored.barcode.into_iter().chain(stored.barcodes).collect())
+        .unwrap_or_default();
+    let check = |code: &str| -> Result<String, String> {
+        let code = barcode::normalize(code);
+        if stored.contains(&code) {
+            return Ok(code);
+        }
+        let code = barcode::validate(&code)?;
+        for candidate in barcode::lookup_candidates(&code) {
+            let owner = db.get_product_by_barcode(&candidate).map_err(|e| e.to_string())?;
+            if let Some(owner) = owner.filter(|owner| owner.id != product.id) {
+                return Err(format!("El código {} ya está asignado a {}", code, owner.name));
+            }
+        }
+        Ok(code)
+    };
+
+    let barcode = match product.barcode.as_deref().filter(|code| !code.trim().is_empty()) {
+        Some(code) => Some(check(code)?),
+        None => None,
+    };
+    let mut barcodes: Vec<String> = Vec::new();
+    for code in product.barcodes.iter().filter(|code| !code.trim().is_empty()) {
+        let code = check(code)?;
+        let same = |other: &String| barcode::lookup_candidates(other).contains(&code);
+        if !barcode.iter().any(same) && !barcodes.iter().any(same) {
+            barcodes.push(code);
+        }
+    }
+
+    Ok(Product { barcode, barcodes, ..product })
+}
+
+#[tauri::command]
+async fn create_product(app: tauri::AppHandle, state: State<'_, DbState>, product: Product) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let product = check_barcodes(db, product)?;
+    let alert = db.create_product(&product).map_err(|e| e.to_string())?;
+    emit_low_stock_alerts(&app, alert);
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_product(state: State<'_, DbState>, product: Product) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let product = check_barcodes(db, product)?;
+    db.update_product(&product).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_product(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_product(id).map_err(|e| e.to_string())
+}
+
+/// Products whose name, brand, category or barcodes start with each of the
+/// words typed, ignoring accents, best matches first.
+#[tauri::command]
+async fn search_products(state: State<'_, DbState>, query: String, limit: Option<u32>) -> Result<Vec<Product>, String> {
+    let Some(fts_query) = search::fts_query(&query) else {
+        return Ok(Vec::new());
+    };
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.search_products(&fts_query, limit.unwrap_or(50).clamp(1, 500)).map_err(|e| e.to_string())
+}
+
+/// Product for a scanned code, or None when no product has it. In-store
+/// codes (prefix 2) also bring the price or weight printed on the label.
+#[tauri::command]
+async fn find_product_by_barcode(state: State<'_, DbState>, code: String) -> Result<Option<BarcodeMatch>, String> {
+    let code = barcode::normalize(&code);
+    if code.is_empty() {
+        return Ok(None);
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    for candidate in barcode::lookup_candidates(&code) {
+        if let Some(product) = db.get_product_by_barcode(&candidate).map_err(|e| e.to_string())? {
+            return Ok(Some(BarcodeMatch {
+                price: product.price,
+                product,
+                code,
+                quantity: 1.0,
+                embedded: false,
+            }));
+        }
+    }
+
+    let layout = db.get_barcode_layout().map_err(|e| e.to_string())?;
+    if let Some((item_code, embedded)) = barcode::embedded(&code, &layout) {
+        if let Some(product) = db.get_product_by_barcode(&item_code).map_err(|e| e.to_string())? {
+            let (quantity, price) = barcode::embedded_line(&product, embedded)?;
+            return Ok(Some(BarcodeMatch {
+                product,
+                code,
+                quantity,
+                price,
+                embedded: true,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[tauri::command]
+async fn get_barcode_layout(state: State<'_, DbState>) -> Result<EmbeddedBarcodeLayout, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_barcode_layout().map_err(|e| e.to_string())
+}
+
+/// Sets how the shop's scales lay out the price and weight codes they print.
+#[tauri::command]
+async fn save_barcode_layout(state: State<'_, DbState>, layout: EmbeddedBarcodeLayout) -> Result<(), String> {
+    barcode::validate_layout(&layout)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.save_barcode_layout(&layout).map_err(|e| e.to_string())
+}
+
+// ==================== Stock ====================
+
+fn emit_low_stock_alerts(app: &tauri::AppHandle, alerts: impl IntoIterator<Item = LowStockAlert>) {
+    for alert in alerts {
+        if let Err(e) = app.emit("low-stock", &alert) {
+            eprintln!("Failed to emit low stock alert: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_stock_movements(state: State<'_, DbState>, product_id: Option<i64>, limit: Option<i64>) -> Result<Vec<StockMovement>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_stock_movements(product_id, limit.unwrap_or(200)).map_err(|e| e.to_string())
+}
+
+/// Records a return, adjustment, purchase, waste or comp movement and updates
+/// the product stock. Returns the movement ids: waste and comp of a product
+/// with a recipe are booked on each of its ingredients.
+#[tauri::command]
+async fn record_stock_movement(app: tauri::AppHandle, state: State<'_, DbState>, movement: NewStockMovement) -> Result<Vec<i64>, String> {
+    let mut movement = movement;
+    movement.quantity = stock::signed_quantity(&movement.movement_type, movement.quantity)?;
+    if stock::is_loss(&movement.movement_type) {
+        stock::validate_loss(&movement)?;
+    } else {
+        movement.reason_code = None;
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let (ids, alerts) = db.record_stock_movement(&movement).map_err(|e| e.to_string())?;
+    emit_low_stock_alerts(&app, alerts);
+    Ok(ids)
+}
+
+/// Waste and staff consumption between `from` and `to` (YYYY-MM-DD, both
+/// included) by reason, product and responsible user.
+#[tauri::command]
+async fn get_loss_summary(state: State<'_, DbState>, from: String, to: String) -> Result<LossSummary, String> {
+    validate_period(&from, &to)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_loss_summary(&from, &to).map_err(|e| e.to_string())
+}
+
+/// Compares each product's stock with the sum of its movements. With `apply`
+/// the ledger value replaces the stored stock.
+#[tauri::command]
+async fn reconcile_stock(state: State<'_, DbState>, apply: Option<bool>) -> Result<Vec<StockDiscrepancy>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.reconcile_stock(apply.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// Products at or below their minimum stock, with a suggested order quantity.
+#[tauri::command]
+async fn get_low_stock_report(state: State<'_, DbState>) -> Result<Vec<LowStockItem>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_low_stock_report().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_low_stock_alerts(state: State<'_, DbState>, acknowledged: Option<bool>) -> Result<Vec<LowStockAlert>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_low_stock_alerts(acknowledged).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn acknowledge_low_stock_alert(state: State<'_, DbState>, id: i64, user_id: Option<i64>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.acknowledge_low_stock_alert(id, user_id).map_err(|e| e.to_string())
+}
+
+// ==================== Lots ====================
+
+#[tauri::command]
+async fn get_stock_lots(state: State<'_, DbState>, product_id: Option<i64>, include_empty: Option<bool>) -> Result<Vec<StockLot>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_stock_lots(product_id, include_empty.unwrap_or(false)).map_err(|e| e.to_string())
+}
+
+/// Lots with stock left expiring within `days` days (7 by default),
+/// already expired ones first.
+#[tauri::command]
+async fn get_expiring_lots(state: State<'_, DbState>, days: Option<i64>) -> Result<Vec<StockLot>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_expiring_lots(days.unwrap_or(7).max(0)).map_err(|e| e.to_string())
+}
+
+/// Receipt, sales and other movements of a lot, for traceability.
+#[tauri::command]
+async fn get_lot_movements(state: State<'_, DbState>, lot_id: i64) -> Result<Vec<StockMovement>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_lot_movements(lot_id).map_err(|e| e.to_string())
+}
+
+// ==================== Stocktakes ====================
+
+// The session must exist and still be open
+fn open_stocktake_or_err(db: &Database, id: i64) -> Result<Stocktake, String> {
+    let stocktake = db.get_stocktake(id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Recuento {} no encontrado", id))?;
+    if stocktake.status != "open" {
+        return Err(format!("El recuento {} ya está cerrado", id));
+    }
+    Ok(stocktake)
+}
+
+// The DB refuses to write to a session that was closed in the meantime
+fn stocktake_write_err(id: i64, e: rusqlite::Error) -> String {
+    match e {
+        rusqlite::Error::QueryReturnedNoRows => format!("El recuento {} ya está cerrado", id),
+        e => e.to_string(),
+    }
+}
+
+#[tauri::command]
+async fn open_stocktake(state: State<'_, DbState>, name: Option<String>, category: Option<String>, user_id: Option<i64>) -> Result<Stocktake, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let name = name.as_deref().map(str::trim).filter(|name| !name.is_empty());
+    let category = category.as_deref().map(str::trim).filter(|category| !category.is_empty());
+    db.open_stocktake(name, category, user_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_stocktakes(state: State<'_, DbState>) -> Result<Vec<Stocktake>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_stocktakes().map_err(|e| e.to_string())
+}
+
+/// Records what one device counted for a product, replacing that device's
+/// previous count.
+#[tauri::command]
+async fn record_stocktake_count(state: State<'_, DbState>, count: StocktakeCount) -> Result<(), String> {
+    if !count.counted.is_finite() || count.counted < 0.0 {
+        return Err("La cantidad contada no puede ser negativa".to_string());
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let stocktake = open_stocktake_or_err(db, count.stocktake_id)?;
+    let product = db.get_product(count.product_id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Producto {} no encontrado", count.product_id))?;
+    if stocktake.category.as_ref().is_some_and(|category| *category != product.category) {
+        return Err(format!("{} no pertenece a la categoría del recuento", product.name));
+    }
+
+    let mut count = count;
+    count.device = count.device.trim().to_string();
+    db.record_stocktake_count(&count).map_err(|e| stocktake_write_err(count.stocktake_id, e))
+}
+
+#[tauri::command]
+async fn get_stocktake_variance(state: State<'_, DbState>, id: i64) -> Result<Vec<StocktakeLine>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_stocktake_variance(id).map_err(|e| e.to_string())
+}
+
+/// Closes the session and posts the variances as stock adjustments.
+#[tauri::command]
+async fn close_stocktake(app: tauri::AppHandle, state: State<'_, DbState>, id: i64, user_id: Option<i64>) -> Result<Vec<StocktakeLine>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    open_stocktake_or_err(db, id)?;
+
+    let (lines, alerts) = db.close_stocktake(id, user_id).map_err(|e| stocktake_write_err(id, e))?;
+    emit_low_stock_alerts(&app, alerts);
+    Ok(lines)
+}
+
+#[tauri::command]
+async fn cancel_stocktake(state: State<'_, DbState>, id: i64, user_id: Option<i64>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    open_stocktake_or_err(db, id)?;
+    db.cancel_stocktake(id, user_id).map_err(|e| stocktake_write_err(id, e))
+}
+
+// ==================== Recipes ====================
+
+#[tauri::command]
+async fn get_recipe(state: State<'_, DbState>, product_id: i64) -> Result<Vec<RecipeItem>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_recipe(product_id).map_err(|e| e.to_string())
+}
+
+/// Replaces the ingredients consumed by one unit of a product. Recipes have a
+/// single level: an ingredient cannot have a recipe of its own.
+#[tauri::command]
+async fn save_recipe(state: State<'_, DbState>, product_id: i64, items: Vec<RecipeItem>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let products = db.get_products().map_err(|e| e.to_string())?;
+    let name_of = |id: i64| products.iter().find(|product| product.id == id).map(|product| product.name.clone());
+    let product_name = name_of(product_id).ok_or_else(|| format!("Producto {} no encontrado", product_id))?;
+    let links = db.get_recipe_links().map_err(|e| e.to_string())?;
+
+    if !items.is_empty() && links.iter().any(|(_, ingredient_id)| *ingredient_id == product_id) {
+        return Err(format!("{} se usa como ingrediente en otra receta", product_name));
+    }
+    for (i, item) in items.iter().enumerate() {
+        let ingredient_name = name_of(item.ingredient_id)
+            .ok_or_else(|| format!("Ingrediente {} no encontrado", item.ingredient_id))?;
+        if item.ingredient_id == product_id {
+            return Err(format!("{} no puede ser ingrediente de sí mismo", product_name));
+        }
+        if !item.quantity.is_finite() || item.quantity <= 0.0 {
+            return Err(format!("Cantidad no válida para {}", ingredient_name));
+        }
+        if items[..i].iter().any(|other| other.ingredient_id == item.ingredient_id) {
+            return Err(format!("{} aparece dos veces en la receta", ingredient_name));
+        }
+        if links.iter().any(|(recipe_product_id, _)| *recipe_product_id == item.ingredient_id) {
+            return Err(format!("{} tiene su propia receta y no puede usarse como ingrediente", ingredient_name));
+        }
+    }
+
+    db.save_recipe(product_id, &items).map_err(|e| e.to_string())
+}
+
+/// Theoretical cost and margin per product from current purchase costs.
+/// `tax_rate` is the IVA included in sale prices (10% by default).
+#[tauri::command]
+async fn get_product_costs(state: State<'_, DbState>, tax_rate: Option<f64>) -> Result<Vec<ProductCost>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_product_costs(tax_rate.unwrap_or(10.0)).map_err(|e| e.to_string())
+}
+
+// ==================== Costs ====================
+
+// `from` and `to` of a report, both YYYY-MM-DD
+fn validate_period(from: &str, to: &str) -> Result<(), String> {
+    let valid_date = |date: &str| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok();
+    if !valid_date(from) || !valid_date(to) {
+        return Err("Las fechas deben tener el formato AAAA-MM-DD".to_string());
+    }
+    if from > to {
+        return Err("La fecha inicial no puede ser posterior a la final".to_string());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_cost_history(state: State<'_, DbState>, product_id: i64) -> Result<Vec<CostPriceChange>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_cost_history(product_id).map_err(|e| e.to_string())
+}
+
+/// Margins of the orders paid between `from` and `to` (YYYY-MM-DD, both
+/// included) grouped by product (default), category, day or month.
+#[tauri::command]
+async fn get_margin_report(
+    state: State<'_, DbState>,
+    from: String,
+    to: String,
+    group_by: Option<MarginGrouping>,
+    tax_rate: Option<f64>,
+) -> Result<Vec<MarginLine>, String> {
+    validate_period(&from, &to)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_margin_report(&from, &to, group_by.unwrap_or(MarginGrouping::Product), tax_rate.unwrap_or(10.0))
+        .map_err(|e| e.to_string())
+}
+
+// ==================== Modifiers ====================
+
+#[tauri::command]
+async fn get_modifier_groups(state: State<'_, DbState>) -> Result<Vec<ModifierGroup>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_modifier_groups().map_err(|e| e.to_string())
+}
+
+/// Creates the group when `id` is 0, otherwise replaces it. Returns its id.
+#[tauri::command]
+async fn save_modifier_group(state: State<'_, DbState>, group: ModifierGroup) -> Result<i64, String> {
+    modifier::validate_group(&group)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let products = db.get_products().map_err(|e| e.to_string())?;
+    for option in &group.options {
+        if let Some(product_id) = option.product_id {
+            if !products.iter().any(|product| product.id == product_id) {
+                return Err(format!("Producto {} no encontrado para {}", product_id, option.name));
+            }
+        }
+    }
+
+    let mut group = group;
+    group.name = group.name.trim().to_string();
+    db.save_modifier_group(&group).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_modifier_group(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_modifier_group(id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_product_modifier_groups(state: State<'_, DbState>, product_id: i64) -> Result<Vec<ModifierGroup>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_product_modifier_groups(product_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_product_modifier_groups(state: State<'_, DbState>, product_id: i64, group_ids: Vec<i64>) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let groups = db.get_modifier_groups().map_err(|e| e.to_string())?;
+    if let Some(id) = group_ids.iter().find(|id| !groups.iter().any(|group| group.id == **id)) {
+        return Err(format!("Grupo {} no encontrado", id));
+    }
+    db.set_product_modifier_groups(product_id, &group_ids).map_err(|e| e.to_string())
+}
+
+/// Order item for a product with the chosen options: checks each group's
+/// limits and adds the price deltas to the unit price.
+#[tauri::command]
+async fn build_order_item(state: State<'_, DbState>, product_id: i64, option_ids: Vec<i64>, quantity: Option<f64>) -> Result<OrderItem, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let product = db.get_product(product_id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Producto {} no encontrado", product_id))?;
+    let groups = db.get_product_modifier_groups(product_id).map_err(|e| e.to_string())?;
+    let modifiers = modifier::select(&groups, &option_ids)?;
+
+    Ok(OrderItem {
+        id: product.id,
+        name: modifier::item_name(&product.name, &modifiers),
+        price: modifier::unit_price(product.price, &modifiers),
+        quantity: pricing::round_quantity(quantity.unwrap_or(1.0), product.sale_unit),
+        unit: product.sale_unit,
+        category: Some(product.category),
+        kitchen_status: None,
+        cost_price: None,
+        modifiers,
+        components: Vec::new(),
+        line_id: None,
+        notes: None,
+        tax_rate: product.tax_rate,
+    })
+}
+
+// ==================== Bundles ====================
+
+#[tauri::command]
+async fn get_bundle_slots(state: State<'_, DbState>, product_id: i64) -> Result<Vec<BundleSlot>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_bundle_slots(product_id).map_err(|e| e.to_string())
+}
+
+/// Replaces the slots of a bundle product; an empty list makes it a regular
+/// product again. Returns the saved slots with their ids.
+#[tauri::command]
+async fn save_bundle_slots(state: State<'_, DbState>, product_id: i64, slots: Vec<BundleSlot>) -> Result<Vec<BundleSlot>, String> {
+    let slots: Vec<BundleSlot> = slots
+        .into_iter()
+        .map(|slot| BundleSlot {
+            name: slot.name.trim().to_string(),
+            ..slot
+        })
+        .collect();
+    if !slots.is_empty() {
+        bundle::validate_slots(&slots)?;
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let products = db.get_products().map_err(|e| e.to_string())?;
+    if !products.iter().any(|product| product.id == product_id) {
+        return Err(format!("Producto {} no encontrado", product_id));
+    }
+    for slot in &slots {
+        if let Some(id) = slot.product_ids.iter().find(|id| !products.iter().any(|product| product.id == **id)) {
+            return Err(format!("Producto {} no encontrado para {}", id, slot.name));
+        }
+        if slot.product_ids.contains(&product_id) {
+            return Err(format!("El menú no puede incluirse a sí mismo en {}", slot.name));
+        }
+    }
+
+    db.save_bundle_slots(product_id, &slots).map_err(|e| e.to_string())
+}
+
+/// Order item for a bundle with the product chosen for each slot, its price
+/// allocated among the components.
+#[tauri::command]
+async fn build_bundle_item(state: State<'_, DbState>, bundle_id: i64, choices: Vec<BundleChoice>, quantity: Option<f64>) -> Result<OrderItem, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let product = db.get_product(bundle_id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Producto {} no encontrado", bundle_id))?;
+    let slots = db.get_bundle_slots(bundle_id).map_err(|e| e.to_string())?;
+    if slots.is_empty() {
+        return Err(format!("{} no es un menú", product.name));
+    }
+    let mut products = Vec::new();
+    for choice in &choices {
+        products.extend(db.get_product(choice.product_id).map_err(|e| e.to_string())?);
+    }
+    let components = bundle::build(&slots, &choices, &products, product.price)?;
+
+    Ok(OrderItem {
+        id: product.id,
+        name: product.name.clone(),
+        price: product.price,
+        quantity: quantity.unwrap_or(1.0).round(),
+        unit: SaleUnit::Unit,
+        category: Some(product.category.clone()),
+        kitchen_status: None,
+        cost_price: None,
+        modifiers: Vec::new(),
+        components,
+        line_id: None,
+        notes: None,
+        tax_rate: product.tax_rate,
+    })
+}
+
+// ==================== Categories ====================
+
+#[tauri::command]
+async fn get_categories(state: State<'_, DbState>) -> Result<Vec<Category>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_categories().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_category(state: State<'_, DbState>, category: Category) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.create_category(&category).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_category(state: State<'_, DbState>, category: Category) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.update_category(&category).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_category(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_category(id).map_err(|e| e.to_string())
+}
+
+// ==================== Orders ====================
+
+#[tauri::command]
+async fn get_orders(state: State<'_, DbState>) -> Result<Vec<Order>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_orders().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_order(
+    app: tauri::AppHandle,
+    state: State<'_, DbState>,
+    order: Order,
+    user_id: Option<i64>,
+    open_drawer: Option<bool>,
+) -> Result<(), String> {
+    let (comandas, was_paid, alert, low_stock) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        let order = pricing::normalize_order(&order)?;
+        let order = db.assign_line_ids(&order).map_err(|e| e.to_string())?;
+        let was_paid = db.get_order_status(order.id).map_err(|e| e.to_string())?.as_deref() == Some("paid");
+        let saved = db.create_order(&order).map_err(|e| e.to_string())?;
+        (saved.comandas, was_paid, saved.prebill_alert, saved.low_stock)
+    };
+
+    emit_prebill_alert(&app, alert);
+    emit_low_stock_alerts(&app, low_stock);
+
+    app.state::<KitchenDisplay>().publish_comandas(&comandas);
+    spawn_comanda_printing(&app, comandas);
+    if !was_paid && open_drawer.unwrap_or(false) {
+        open_drawer_for_cash_payment(&app, &state, &order, user_id).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn update_order(
+    app: tauri::AppHandle,
+    state: State<'_, DbState>,
+    order: Order,
+    user_id: Option<i64>,
+    open_drawer: Option<bool>,
+) -> Result<(), String> {
+    let (comandas, was_paid, alert, low_stock) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        let order = pricing::normalize_order(&order)?;
+        let order = db.assign_line_ids(&order).map_err(|e| e.to_string())?;
+        let was_paid = db.get_order_status(order.id).map_err(|e| e.to_string())?.as_deref() == Some("paid");
+        let saved = db.update_order(&order).map_err(|e| e.to_string())?;
+        (saved.comandas, was_paid, saved.prebill_alert, saved.low_stock)
+    };
+
+    emit_prebill_alert(&app, alert);
+    emit_low_stock_alerts(&app, low_stock);
+
+    app.state::<KitchenDisplay>().publish_comandas(&comandas);
+    spawn_comanda_printing(&app, comandas);
+    if !was_paid && open_drawer.unwrap_or(false) {
+        open_drawer_for_cash_payment(&app, &state, &order, user_id).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn delete_order(app: tauri::AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let alert = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        db.delete_order(id).map_err(|e| e.to_string())?
+    };
+
+    emit_prebill_alert(&app, alert);
+    Ok(())
+}
+
+// ==================== Tables ====================
+
+#[tauri::command]
+async fn get_tables(state: State<'_, DbState>) -> Result<Vec<Table>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_tables().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_table(state: State<'_, DbState>, table: Table) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.create_table(&table).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_table(state: State<'_, DbState>, table: Table) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.update_table(&table).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_table(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_table(id).map_err(|e| e.to_string())
+}
+
+// ==================== Users ====================
+
+#[tauri::command]
+async fn get_users(state: State<'_, DbState>) -> Result<Vec<User>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_users().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_user(state: State<'_, DbState>, user: User) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.create_user(&user).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_user(state: State<'_, DbState>, user: User) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.update_user(&user).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_user(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_user(id).map_err(|e| e.to_string())
+}
+
+// ==================== Customers ====================
+
+#[tauri::command]
+async fn get_customers(state: State<'_, DbState>) -> Result<Vec<Customer>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_customers().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_customer(state: State<'_, DbState>, mut customer: Customer) -> Result<(), String> {
+    customer.cif_nif = validate_tax_id(&customer.cif_nif).map_err(|e| e.to_string())?.value;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.create_customer(&customer).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_customer(state: State<'_, DbState>, mut customer: Customer) -> Result<(), String> {
+    customer.cif_nif = validate_tax_id(&customer.cif_nif).map_err(|e| e.to_string())?.value;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.update_customer(&customer).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_customer(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.delete_customer(id).map_err(|e| e.to_string())
+}
+
+// ==================== Suppliers ====================
+
+fn validate_supplier(supplier: &mut Supplier) -> Result<(), String> {
+    if supplier.nombre_fiscal.trim().is_empty() {
+        return Err("El nombre del proveedor es obligatorio".to_string());
+    }
+    if !supplier.cif_nif.trim().is_empty() {
+        supplier.cif_nif = validate_tax_id(&supplier.cif_nif).map_err(|e| e.to_string())?.value;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_suppliers(state: State<'_, DbState>) -> Result<Vec<Supplier>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_suppliers().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn create_supplier(state: State<'_, DbState>, mut supplier: Supplier) -> Result<(), String> {
+    validate_supplier(&mut supplier)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.create_supplier(&supplier).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn update_supplier(state: State<'_, DbState>, mut supplier: Supplier) -> Result<(), String> {
+    validate_supplier(&mut supplier)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.update_supplier(&supplier).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn delete_supplier(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    // Purchase orders keep their supplier: deactivate it instead
+    if db.count_supplier_purchase_orders(id).map_err(|e| e.to_string())? > 0 {
+        return Err("El proveedor tiene pedidos registrados; desactívalo en lugar de eliminarlo".to_string());
+    }
+    db.delete_supplier(id).map_err(|e| e.to_string())
+}
+
+// ==================== Purchase Orders ====================
+
+fn purchase_order_or_err(db: &Database, id: i64) -> Result<PurchaseOrder, String> {
+    db.get_purchase_order(id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Pedido a proveedor {} no encontrado", id))
+}
+
+#[tauri::command]
+async fn get_purchase_orders(state: State<'_, DbState>, status: Option<String>) -> Result<Vec<PurchaseOrder>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_purchase_orders(status.as_deref()).map_err(|e| e.to_string())
+}
+
+/// Creates (id 0) or replaces a draft or sent purchase order. Returns its id.
+#[tauri::command]
+async fn save_purchase_order(state: State<'_, DbState>, order: PurchaseOrder) -> Result<i64, String> {
+    if !purchasing::is_editable(&order.status) {
+        return Err(format!("Estado no válido para guardar el pedido: {}", order.status));
+    }
+    if order.lines.is_empty() {
+        return Err("El pedido no tiene productos".to_string());
+    }
+    if let Some(line) = order.lines.iter().find(|line| line.quantity <= 0.0 || !line.quantity.is_finite() || line.cost_price < 0.0) {
+        return Err(format!("Cantidad o coste no válido para {}", line.product_name));
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    if !db.get_suppliers().map_err(|e| e.to_string())?.iter().any(|s| s.id == order.supplier_id) {
+        return Err(format!("Proveedor {} no encontrado", order.supplier_id));
+    }
+    if order.id > 0 {
+        let existing = purchase_order_or_err(db, order.id)?;
+        if !purchasing::is_editable(&existing.status) {
+            return Err("Solo se pueden modificar pedidos en borrador o enviados".to_string());
+        }
+    }
+
+    // Names are snapshotted so the order stays readable if a product is renamed
+    let products = db.get_products().map_err(|e| e.to_string())?;
+    let mut order = order;
+    for line in &mut order.lines {
+        let product = products.iter()
+            .find(|product| product.id == line.product_id)
+            .ok_or_else(|| format!("Producto {} no encontrado", line.product_id))?;
+        line.product_name = product.name.clone();
+    }
+
+    db.save_purchase_order(&order).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cancel_purchase_order(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let order = purchase_order_or_err(db, id)?;
+    if !purchasing::is_editable(&order.status) {
+        return Err("Solo se pueden cancelar pedidos sin mercancía recibida".to_string());
+    }
+    db.set_purchase_order_status(id, "cancelled").map_err(|e| e.to_string())
+}
+
+/// Books a delivery against the order lines and adds it to stock.
+#[tauri::command]
+async fn receive_purchase_order(state: State<'_, DbState>, id: i64, lines: Vec<GoodsReceiptLine>, user_id: Option<i64>) -> Result<PurchaseOrder, String> {
+    let mut lines: Vec<GoodsReceiptLine> = lines.into_iter().filter(|line| line.quantity != 0.0).collect();
+    if lines.is_empty() {
+        return Err("No se ha indicado ninguna cantidad recibida".to_string());
+    }
+    if lines.iter().any(|line| line.quantity < 0.0 || !line.quantity.is_finite()) {
+        return Err("Las cantidades recibidas deben ser positivas".to_string());
+    }
+    for line in &mut lines {
+        line.lot_number = line.lot_number.take()
+            .map(|lot| lot.trim().to_string())
+            .filter(|lot| !lot.is_empty());
+        line.expiry_date = line.expiry_date.take().filter(|date| !date.trim().is_empty());
+        if let Some(date) = &line.expiry_date {
+            if chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                return Err(format!("Fecha de caducidad no válida: {}", date));
+            }
+        }
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let order = purchase_order_or_err(db, id)?;
+    if !purchasing::can_receive(&order.status) {
+        return Err(format!("El pedido {} no admite recepciones ({})", id, order.status));
+    }
+    if let Some(line) = lines.iter().find(|line| !order.lines.iter().any(|l| l.id == line.line_id)) {
+        return Err(format!("La línea {} no pertenece al pedido {}", line.line_id, id));
+    }
+    for line in &order.lines {
+        let received: f64 = lines.iter().filter(|l| l.line_id == line.id).map(|l| l.quantity).sum();
+        if purchasing::exceeds_pending(line, received) {
+            return Err(format!(
+                "{}: se reciben {} pero solo quedan {} pendientes",
+                line.product_name,
+                received,
+                purchasing::pending_quantity(line)
+            ));
+        }
+    }
+
+    db.receive_purchase_order(id, &lines, user_id).map_err(|e| e.to_string())
+}
+
+/// Products to reorder from a supplier (or from any supplier), using the
+/// sales of the last `days` days (28 by default) to cover `cover_days` (7).
+#[tauri::command]
+async fn suggest_purchase_order(
+    state: State<'_, DbState>,
+    supplier_id: Option<i64>,
+    days: Option<i64>,
+    cover_days: Option<f64>,
+) -> Result<Vec<SuggestedPurchaseLine>, String> {
+    let days = days.unwrap_or(28).max(1);
+    let cover_days = cover_days.unwrap_or(7.0).max(0.0);
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    let candidates = db.get_purchase_suggestion_data(supplier_id, days).map_err(|e| e.to_string())?;
+
+    Ok(candidates
+        .into_iter()
+        .filter(|line| line.min_stock.is_some() || line.daily_sales > 0.0)
+        .filter_map(|mut line| {
+            line.suggested_quantity = purchasing::suggested_quantity(
+                line.stock,
+                line.pending,
+                line.min_stock.unwrap_or(0.0),
+                line.reorder_quantity.unwrap_or(0.0),
+                line.daily_sales,
+                cover_days,
+            );
+            (line.suggested_quantity > 0.0).then_some(line)
+        })
+        .collect())
+}
+
+// ==================== Tax IDs ====================
+
+#[tauri::command]
+async fn check_tax_id(value: String) -> Result<TaxId, String> {
+    validate_tax_id(&value).map_err(|e| e.to_string())
+}
+
+// Checks issuer and recipient before an invoice is issued. The recipient is
+// taken from the customer record when one is given.
+fn check_invoice_identities(
+    db: &Database,
+    invoice_type: Option<&str>,
+    customer_id: Option<i64>,
+    recipient_nif: Option<String>,
+) -> Result<(), String> {
+    let profile = db.get_business_profile().map_err(|e| e.to_string())?
+        .ok_or("Business profile not configured")?;
+    let invoice_type = invoice_type.unwrap_or(&profile.tipo_factura);
+
+    let recipient_nif = match customer_id {
+        Some(id) => {
+            let customer = db.get_customer(id).map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Customer {} not found", id))?;
+            Some(customer.cif_nif)
+        }
+        None => recipient_nif,
+    };
+
+    validate_invoice_parties(invoice_type, &profile.nif, recipient_nif.as_deref())
+}
+
+#[tauri::command]
+async fn validate_invoice_identities(
+    state: State<'_, DbState>,
+    invoice_type: Option<String>,
+    customer_id: Option<i64>,
+    recipient_nif: Option<String>,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    check_invoice_identities(db, invoice_type.as_deref(), customer_id, recipient_nif)
+}
+
+// ==================== Business Profile ====================
+
+// Normalizes the NIF and checks the fields every invoice depends on
+fn validate_business_profile(profile: &mut BusinessProfile) -> Result<(), String> {
+    profile.nif = validate_spanish_tax_id(&profile.nif).map_err(|e| e.to_string())?.value;
+    if profile.nombre_razon.trim().len() < 2 {
+        return Err("La razón social debe tener al menos 2 caracteres".to_string());
+    }
+    if profile.serie_factura.trim().is_empty() {
+        return Err("El prefijo de serie de factura es obligatorio".to_string());
+    }
+    if !matches!(profile.tipo_factura.as_str(), "F1" | "F2" | "F3") {
+        return Err(format!("Tipo de factura no soportado: {}", profile.tipo_factura));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_business_profile(state: State<'_, DbState>) -> Result<Option<BusinessProfile>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_business_profile().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn save_business_profile(state: State<'_, DbState>, mut profile: BusinessProfile) -> Result<(), String> {
+    validate_business_profile(&mut profile)?;
+    profile.updated_at = Some(chrono::Utc::now().to_rfc3339());
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.save_business_profile(&profile).map_err(|e| e.to_string())
+}
+
+/// Returns the next number of the series for the current year, reserving it
+/// when `consume` is set. `serie` defaults to the business profile's;
+/// `issued` is the highest number the caller has already issued for it.
+#[tauri::command]
+async fn next_invoice_number(
+    state: State<'_, DbState>,
+    consume: bool,
+    serie: Option<String>,
+    issued: Option<i64>,
+) -> Result<String, String> {
+    use chrono::Datelike;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let serie = match serie.filter(|serie| !serie.trim().is_empty()) {
+        Some(serie) => serie.trim().to_string(),
+        None => db.get_business_profile().map_err(|e| e.to_string())?
+            .ok_or("Business profile not configured")?
+            .serie_factura,
+    };
+    let year = chrono::Local::now().year();
+    db.next_invoice_number(&serie, year, consume, issued.unwrap_or(0))
+        .map_err(|e| e.to_string())
+}
+
+// ==================== Utility ====================
+
 #[tauri::command]
-async fn create_product(state: State<'_, DbState>, product: Product) -> Result<(), String> {
+async fn export_data(state: State<'_, DbState>) -> Result<ExportData, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.create_product(&product).map_err(|e| e.to_string())
+    db.export_data().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn update_product(state: State<'_, DbState>, product: Product) -> Result<(), String> {
+async fn import_data(state: State<'_, DbState>, mut data: ImportData) -> Result<(), String> {
+    for customer in data.customers.iter_mut().flatten() {
+        customer.cif_nif = validate_tax_id(&customer.cif_nif)
+            .map_err(|e| format!("Cliente {}: {}", customer.nombre_fiscal, e))?
+            .value;
+    }
+    if let Some(profile) = data.business_profile.as_mut() {
+        validate_business_profile(profile)?;
+    }
+
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.update_product(&product).map_err(|e| e.to_string())
+    db.import_data(&data).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn delete_product(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+async fn clear_all_data(state: State<'_, DbState>) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.delete_product(id).map_err(|e| e.to_string())
+    db.clear_all_data().map_err(|e| e.to_string())
 }
 
-// ==================== Categories ====================
+// ==================== Printer Configuration ====================
+
+fn app_data_dir(app: &tauri::AppHandle) -> Result<std::path::PathBuf, String> {
+    app.path().app_data_dir().map_err(|e| format!("Failed to get app directory: {}", e))
+}
 
 #[tauri::command]
-async fn get_categories(state: State<'_, DbState>) -> Result<Vec<Category>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let db = db.as_ref().ok_or("Database not initialized")?;
-    db.get_categories().map_err(|e| e.to_string())
+async fn get_printer_configs(app: tauri::AppHandle) -> Result<Vec<PrinterConfig>, String> {
+    printer::load_configs(&app_data_dir(&app)?)
 }
 
+/// Adds a printer or replaces the one with the same name.
 #[tauri::command]
-async fn create_category(state: State<'_, DbState>, category: Category) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let db = db.as_ref().ok_or("Database not initialized")?;
-    db.create_category(&category).map_err(|e| e.to_string())
+async fn save_printer_config(app: tauri::AppHandle, config: PrinterConfig) -> Result<Vec<PrinterConfig>, String> {
+    let app_dir = app_data_dir(&app)?;
+    let mut configs = printer::load_configs(&app_dir)?;
+
+    let mut config = config;
+    config.name = config.name.trim().to_string();
+    match configs.iter_mut().find(|c| c.name.eq_ignore_ascii_case(&config.name)) {
+        Some(existing) => *existing = config,
+        None => configs.push(config),
+    }
+
+    printer::save_configs(&app_dir, &configs)?;
+    Ok(configs)
 }
 
 #[tauri::command]
-async fn update_category(state: State<'_, DbState>, category: Category) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let db = db.as_ref().ok_or("Database not initialized")?;
-    db.update_category(&category).map_err(|e| e.to_string())
+async fn delete_printer_config(app: tauri::AppHandle, name: String) -> Result<Vec<PrinterConfig>, String> {
+    let app_dir = app_data_dir(&app)?;
+    let mut configs = printer::load_configs(&app_dir)?;
+    configs.retain(|c| !c.name.eq_ignore_ascii_case(name.trim()));
+
+    printer::save_configs(&app_dir, &configs)?;
+    Ok(configs)
+}
+
+// ==================== Printing ====================
+
+async fn send_to_printer(interface: &str, data: Vec<u8>, timeout: Duration) -> Result<(), String> {
+    let interface = PrinterInterface::parse(interface)?;
+    tauri::async_runtime::spawn_blocking(move || interface.send(&data, timeout))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn resolve_template(db: &Database, kind: &str, printer_name: Option<&str>) -> Result<String, String> {
+    match db.get_template_for(kind, printer_name).map_err(|e| e.to_string())? {
+        Some(content) => Ok(content),
+        None => template::default_template(kind)
+            .map(str::to_string)
+            .ok_or_else(|| format!("Unknown template kind: {}", kind)),
+    }
+}
+
+fn render_template(db: &Database, content: &str, order: &Order, invoice: Option<&InvoiceData>, prebill: Option<&PrebillInfo>) -> Result<Vec<template::Block>, String> {
+    let profile = db.get_business_profile().map_err(|e| e.to_string())?;
+    let customer = match invoice.and_then(|invoice| invoice.customer_id) {
+        Some(id) => db.get_customer(id).map_err(|e| e.to_string())?,
+        None => None,
+    };
+
+    let context = template::build_context(order, profile.as_ref(), invoice, customer.as_ref(), prebill);
+    template::Template::parse(content)?.render(&context)
+}
+
+// Prints a document on the ticket printer using its template for `kind`
+async fn print_document(
+    app: &tauri::AppHandle,
+    state: &State<'_, DbState>,
+    kind: &str,
+    order: &Order,
+    invoice: Option<&InvoiceData>,
+    prebill: Option<&PrebillInfo>,
+) -> Result<(), String> {
+    let app_dir = app_data_dir(app)?;
+    let config = printer::load_ticket_printer(&app_dir)?;
+    let options = printer::ticket_options(&config.settings)?;
+
+    let data = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        let content = resolve_template(db, kind, Some(&config.name))?;
+        let blocks = render_template(db, &content, order, invoice, prebill)?;
+        template::to_escpos(&blocks, &options)
+    };
+
+    send_to_printer(&config.settings.interface, data, printer::timeout(&config.settings)).await
 }
 
 #[tauri::command]
-async fn delete_category(state: State<'_, DbState>, id: i64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let db = db.as_ref().ok_or("Database not initialized")?;
-    db.delete_category(id).map_err(|e| e.to_string())
+async fn print_order(app: tauri::AppHandle, state: State<'_, DbState>, order: Order) -> Result<(), String> {
+    print_document(&app, &state, "ticket", &order, None, None).await
 }
 
-// ==================== Orders ====================
+/// Prints an invoice for an order. The number must already be reserved with
+/// `next_invoice_number`; issuer and recipient NIFs are checked first.
+#[tauri::command]
+async fn print_invoice(app: tauri::AppHandle, state: State<'_, DbState>, order: Order, invoice: InvoiceData) -> Result<(), String> {
+    {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        check_invoice_identities(db, Some(&invoice.invoice_type), invoice.customer_id, invoice.recipient_nif.clone())?;
+    }
+    print_document(&app, &state, "invoice", &order, Some(&invoice), None).await
+}
 
 #[tauri::command]
-async fn get_orders(state: State<'_, DbState>) -> Result<Vec<Order>, String> {
+async fn print_test_page(app: tauri::AppHandle) -> Result<(), String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| format!("Failed to get app directory: {}", e))?;
+    let settings = printer::load_settings(&app_dir)?;
+    let options = printer::ticket_options(&settings)?;
+
+    let data = printer::ticket::render_test_page(&options);
+    send_to_printer(&settings.interface, data, printer::timeout(&settings)).await
+}
+
+#[tauri::command]
+async fn test_printer_connection(app: tauri::AppHandle) -> Result<(), String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| format!("Failed to get app directory: {}", e))?;
+    let settings = printer::load_settings(&app_dir)?;
+    let interface = PrinterInterface::parse(&settings.interface)?;
+    let timeout = printer::timeout(&settings);
+
+    tauri::async_runtime::spawn_blocking(move || interface.test_connection(timeout))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+// ==================== Labels ====================
+
+/// Price labels for the requested products, sent to the label printer or
+/// saved to `output_path`.
+#[tauri::command]
+async fn print_labels(app: tauri::AppHandle, state: State<'_, DbState>, request: LabelRequest) -> Result<LabelResult, String> {
+    let products: Vec<Product> = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        db.get_products().map_err(|e| e.to_string())?
+            .into_iter()
+            .filter(|product| {
+                request.product_ids.contains(&product.id)
+                    || request.category.as_deref().is_some_and(|category| product.category == category)
+            })
+            .collect()
+    };
+    if products.is_empty() {
+        return Err("No hay productos para etiquetar".to_string());
+    }
+
+    let labels: Vec<Label> = products.iter().map(Label::from_product).collect();
+    let copies = request.copies.unwrap_or(1).max(1);
+    let count = labels.len() * copies as usize;
+
+    let configs = printer::load_configs(&app_data_dir(&app)?)?;
+    let config = printer::label_printer(&configs, request.printer_name.as_deref());
+    let settings = config.and_then(|config| config.settings.label.clone()).unwrap_or_default();
+    let language = request.language.unwrap_or(settings.language);
+
+    let data = match language {
+        LabelLanguage::Zpl => label::render_zpl(&labels, &settings, copies).into_bytes(),
+        LabelLanguage::Escpos => {
+            let options = match config {
+                Some(config) => printer::ticket_options(&config.settings)?,
+                None => Default::default(),
+            };
+            label::render_escpos(&labels, &options, copies)
+        }
+    };
+
+    if let Some(path) = request.output_path.filter(|path| !path.trim().is_empty()) {
+        fs::write(&path, data).map_err(|e| format!("Failed to save labels to {}: {}", path, e))?;
+        return Ok(LabelResult { count, path: Some(path) });
+    }
+
+    let config = config.ok_or("No hay ninguna impresora de etiquetas configurada")?;
+    send_to_printer(&config.settings.interface, data, printer::timeout(&config.settings)).await?;
+    Ok(LabelResult { count, path: None })
+}
+
+// ==================== Pre-bills ====================
+
+fn emit_prebill_alert(app: &tauri::AppHandle, alert: Option<PrebillAlert>) {
+    if let Some(alert) = alert {
+        if let Err(e) = app.emit("prebill-alert", &alert) {
+            eprintln!("Failed to emit pre-bill alert: {}", e);
+        }
+    }
+}
+
+/// Prints the bill of an order still in progress, marked as not valid as an
+/// invoice. The order is not modified.
+#[tauri::command]
+async fn print_prebill(app: tauri::AppHandle, state: State<'_, DbState>, order: Order, user_id: Option<i64>) -> Result<PrebillInfo, String> {
+    if order.status == "paid" {
+        return Err("El pedido ya está cobrado: imprima el ticket".to_string());
+    }
+    if order.items.is_empty() {
+        return Err("El pedido no tiene productos".to_string());
+    }
+
+    let mut info = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        db.get_prebill_info(order.id).map_err(|e| e.to_string())?
+    };
+    info.print_count += 1;
+
+    print_document(&app, &state, "prebill", &order, None, Some(&info)).await?;
+
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.get_orders().map_err(|e| e.to_string())
+    db.record_prebill_print(&order, user_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn create_order(state: State<'_, DbState>, order: Order) -> Result<(), String> {
+async fn get_prebill_info(state: State<'_, DbState>, order_id: i64) -> Result<PrebillInfo, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.create_order(&order).map_err(|e| e.to_string())
+    db.get_prebill_info(order_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn update_order(state: State<'_, DbState>, order: Order) -> Result<(), String> {
+async fn get_prebill_alerts(state: State<'_, DbState>, reviewed: Option<bool>) -> Result<Vec<PrebillAlert>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.update_order(&order).map_err(|e| e.to_string())
+    db.get_prebill_alerts(reviewed).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn delete_order(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+async fn review_prebill_alert(state: State<'_, DbState>, id: i64, user_id: Option<i64>) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.delete_order(id).map_err(|e| e.to_string())
+    db.review_prebill_alert(id, user_id).map_err(|e| e.to_string())
 }
 
-// ==================== Tables ====================
+// ==================== Cash Drawer ====================
+
+// Sends the drawer pulse through the ticket printer and audits the attempt
+async fn kick_cash_drawer(app: &tauri::AppHandle, state: &State<'_, DbState>, user_id: Option<i64>, reason: &str, order_id: Option<i64>) -> Result<(), String> {
+    let result: Result<(), String> = async {
+        let config = printer::load_ticket_printer(&app_data_dir(app)?)?;
+        let data = printer::drawer_kick(&config.settings);
+        send_to_printer(&config.settings.interface, data, printer::timeout(&config.settings)).await
+    }
+    .await;
 
-#[tauri::command]
-async fn get_tables(state: State<'_, DbState>) -> Result<Vec<Table>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.get_tables().map_err(|e| e.to_string())
+    db.record_cash_drawer_event(user_id, reason, order_id, result.as_ref().err().map(String::as_str))
+        .map_err(|e| e.to_string())?;
+    result
+}
+
+// Opens the drawer when a cash payment completes an order, if the terminal's
+// "abrir caja automáticamente" setting is on. Failures are only logged: the
+// payment is already saved.
+async fn open_drawer_for_cash_payment(app: &tauri::AppHandle, state: &State<'_, DbState>, order: &Order, user_id: Option<i64>) {
+    if order.status != "paid" || order.payment_method != "efectivo" {
+        return;
+    }
+
+    let reason = format!("Cobro en efectivo del pedido #{}", order.id);
+    if let Err(e) = kick_cash_drawer(app, state, user_id, &reason, Some(order.id)).await {
+        eprintln!("Failed to open cash drawer: {}", e);
+    }
 }
 
 #[tauri::command]
-async fn create_table(state: State<'_, DbState>, table: Table) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-    let db = db.as_ref().ok_or("Database not initialized")?;
-    db.create_table(&table).map_err(|e| e.to_string())
+async fn open_cash_drawer(app: tauri::AppHandle, state: State<'_, DbState>, user_id: Option<i64>, reason: Option<String>) -> Result<(), String> {
+    let reason = reason
+        .filter(|reason| !reason.trim().is_empty())
+        .unwrap_or_else(|| "Apertura manual".to_string());
+    kick_cash_drawer(&app, &state, user_id, &reason, None).await
 }
 
 #[tauri::command]
-async fn update_table(state: State<'_, DbState>, table: Table) -> Result<(), String> {
+async fn get_cash_drawer_events(state: State<'_, DbState>, limit: Option<i64>) -> Result<Vec<CashDrawerEvent>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.update_table(&table).map_err(|e| e.to_string())
+    db.get_cash_drawer_events(limit.unwrap_or(100)).map_err(|e| e.to_string())
 }
 
+// ==================== Receipt Templates ====================
+
 #[tauri::command]
-async fn delete_table(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+async fn get_templates(state: State<'_, DbState>) -> Result<Vec<ReceiptTemplate>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.delete_table(id).map_err(|e| e.to_string())
+    db.get_templates().map_err(|e| e.to_string())
 }
 
-// ==================== Users ====================
+#[tauri::command]
+async fn get_default_template(kind: String) -> Result<String, String> {
+    template::default_template(&kind)
+        .map(str::to_string)
+        .ok_or_else(|| format!("Unknown template kind: {}", kind))
+}
 
 #[tauri::command]
-async fn get_users(state: State<'_, DbState>) -> Result<Vec<User>, String> {
+async fn save_template(state: State<'_, DbState>, template: ReceiptTemplate) -> Result<i64, String> {
+    if template.name.trim().is_empty() {
+        return Err("El nombre de la plantilla es obligatorio".to_string());
+    }
+    if !template::TEMPLATE_KINDS.contains(&template.kind.as_str()) {
+        return Err(format!("Tipo de plantilla desconocido: {}", template.kind));
+    }
+    template::Template::parse(&template.content)?;
+
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.get_users().map_err(|e| e.to_string())
+    db.save_template(&template).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn create_user(state: State<'_, DbState>, user: User) -> Result<(), String> {
+async fn delete_template(state: State<'_, DbState>, id: i64) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.create_user(&user).map_err(|e| e.to_string())
+    db.delete_template(id).map_err(|e| e.to_string())
 }
 
+/// Renders a template as plain text and PNG. Without `content` the stored
+/// template for the printer is used; without `order` a sample order.
 #[tauri::command]
-async fn update_user(state: State<'_, DbState>, user: User) -> Result<(), String> {
+async fn preview_template(
+    app: tauri::AppHandle,
+    state: State<'_, DbState>,
+    kind: String,
+    content: Option<String>,
+    order: Option<Order>,
+    invoice: Option<InvoiceData>,
+    printer_name: Option<String>,
+) -> Result<TemplatePreview, String> {
+    let options = printer::printer_options(&app_data_dir(&app)?, printer_name.as_deref())?;
+    let order = match order {
+        Some(order) => order,
+        None => template::sample_order()?,
+    };
+    let invoice = invoice.or_else(|| {
+        (kind == "invoice").then(|| InvoiceData {
+            number: "F2026-000001".to_string(),
+            date: chrono::Local::now().format("%d/%m/%Y").to_string(),
+            invoice_type: "F2".to_string(),
+            customer_id: None,
+            recipient_nif: None,
+            tax_rate: 10.0,
+        })
+    });
+
+    let blocks = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        let content = match content {
+            Some(content) => content,
+            None => resolve_template(db, &kind, printer_name.as_deref())?,
+        };
+        let prebill = (kind == "prebill").then_some(PrebillInfo {
+            order_id: order.id,
+            print_count: 1,
+            last_printed_at: None,
+            last_total: None,
+            modified_after_print: false,
+        });
+        render_template(db, &content, &order, invoice.as_ref(), prebill.as_ref())?
+    };
+
+    Ok(TemplatePreview {
+        text: template::to_text(&blocks, &options),
+        png: template::to_png(&blocks, &options)?,
+    })
+}
+
+// ==================== Stations & Comandas ====================
+
+// Sends each comanda to its station printer and records the outcome. Printing
+// problems never fail the order update: they are logged in the print history.
+// Stations are printed in parallel so an offline one doesn't hold the others.
+async fn print_comandas(app: &tauri::AppHandle, comandas: Vec<Comanda>, reprint: bool) {
+    if comandas.is_empty() {
+        return;
+    }
+
+    let state = app.state::<DbState>();
+    let stations = {
+        let db = match state.db.lock() {
+            Ok(db) => db,
+            Err(_) => return,
+        };
+        match db.as_ref().map(|db| db.get_stations()) {
+            Some(Ok(stations)) => stations,
+            _ => return,
+        }
+    };
+    let app_dir = app.path().app_data_dir().ok();
+
+    let jobs = comandas.into_iter().filter_map(|comanda| {
+        let interface = stations.iter()
+            .find(|s| s.id == comanda.station_id)
+            .and_then(|s| s.printer_interface.clone())
+            .filter(|i| !i.trim().is_empty())?;
+
+        let options = app_dir.as_deref()
+            .map(|app_dir| printer::comanda_options(app_dir, &interface))
+            .unwrap_or_default();
+        let data = printer::ticket::render_comanda(&comanda, &options, reprint);
+        Some(async move {
+            let result = send_to_printer(&interface, data, Duration::from_secs(3)).await;
+            (comanda, result)
+        })
+    });
+
+    for (comanda, result) in futures_util::future::join_all(jobs).await {
+        if let Err(e) = &result {
+            eprintln!("Failed to print comanda {} at {}: {}", comanda.id, comanda.station_name, e);
+        }
+
+        if let Ok(db) = state.db.lock() {
+            if let Some(db) = db.as_ref() {
+                let error = result.err();
+                if let Err(e) = db.record_comanda_print(comanda.id, reprint, error.as_deref()) {
+                    eprintln!("Failed to record comanda print: {}", e);
+                }
+            }
+        }
+    }
+}
+
+// Prints new comandas after the order is committed without holding up the
+// save command
+fn spawn_comanda_printing(app: &tauri::AppHandle, comandas: Vec<Comanda>) {
+    if comandas.is_empty() {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        print_comandas(&app, comandas, false).await;
+    });
+}
+
+#[tauri::command]
+async fn get_stations(state: State<'_, DbState>) -> Result<Vec<Station>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.update_user(&user).map_err(|e| e.to_string())
+    db.get_stations().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn delete_user(state: State<'_, DbState>, id: i64) -> Result<(), String> {
+async fn create_station(state: State<'_, DbState>, station: Station) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.delete_user(id).map_err(|e| e.to_string())
+    db.create_station(&station).map_err(|e| e.to_string())
 }
 
-// ==================== Utility ====================
-
 #[tauri::command]
-async fn export_data(state: State<'_, DbState>) -> Result<ExportData, String> {
+async fn update_station(state: State<'_, DbState>, station: Station) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.export_data().map_err(|e| e.to_string())
+    db.update_station(&station).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn import_data(state: State<'_, DbState>, data: ImportData) -> Result<(), String> {
+async fn delete_station(state: State<'_, DbState>, id: i64) -> Result<(), String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.import_data(&data).map_err(|e| e.to_string())
+    db.delete_station(id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn clear_all_data(state: State<'_, DbState>) -> Result<(), String> {
+async fn get_comandas(state: State<'_, DbState>, order_id: Option<i64>) -> Result<Vec<Comanda>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let db = db.as_ref().ok_or("Database not initialized")?;
-    db.clear_all_data().map_err(|e| e.to_string())
+    db.get_comandas(order_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-async fn write_json_config(app: tauri::AppHandle, config: Value) -> Result<String, String> {
-    // Tauri v2: use app.path() instead of app.path_resolver()
-    let app_dir = app.path().app_data_dir().map_err(|e| format!("Failed to get app directory: {}", e))?;
+async fn reprint_comanda(app: tauri::AppHandle, state: State<'_, DbState>, id: i64) -> Result<Comanda, String> {
+    let comanda = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let db = db.as_ref().ok_or("Database not initialized")?;
+        db.get_comanda(id).map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Comanda {} not found", id))?
+    };
 
-    // Create directory if it doesn't exist
-    fs::create_dir_all(&app_dir).map_err(|e| format!("Failed to create app directory: {}", e))?;
+    print_comandas(&app, vec![comanda], true).await;
 
-    let config_path = app_dir.join("printerSettings.json");
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    db.get_comanda(id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Comanda {} not found", id))
+}
 
-    // Write config file
-    fs::write(&config_path, config.to_string())
-        .map_err(|e| format!("Failed to write config: {}", e))?;
+#[tauri::command]
+async fn set_kitchen_status(app: tauri::AppHandle, update: KitchenStatusUpdate) -> Result<Comanda, String> {
+    kitchen_display::apply_status_update(&app, update)
+}
 
-    Ok(format!("Configuration saved to: {}", config_path.display()))
+#[tauri::command]
+async fn get_kitchen_display_info(display: State<'_, KitchenDisplay>) -> Result<KitchenDisplayInfo, String> {
+    Ok(display.info())
 }
 
 // ==================== License Commands ====================
@@ -378,6 +1835,234 @@ async fn clear_license(state: State<'_, DbState>) -> Result<(), String> {
     db.clear_license().map_err(|e| e.to_string())
 }
 
+// ==================== Certificates ====================
+
+fn open_certificate_store(app: &tauri::AppHandle) -> Result<CertificateStore, String> {
+    let app_dir = app.path().app_data_dir().map_err(|e| format!("Failed to get app directory: {}", e))?;
+    CertificateStore::open(app_dir.join("certificates"))
+}
+
+fn with_days_remaining(mut cert: CertificateInfo, now: i64) -> CertificateInfo {
+    cert.days_remaining = Some((cert.not_after - now) / 86400);
+    cert
+}
+
+fn expiring_certificates(db: &Database, days: i64) -> Result<Vec<CertificateExpiryWarning>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let certificates = db.get_certificates().map_err(|e| e.to_string())?;
+
+    Ok(certificates
+        .into_iter()
+        .map(|cert| with_days_remaining(cert, now))
+        .filter_map(|cert| {
+            let days_remaining = cert.days_remaining?;
+            (days_remaining <= days).then_some(CertificateExpiryWarning {
+                id: cert.id,
+                alias: cert.alias,
+                nif: cert.nif,
+                not_after: cert.not_after,
+                days_remaining,
+            })
+        })
+        .collect())
+}
+
+#[tauri::command]
+async fn list_certificates(state: State<'_, DbState>) -> Result<Vec<CertificateInfo>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let now = chrono::Utc::now().timestamp();
+    let certificates = db.get_certificates().map_err(|e| e.to_string())?;
+    Ok(certificates.into_iter().map(|cert| with_days_remaining(cert, now)).collect())
+}
+
+#[tauri::command]
+async fn import_certificate(
+    app: tauri::AppHandle,
+    state: State<'_, DbState>,
+    request: CertificateImportRequest,
+) -> Result<CertificateInfo, String> {
+    let (format, payload, password) = if let Some(pfx_path) = &request.pfx_path {
+        let data = fs::read(pfx_path).map_err(|e| format!("Failed to read PFX file: {}", e))?;
+        ("pfx", data, request.pfx_password.clone().unwrap_or_default())
+    } else if let Some(cert_path) = &request.cert_path {
+        // Certificate and key are stored together as a single PEM bundle
+        let mut bundle = fs::read(cert_path).map_err(|e| format!("Failed to read certificate file: {}", e))?;
+        if let Some(key_path) = &request.key_path {
+            let key = fs::read(key_path).map_err(|e| format!("Failed to read key file: {}", e))?;
+            bundle.push(b'\n');
+            bundle.extend_from_slice(&key);
+        }
+        ("pem", bundle, request.passphrase.clone().unwrap_or_default())
+    } else {
+        return Err("A PFX or PEM certificate path is required".to_string());
+    };
+
+    let parsed = match format {
+        "pfx" => parse_pfx(&payload, &password)?,
+        _ => parse_pem(&payload, &password)?,
+    };
+
+    let now = chrono::Utc::now().timestamp();
+    if parsed.not_after <= now {
+        return Err("El certificado ha caducado".to_string());
+    }
+
+    let store = open_certificate_store(&app)?;
+    let file_name = format!("{}.{}.enc", &parsed.fingerprint[..16], format);
+    let encrypted_password = hex::encode(store.encrypt(password.as_bytes())?);
+
+    let mut cert = CertificateInfo {
+        id: 0,
+        alias: request.alias.clone().unwrap_or_else(|| parsed.subject.clone()),
+        format: format.to_string(),
+        certificate_type: request.certificate_type.clone().unwrap_or_else(|| "personal".to_string()),
+        subject: parsed.subject,
+        issuer: parsed.issuer,
+        serial_number: parsed.serial_number,
+        fingerprint: parsed.fingerprint,
+        nif: parsed.nif,
+        not_before: parsed.not_before,
+        not_after: parsed.not_after,
+        imported_at: now,
+        days_remaining: None,
+    };
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    store.write(&file_name, &payload)?;
+    cert.id = match db.save_certificate(&cert, &file_name, &encrypted_password) {
+        Ok(id) => id,
+        Err(e) => {
+            store.remove(&file_name)?;
+            return Err(format!("Failed to save certificate (already imported?): {}", e));
+        }
+    };
+
+    Ok(with_days_remaining(cert, now))
+}
+
+#[tauri::command]
+async fn remove_certificate(app: tauri::AppHandle, state: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let (file_name, _) = db.get_certificate_secrets(id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Certificate {} not found", id))?;
+
+    let store = open_certificate_store(&app)?;
+    store.remove(&file_name)?;
+    db.delete_certificate(id).map_err(|e| e.to_string())
+}
+
+/// Decrypts a certificate and re-packs it as a temporary PKCS#12 file for the
+/// signing service. Without `id` the valid certificate for the business NIF
+/// that expires last is used, or none when no valid certificate is imported.
+fn prepare_signing_certificate(
+    app: &tauri::AppHandle,
+    state: &DbState,
+    id: Option<i64>,
+) -> Result<Option<SigningCertificate>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+
+    let now = chrono::Utc::now().timestamp();
+    let issuer_nif = db.get_business_profile().map_err(|e| e.to_string())?.map(|profile| profile.nif);
+    let certificates = db.get_certificates().map_err(|e| e.to_string())?;
+    let cert = match id {
+        Some(id) => certificates.into_iter().find(|cert| cert.id == id)
+            .ok_or_else(|| format!("Certificate {} not found", id))?,
+        None => match certificates
+            .into_iter()
+            .filter(|cert| cert.not_after > now)
+            .max_by_key(|cert| (issuer_nif.is_some() && cert.nif == issuer_nif, cert.not_after))
+        {
+            Some(cert) => cert,
+            None => return Ok(None),
+        },
+    };
+    if cert.not_after <= now {
+        return Err("El certificado ha caducado".to_string());
+    }
+
+    let (file_name, encrypted_password) = db.get_certificate_secrets(cert.id).map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Certificate {} not found", cert.id))?;
+
+    let store = open_certificate_store(app)?;
+    let payload = store.read(&file_name)?;
+    let password = hex::decode(&encrypted_password).map_err(|e| e.to_string())?;
+    let password = String::from_utf8(store.decrypt(&password)?).map_err(|e| e.to_string())?;
+
+    let pfx = to_pkcs12(&cert.format, &payload, &password)?;
+    let path = store.export_temporary(&pfx)?;
+
+    Ok(Some(SigningCertificate { path, password }))
+}
+
+/// Starts the AEAT signing service on `port` with the imported certificate
+/// and returns its pid. Without a stored certificate the service falls back
+/// to its own configuration.
+#[tauri::command]
+async fn start_aeat_sidecar(
+    app: tauri::AppHandle,
+    state: State<'_, DbState>,
+    sidecar: State<'_, AeatSidecar>,
+    port: u16,
+    id: Option<i64>,
+) -> Result<u32, String> {
+    if let Some(pid) = sidecar.pid()? {
+        return Ok(pid);
+    }
+    let certificate = prepare_signing_certificate(&app, &state, id)?;
+    sidecar.start(&app, port, certificate)
+}
+
+#[tauri::command]
+async fn stop_aeat_sidecar(sidecar: State<'_, AeatSidecar>) -> Result<(), String> {
+    sidecar.stop()
+}
+
+#[tauri::command]
+async fn get_expiring_certificates(state: State<'_, DbState>, days: Option<i64>) -> Result<Vec<CertificateExpiryWarning>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let db = db.as_ref().ok_or("Database not initialized")?;
+    expiring_certificates(db, days.unwrap_or(EXPIRY_WARNING_DAYS))
+}
+
+// Emits `certificate-expiring` shortly after startup and then twice a day
+fn spawn_certificate_expiry_monitor(app: tauri::AppHandle) {
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(15));
+        loop {
+            let warnings = {
+                let state = app.state::<DbState>();
+                let db = match state.db.lock() {
+                    Ok(db) => db,
+                    Err(_) => return,
+                };
+                db.as_ref()
+                    .map(|db| expiring_certificates(db, EXPIRY_WARNING_DAYS))
+                    .unwrap_or_else(|| Ok(Vec::new()))
+            };
+
+            match warnings {
+                Ok(warnings) => {
+                    for warning in warnings {
+                        if let Err(e) = app.emit("certificate-expiring", &warning) {
+                            eprintln!("Failed to emit certificate warning: {}", e);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("Certificate expiry check failed: {}", e),
+            }
+
+            std::thread::sleep(Duration::from_secs(12 * 3600));
+        }
+    });
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -405,6 +2090,14 @@ pub fn run() {
 
             println!("Database initialized successfully");
 
+            spawn_certificate_expiry_monitor(app.handle().clone());
+
+            app.manage(AeatSidecar::default());
+
+            // Kitchen displays connect over the LAN
+            app.manage(KitchenDisplay::from_env(&app_dir));
+            tauri::async_runtime::spawn(kitchen_display::serve(app.handle().clone()));
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -415,6 +2108,47 @@ pub fn run() {
             create_product,
             update_product,
             delete_product,
+            find_product_by_barcode,
+            get_barcode_layout,
+            save_barcode_layout,
+            search_products,
+            // Stock
+            get_stock_movements,
+            record_stock_movement,
+            get_loss_summary,
+            reconcile_stock,
+            get_low_stock_report,
+            get_low_stock_alerts,
+            acknowledge_low_stock_alert,
+            // Lots
+            get_stock_lots,
+            get_expiring_lots,
+            get_lot_movements,
+            // Stocktakes
+            open_stocktake,
+            get_stocktakes,
+            record_stocktake_count,
+            get_stocktake_variance,
+            close_stocktake,
+            cancel_stocktake,
+            // Recipes
+            get_recipe,
+            save_recipe,
+            get_product_costs,
+            // Costs
+            get_cost_history,
+            get_margin_report,
+            // Modifiers
+            get_modifier_groups,
+            save_modifier_group,
+            delete_modifier_group,
+            get_product_modifier_groups,
+            set_product_modifier_groups,
+            build_order_item,
+            // Bundles
+            get_bundle_slots,
+            save_bundle_slots,
+            build_bundle_item,
             // Categories
             get_categories,
             create_category,
@@ -435,16 +2169,79 @@ pub fn run() {
             create_user,
             update_user,
             delete_user,
+            // Customers
+            get_customers,
+            create_customer,
+            update_customer,
+            delete_customer,
+            // Suppliers
+            get_suppliers,
+            create_supplier,
+            update_supplier,
+            delete_supplier,
+            // Purchase orders
+            get_purchase_orders,
+            save_purchase_order,
+            cancel_purchase_order,
+            receive_purchase_order,
+            suggest_purchase_order,
+            // Tax IDs
+            check_tax_id,
+            validate_invoice_identities,
+            // Business profile
+            get_business_profile,
+            save_business_profile,
+            next_invoice_number,
             // Utility
             export_data,
             import_data,
             clear_all_data,
-            write_json_config,
+            // Printer configuration
+            get_printer_configs,
+            save_printer_config,
+            delete_printer_config,
+            // Printing
+            print_order,
+            print_invoice,
+            print_test_page,
+            test_printer_connection,
+            // Labels
+            print_labels,
+            // Pre-bills
+            print_prebill,
+            get_prebill_info,
+            get_prebill_alerts,
+            review_prebill_alert,
+            // Cash drawer
+            open_cash_drawer,
+            get_cash_drawer_events,
+            // Receipt templates
+            get_templates,
+            get_default_template,
+            save_template,
+            delete_template,
+            preview_template,
+            // Stations & comandas
+            get_stations,
+            create_station,
+            update_station,
+            delete_station,
+            get_comandas,
+            reprint_comanda,
+            set_kitchen_status,
+            get_kitchen_display_info,
             // License
             check_license_status,
             validate_and_activate_license,
             get_machine_fingerprint,
             clear_license,
+            // Certificates
+            list_certificates,
+            import_certificate,
+            remove_certificate,
+            start_aeat_sidecar,
+            stop_aeat_sidecar,
+            get_expiring_certificates,
             // Screenshot
             save_screenshot_from_base64,
             get_screenshots_dir,
@@ -1,6 +1,43 @@
 use serde::{Deserialize, Serialize};
 
+pub mod bundle;
+pub mod certificate;
+pub mod kitchen;
 pub mod license;
+pub mod modifier;
+pub mod prebill;
+pub mod printer;
+pub mod purchasing;
+pub mod recipe;
+pub mod stock;
+
+/// How a product is sold: by the unit, by weight or by volume.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SaleUnit {
+    #[default]
+    Unit,
+    Kg,
+    L,
+}
+
+impl SaleUnit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SaleUnit::Unit => "unit",
+            SaleUnit::Kg => "kg",
+            SaleUnit::L => "l",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "kg" => SaleUnit::Kg,
+            "l" => SaleUnit::L,
+            _ => SaleUnit::Unit,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -17,8 +54,86 @@ pub struct Product {
     pub selected_icon: Option<String>,
     #[serde(default)]
     pub uploaded_image: Option<String>,
+    /// Units in stock, fractional for products consumed by recipes
+    #[serde(default)]
+    pub stock: Option<f64>,
+    /// Main barcode, printed on labels
+    #[serde(default)]
+    pub barcode: Option<String>,
+    /// Other codes the product is sold under (other pack sizes, the UPC of
+    /// an import); no code belongs to two products
+    #[serde(default)]
+    pub barcodes: Vec<String>,
+    #[serde(default)]
+    pub allergens: Option<Vec<String>>,
+    /// Net content of one unit (0.33 for a 33cl bottle), used for the price per unit on labels
+    #[serde(default)]
+    pub net_quantity: Option<f64>,
+    /// Unit of `net_quantity`: kg, g, l, ml
+    #[serde(default)]
+    pub net_unit: Option<String>,
+    /// Stock at or below which the product is reported as low
     #[serde(default)]
-    pub stock: Option<i32>,
+    pub min_stock: Option<f64>,
+    /// Quantity usually ordered from the supplier when restocking
+    #[serde(default)]
+    pub reorder_quantity: Option<f64>,
+    /// Usual supplier, used for suggested purchase orders
+    #[serde(default)]
+    pub supplier_id: Option<i64>,
+    /// Stock item used in recipes and not sold on its own (a bottle of gin)
+    #[serde(default)]
+    pub ingredient: bool,
+    /// Current unit cost without IVA; every change is kept in the cost history
+    #[serde(default)]
+    pub cost_price: Option<f64>,
+    /// `price` is per unit, per kilogram or per litre
+    #[serde(default)]
+    pub sale_unit: SaleUnit,
+    /// IVA included in `price` when it isn't the shop's usual rate
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+}
+
+/// Product found for a scanned code, with the quantity and unit price of the
+/// line to add. Both come from the label for in-store price or weight codes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BarcodeMatch {
+    pub product: Product,
+    pub code: String,
+    pub quantity: f64,
+    pub price: f64,
+    /// The code carried a price or weight
+    pub embedded: bool,
+}
+
+/// How the shop's scales lay out in-store EAN-13 codes: "2", a type digit,
+/// the item number, an optional price check digit, the value and the EAN
+/// check digit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedBarcodeLayout {
+    /// Type digits whose value is a price in cents ("01234")
+    pub price_types: String,
+    /// Type digits whose value is a weight in grams or millilitres ("56789")
+    pub weight_types: String,
+    /// Digits of the item number after the type digit, 4 or 5
+    pub item_digits: usize,
+    /// A GS1 price check digit comes before the value
+    #[serde(default)]
+    pub price_check_digit: bool,
+}
+
+impl Default for EmbeddedBarcodeLayout {
+    fn default() -> Self {
+        EmbeddedBarcodeLayout {
+            price_types: "01234".to_string(),
+            weight_types: "56789".to_string(),
+            item_digits: 5,
+            price_check_digit: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -37,10 +152,35 @@ pub struct Category {
 pub struct OrderItem {
     pub id: i64,
     pub name: String,
+    /// Price per unit, kilogram or litre
     pub price: f64,
-    pub quantity: i32,
+    /// Units, or kilograms / litres for products sold by weight or volume
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit: SaleUnit,
     #[serde(default)]
     pub category: Option<String>,
+    /// Preparation status reported by the kitchen display (pending, started, ready, served)
+    #[serde(default)]
+    pub kitchen_status: Option<String>,
+    /// Unit cost when the item was sold, kept when product costs change later
+    #[serde(default)]
+    pub cost_price: Option<f64>,
+    /// Chosen variant and modifiers; `price` already includes their deltas
+    #[serde(default)]
+    pub modifiers: Vec<modifier::OrderItemModifier>,
+    /// Products served when the item is a bundle (menú del día)
+    #[serde(default)]
+    pub components: Vec<bundle::BundleComponent>,
+    /// Stable id of the line, assigned by the backend when missing
+    #[serde(default)]
+    pub line_id: Option<String>,
+    /// Free text for the kitchen ("poco hecho")
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// IVA of the product when the line was saved; the shop's rate if missing
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -90,6 +230,94 @@ pub struct User {
     pub pinned_product_ids: Option<Vec<i64>>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Customer {
+    pub id: i64,
+    pub cif_nif: String,
+    pub nombre_fiscal: String,
+    #[serde(default)]
+    pub nombre_comercial: String,
+    #[serde(default)]
+    pub direccion: String,
+    #[serde(default)]
+    pub codigo_postal: String,
+    #[serde(default)]
+    pub poblacion: String,
+    #[serde(default)]
+    pub telefono: String,
+    #[serde(default)]
+    pub email: String,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Supplier {
+    pub id: i64,
+    #[serde(default)]
+    pub cif_nif: String,
+    pub nombre_fiscal: String,
+    #[serde(default)]
+    pub nombre_comercial: String,
+    #[serde(default)]
+    pub direccion: String,
+    #[serde(default)]
+    pub telefono: String,
+    #[serde(default)]
+    pub email: String,
+    /// Sales representative or order contact
+    #[serde(default)]
+    pub contacto: String,
+    #[serde(default = "default_true")]
+    pub activo: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BusinessProfile {
+    pub nif: String,
+    pub nombre_razon: String,
+    #[serde(default)]
+    pub nombre_comercial: Option<String>,
+    #[serde(default)]
+    pub direccion: String,
+    #[serde(default)]
+    pub codigo_postal: String,
+    #[serde(default)]
+    pub poblacion: String,
+    #[serde(default)]
+    pub provincia: Option<String>,
+    #[serde(default)]
+    pub telefono: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    pub serie_factura: String,
+    #[serde(default = "default_tipo_factura")]
+    pub tipo_factura: String,
+    #[serde(default)]
+    pub descripcion_operacion: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+fn default_tipo_factura() -> String {
+    "F1".to_string()
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExportData {
     pub products: Vec<Product>,
@@ -97,6 +325,8 @@ pub struct ExportData {
     pub orders: Vec<Order>,
     pub tables: Vec<Table>,
     pub users: Vec<User>,
+    pub customers: Vec<Customer>,
+    pub business_profile: Option<BusinessProfile>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -108,4 +338,8 @@ pub struct ImportData {
     pub tables: Option<Vec<Table>>,
     #[serde(default)]
     pub users: Option<Vec<User>>,
+    #[serde(default)]
+    pub customers: Option<Vec<Customer>>,
+    #[serde(default)]
+    pub business_profile: Option<BusinessProfile>,
 }
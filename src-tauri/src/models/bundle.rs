@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+fn default_required() -> bool {
+    true
+}
+
+/// One course of a bundle (first, second, dessert, drink), filled with a
+/// product from the listed categories or products.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleSlot {
+    #[serde(default)]
+    pub id: i64,
+    pub name: String,
+    #[serde(default = "default_required")]
+    pub required: bool,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub product_ids: Vec<i64>,
+}
+
+/// Product chosen for a slot when selling a bundle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleChoice {
+    pub slot_id: i64,
+    pub product_id: i64,
+}
+
+/// Product served as part of a bundle order item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleComponent {
+    #[serde(default)]
+    pub slot_id: Option<i64>,
+    #[serde(default)]
+    pub slot_name: String,
+    pub product_id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Price of the product sold on its own
+    pub list_price: f64,
+    /// Share of one bundle's price, proportional to the list prices, so each
+    /// component can be taxed at its own rate
+    pub allocated_price: f64,
+    /// IVA of the product; the shop's rate if missing
+    #[serde(default)]
+    pub tax_rate: Option<f64>,
+}
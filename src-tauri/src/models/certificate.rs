@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateInfo {
+    pub id: i64,
+    pub alias: String,
+    pub format: String,
+    pub certificate_type: String,
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    pub fingerprint: String,
+    pub nif: Option<String>,
+    pub not_before: i64,
+    pub not_after: i64,
+    pub imported_at: i64,
+    #[serde(default)]
+    pub days_remaining: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateImportRequest {
+    #[serde(default)]
+    pub alias: Option<String>,
+    #[serde(default)]
+    pub certificate_type: Option<String>,
+    // PFX format (recommended)
+    #[serde(default)]
+    pub pfx_path: Option<String>,
+    #[serde(default)]
+    pub pfx_password: Option<String>,
+    // PEM format
+    #[serde(default)]
+    pub cert_path: Option<String>,
+    #[serde(default)]
+    pub key_path: Option<String>,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CertificateExpiryWarning {
+    pub id: i64,
+    pub alias: String,
+    pub nif: Option<String>,
+    pub not_after: i64,
+    pub days_remaining: i64,
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+
+use super::SaleUnit;
+
+/// Preparation station (kitchen, bar...). Items are routed to a station by
+/// product id first and by category otherwise.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Station {
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub printer_interface: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub product_ids: Vec<i64>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComandaItem {
+    #[serde(default)]
+    pub id: i64,
+    pub product_id: i64,
+    pub name: String,
+    /// Positive for new items, negative for items removed from the order,
+    /// zero when only the notes of the line changed
+    pub quantity: f64,
+    #[serde(default)]
+    pub unit: SaleUnit,
+    #[serde(default = "default_status")]
+    pub status: String,
+    /// Extras and preferences to prepare ("Sin cebolla"); variants are part of `name`
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    /// Order line the item comes from
+    #[serde(default)]
+    pub line_id: Option<String>,
+    /// Free text from the waiter ("poco hecho")
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn default_status() -> String {
+    "pending".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Comanda {
+    pub id: i64,
+    pub order_id: i64,
+    pub station_id: i64,
+    pub station_name: String,
+    pub table_number: i32,
+    pub created_at: String,
+    pub items: Vec<ComandaItem>,
+    #[serde(default)]
+    pub prints: Vec<ComandaPrint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComandaPrint {
+    pub id: i64,
+    pub comanda_id: i64,
+    pub printed_at: String,
+    pub reprint: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Acknowledgement from a kitchen display, also emitted to the frontend as
+/// `kitchen-status-changed`. Without `comanda_item_id` it applies to every
+/// item of the comanda.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitchenStatusUpdate {
+    pub comanda_id: i64,
+    #[serde(default)]
+    pub comanda_item_id: Option<i64>,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KitchenDisplayInfo {
+    pub bind_address: String,
+    pub port: u16,
+    pub running: bool,
+    /// Displays send it as `?token=` or `Authorization: Bearer`
+    pub token: String,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+fn default_quantity() -> f64 {
+    1.0
+}
+
+/// Choices offered when a product is sold: a variant group (caña, tercio,
+/// jarra) or a modifier group (extras, cooking point, without onion).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifierGroup {
+    pub id: i64,
+    pub name: String,
+    /// Exactly one option is chosen and it replaces the product's own stock
+    /// consumption when it names a stock product
+    #[serde(default)]
+    pub variant: bool,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub min_select: u32,
+    /// No limit when None
+    #[serde(default)]
+    pub max_select: Option<u32>,
+    #[serde(default)]
+    pub options: Vec<ModifierOption>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModifierOption {
+    pub id: i64,
+    pub name: String,
+    /// Added to the unit price, negative for cheaper choices
+    #[serde(default)]
+    pub price_delta: f64,
+    /// Stock item consumed for each unit sold (extra cheese, 0.5 l of beer)
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    #[serde(default = "default_quantity")]
+    pub quantity: f64,
+}
+
+/// Option chosen on an order item, copied from the group so later menu
+/// changes don't alter past orders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrderItemModifier {
+    #[serde(default)]
+    pub group_id: Option<i64>,
+    #[serde(default)]
+    pub option_id: Option<i64>,
+    pub name: String,
+    #[serde(default)]
+    pub price_delta: f64,
+    #[serde(default)]
+    pub product_id: Option<i64>,
+    #[serde(default = "default_quantity")]
+    pub quantity: f64,
+    #[serde(default)]
+    pub variant: bool,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Pre-bill (cuenta) history of an order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrebillInfo {
+    pub order_id: i64,
+    pub print_count: i64,
+    #[serde(default)]
+    pub last_printed_at: Option<String>,
+    #[serde(default)]
+    pub last_total: Option<f64>,
+    /// The order has been changed after the last pre-bill was printed
+    #[serde(default)]
+    pub modified_after_print: bool,
+}
+
+/// Change to a pre-billed order, queued for review.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrebillAlert {
+    pub id: i64,
+    pub order_id: i64,
+    pub detected_at: String,
+    pub prebill_total: f64,
+    #[serde(default)]
+    pub new_total: Option<f64>,
+    pub details: String,
+    #[serde(default)]
+    pub reviewed: bool,
+    #[serde(default)]
+    pub reviewed_by: Option<i64>,
+    #[serde(default)]
+    pub reviewed_at: Option<String>,
+}
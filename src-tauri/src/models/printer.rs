@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+
+/// Connection and rendering settings of one printer: the frontend
+/// `ThermalPrinterServiceOptions` plus backend-only options. Also the format
+/// of the legacy `printerSettings.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrinterSettings {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub printer_type: Option<String>,
+    pub interface: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub character_set: Option<String>,
+    #[serde(default)]
+    pub remove_special_characters: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line_character: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub break_line: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<PrinterOptions>,
+    /// Characters per line: 48 for 80mm paper, 32 for 58mm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paper_width: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo_path: Option<String>,
+    /// Drawer connector pin, 2 (default) or 5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drawer_pin: Option<u8>,
+    /// Label stock of a `labels` printer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<LabelSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrinterOptions {
+    /// Connection timeout in milliseconds
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelLanguage {
+    /// Zebra and compatible label printers
+    Zpl,
+    /// Thermal receipt printers, one label per cut
+    Escpos,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelSettings {
+    pub language: LabelLanguage,
+    #[serde(default = "default_label_width")]
+    pub width_mm: u32,
+    #[serde(default = "default_label_height")]
+    pub height_mm: u32,
+    /// Print head resolution, only used by ZPL
+    #[serde(default = "default_label_dpi")]
+    pub dpi: u32,
+}
+
+impl Default for LabelSettings {
+    fn default() -> Self {
+        LabelSettings {
+            language: LabelLanguage::Zpl,
+            width_mm: default_label_width(),
+            height_mm: default_label_height(),
+            dpi: default_label_dpi(),
+        }
+    }
+}
+
+fn default_label_width() -> u32 {
+    58
+}
+
+fn default_label_height() -> u32 {
+    40
+}
+
+fn default_label_dpi() -> u32 {
+    203
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PrinterRole {
+    Ticket,
+    Kitchen,
+    Bar,
+    Labels,
+}
+
+/// A named printer from `printers.json`. The first enabled `ticket` printer
+/// prints customer receipts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrinterConfig {
+    pub name: String,
+    pub role: PrinterRole,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub settings: PrinterSettings,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// User-edited receipt layout. Without `printer_name` it applies to every
+/// printer that has no template of its own for the same kind.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiptTemplate {
+    #[serde(default)]
+    pub id: i64,
+    pub name: String,
+    /// ticket, prebill or invoice
+    pub kind: String,
+    #[serde(default)]
+    pub printer_name: Option<String>,
+    pub content: String,
+    #[serde(default)]
+    pub updated_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplatePreview {
+    pub text: String,
+    /// Base64 encoded PNG at printer resolution
+    pub png: String,
+}
+
+/// Invoice fields available to invoice templates as `invoice.*`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvoiceData {
+    pub number: String,
+    pub date: String,
+    /// F1 (complete) or F2 (simplified)
+    #[serde(default = "default_invoice_type")]
+    pub invoice_type: String,
+    #[serde(default)]
+    pub customer_id: Option<i64>,
+    /// Recipient NIF typed at the till when there is no customer record
+    #[serde(default)]
+    pub recipient_nif: Option<String>,
+    /// IVA percentage included in the order prices
+    #[serde(default = "default_tax_rate")]
+    pub tax_rate: f64,
+}
+
+fn default_invoice_type() -> String {
+    "F2".to_string()
+}
+
+fn default_tax_rate() -> f64 {
+    10.0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CashDrawerEvent {
+    pub id: i64,
+    pub opened_at: String,
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    pub reason: String,
+    #[serde(default)]
+    pub order_id: Option<i64>,
+    pub success: bool,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Products to label: the listed ids plus every product of `category`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelRequest {
+    #[serde(default)]
+    pub product_ids: Vec<i64>,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Labels per product
+    #[serde(default)]
+    pub copies: Option<u32>,
+    /// Label printer to use instead of the first enabled one
+    #[serde(default)]
+    pub printer_name: Option<String>,
+    /// Save the output to this file instead of printing it
+    #[serde(default)]
+    pub output_path: Option<String>,
+    /// Output language, defaults to the printer's (ZPL when saving without one)
+    #[serde(default)]
+    pub language: Option<LabelLanguage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelResult {
+    /// Labels generated, copies included
+    pub count: usize,
+    #[serde(default)]
+    pub path: Option<String>,
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrder {
+    #[serde(default)]
+    pub id: i64,
+    pub supplier_id: i64,
+    #[serde(default)]
+    pub supplier_name: Option<String>,
+    /// draft, sent, partial, received or cancelled
+    #[serde(default = "default_status")]
+    pub status: String,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    #[serde(default)]
+    pub expected_at: Option<String>,
+    #[serde(default)]
+    pub received_at: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub created_by: Option<i64>,
+    #[serde(default)]
+    pub lines: Vec<PurchaseOrderLine>,
+    /// Cost of everything ordered, filled in when loaded
+    #[serde(default)]
+    pub total: f64,
+}
+
+fn default_status() -> String {
+    "draft".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PurchaseOrderLine {
+    #[serde(default)]
+    pub id: i64,
+    pub product_id: i64,
+    #[serde(default)]
+    pub product_name: String,
+    pub quantity: f64,
+    #[serde(default)]
+    pub received_quantity: f64,
+    /// Unit cost agreed with the supplier, without IVA
+    #[serde(default)]
+    pub cost_price: f64,
+}
+
+/// Quantity delivered for one purchase order line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoodsReceiptLine {
+    pub line_id: i64,
+    pub quantity: f64,
+    /// With a lot number or expiry date the delivery becomes a stock lot
+    #[serde(default)]
+    pub lot_number: Option<String>,
+    /// YYYY-MM-DD
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuggestedPurchaseLine {
+    pub product_id: i64,
+    pub product_name: String,
+    #[serde(default)]
+    pub supplier_id: Option<i64>,
+    pub stock: f64,
+    /// Ordered and not yet received
+    pub pending: f64,
+    #[serde(default)]
+    pub min_stock: Option<f64>,
+    #[serde(default)]
+    pub reorder_quantity: Option<f64>,
+    /// Average units sold per day over the analysed period
+    pub daily_sales: f64,
+    pub suggested_quantity: f64,
+    /// Last purchase cost
+    #[serde(default)]
+    pub cost_price: Option<f64>,
+}
@@ -0,0 +1,89 @@
+use serde::{Deserialize, Serialize};
+
+/// Quantity of an ingredient consumed by one unit of a product, in the
+/// ingredient's stock unit (0.05 of a gin stocked in litres).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecipeItem {
+    pub ingredient_id: i64,
+    #[serde(default)]
+    pub ingredient_name: String,
+    pub quantity: f64,
+    /// Current purchase cost of one unit of the ingredient
+    #[serde(default)]
+    pub unit_cost: Option<f64>,
+}
+
+/// Theoretical cost of a product from its recipe (or its own purchase cost
+/// when it has none) against its sale price.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProductCost {
+    pub product_id: i64,
+    pub product_name: String,
+    pub category: String,
+    /// Sale price, IVA included
+    pub price: f64,
+    /// Sale price without IVA
+    pub net_price: f64,
+    #[serde(default)]
+    pub cost: Option<f64>,
+    #[serde(default)]
+    pub margin: Option<f64>,
+    /// Margin as a percentage of the net price
+    #[serde(default)]
+    pub margin_percent: Option<f64>,
+    pub has_recipe: bool,
+    /// Some ingredient has no purchase cost yet, so `cost` is incomplete
+    pub missing_costs: bool,
+}
+
+/// One entry of a product's cost price history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CostPriceChange {
+    pub id: i64,
+    pub product_id: i64,
+    pub cost_price: f64,
+    /// manual, purchase or initial
+    pub source: String,
+    #[serde(default)]
+    pub purchase_order_id: Option<i64>,
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MarginGrouping {
+    Product,
+    Category,
+    Day,
+    Month,
+}
+
+/// Sales of paid orders against the costs snapshotted on their items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarginLine {
+    /// Product id, category name, date (2024-05-31) or month (2024-05)
+    pub key: String,
+    pub label: String,
+    pub quantity: f64,
+    /// Sales without IVA
+    pub revenue: f64,
+    /// Cost of the units with a known cost, None when none had one
+    #[serde(default)]
+    pub cost: Option<f64>,
+    /// Net sales minus cost of the units with a known cost
+    #[serde(default)]
+    pub margin: Option<f64>,
+    #[serde(default)]
+    pub margin_percent: Option<f64>,
+    /// Units sold without a cost, left out of the margin
+    pub uncosted_quantity: f64,
+    /// Cost of waste and staff consumption, not deducted from `margin`
+    #[serde(default)]
+    pub loss_cost: f64,
+}
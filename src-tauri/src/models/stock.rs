@@ -0,0 +1,187 @@
+use serde::{Deserialize, Serialize};
+
+/// Entry of the stock ledger. `quantity` is the signed stock change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockMovement {
+    pub id: i64,
+    pub product_id: i64,
+    #[serde(default)]
+    pub product_name: Option<String>,
+    /// sale, return, adjustment, purchase, waste or comp
+    pub movement_type: String,
+    pub quantity: f64,
+    /// Why stock was lost, for waste and comp movements
+    #[serde(default)]
+    pub reason_code: Option<String>,
+    /// Unit cost when a waste or comp movement was recorded
+    #[serde(default)]
+    pub unit_cost: Option<f64>,
+    #[serde(default)]
+    pub order_id: Option<i64>,
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    #[serde(default)]
+    pub user_name: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    pub created_at: String,
+}
+
+/// Movement entered by hand. The sign of `quantity` is only used for
+/// adjustments; purchases and returns always add, waste and comp always
+/// remove. Waste and comp need a `reason_code` and the responsible user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewStockMovement {
+    pub product_id: i64,
+    pub movement_type: String,
+    pub quantity: f64,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub reason_code: Option<String>,
+    #[serde(default)]
+    pub user_id: Option<i64>,
+    #[serde(default)]
+    pub order_id: Option<i64>,
+}
+
+/// Product whose stored stock differs from the sum of its movements.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockDiscrepancy {
+    pub product_id: i64,
+    pub product_name: String,
+    pub recorded_stock: f64,
+    pub ledger_stock: f64,
+}
+
+/// Persisted notification of a product falling to its minimum stock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LowStockAlert {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    pub stock: f64,
+    pub min_stock: f64,
+    pub created_at: String,
+    #[serde(default)]
+    pub acknowledged: bool,
+    #[serde(default)]
+    pub acknowledged_by: Option<i64>,
+    #[serde(default)]
+    pub acknowledged_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LowStockItem {
+    pub product_id: i64,
+    pub product_name: String,
+    pub category: String,
+    pub stock: f64,
+    pub min_stock: f64,
+    #[serde(default)]
+    pub reorder_quantity: Option<f64>,
+    /// Reorder quantity, or more if that would not reach the minimum
+    pub suggested_quantity: f64,
+}
+
+/// Inventory count session. Counts are posted as adjustments when it closes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stocktake {
+    pub id: i64,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Limits the count to one category
+    #[serde(default)]
+    pub category: Option<String>,
+    /// open, closed or cancelled
+    pub status: String,
+    pub opened_at: String,
+    #[serde(default)]
+    pub opened_by: Option<i64>,
+    #[serde(default)]
+    pub closed_at: Option<String>,
+    #[serde(default)]
+    pub closed_by: Option<i64>,
+}
+
+/// Quantity counted by one device. A new count from the same device
+/// replaces its previous one; counts from different devices add up.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeCount {
+    pub stocktake_id: i64,
+    pub product_id: i64,
+    pub counted: f64,
+    #[serde(default)]
+    pub device: String,
+    #[serde(default)]
+    pub user_id: Option<i64>,
+}
+
+/// Counted quantity of a product against its ledger stock.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StocktakeLine {
+    pub product_id: i64,
+    pub product_name: String,
+    pub expected: f64,
+    pub counted: f64,
+    /// counted - expected, the adjustment posted on close
+    pub variance: f64,
+    pub devices: Vec<String>,
+}
+
+/// Waste and comp totals of one reason, product or user. Quantities are
+/// positive units lost.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LossLine {
+    pub key: String,
+    pub label: String,
+    pub movements: i64,
+    pub quantity: f64,
+    /// Cost of the units with a known cost
+    pub cost: f64,
+    pub uncosted_quantity: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LossSummary {
+    pub by_reason: Vec<LossLine>,
+    pub by_product: Vec<LossLine>,
+    pub by_user: Vec<LossLine>,
+    pub total_cost: f64,
+    /// Movements left out of `total_cost` for lack of a cost price
+    pub uncosted_movements: i64,
+}
+
+/// Lot of a product received with a lot number or expiry date. Sales and
+/// other outgoing movements take stock from the lots that expire first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StockLot {
+    pub id: i64,
+    pub product_id: i64,
+    pub product_name: String,
+    #[serde(default)]
+    pub lot_number: Option<String>,
+    /// Best-before or use-by date, YYYY-MM-DD
+    #[serde(default)]
+    pub expiry_date: Option<String>,
+    /// Quantity received
+    pub quantity: f64,
+    pub remaining: f64,
+    pub received_at: String,
+    #[serde(default)]
+    pub purchase_order_id: Option<i64>,
+    /// Days until expiry, negative once expired
+    #[serde(default)]
+    pub days_left: Option<i64>,
+}
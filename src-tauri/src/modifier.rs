@@ -0,0 +1,114 @@
+use crate::models::modifier::{ModifierGroup, OrderItemModifier};
+
+/// Fewest and most options of a group that can be chosen. Variants take
+/// exactly one; required groups at least one.
+pub fn selection_limits(group: &ModifierGroup) -> (u32, Option<u32>) {
+    if group.variant {
+        return (1, Some(1));
+    }
+    let min = if group.required { group.min_select.max(1) } else { group.min_select };
+    (min, group.max_select)
+}
+
+/// Checks a group before it is saved.
+pub fn validate_group(group: &ModifierGroup) -> Result<(), String> {
+    if group.name.trim().is_empty() {
+        return Err("El grupo necesita un nombre".to_string());
+    }
+    if group.options.is_empty() {
+        return Err(format!("{} no tiene opciones", group.name));
+    }
+    let (min, max) = selection_limits(group);
+    if let Some(max) = max {
+        if max < min {
+            return Err(format!("{}: el máximo no puede ser menor que el mínimo", group.name));
+        }
+    }
+    if min as usize > group.options.len() {
+        return Err(format!("{}: el mínimo supera el número de opciones", group.name));
+    }
+    for option in &group.options {
+        if option.name.trim().is_empty() {
+            return Err(format!("{}: hay una opción sin nombre", group.name));
+        }
+        if !option.price_delta.is_finite() {
+            return Err(format!("Precio no válido para {}", option.name));
+        }
+        if option.product_id.is_some() && (!option.quantity.is_finite() || option.quantity <= 0.0) {
+            return Err(format!("Cantidad no válida para {}", option.name));
+        }
+    }
+    Ok(())
+}
+
+/// Turns the option ids chosen for a product into order item modifiers,
+/// checking every group's limits. Modifiers come out in group order.
+pub fn select(groups: &[ModifierGroup], option_ids: &[i64]) -> Result<Vec<OrderItemModifier>, String> {
+    if let Some(id) = option_ids
+        .iter()
+        .find(|id| !groups.iter().any(|group| group.options.iter().any(|option| option.id == **id)))
+    {
+        return Err(format!("La opción {} no está disponible para este producto", id));
+    }
+
+    let mut modifiers = Vec::new();
+    for group in groups {
+        let chosen: Vec<_> = group.options.iter().filter(|option| option_ids.contains(&option.id)).collect();
+        let (min, max) = selection_limits(group);
+        if (chosen.len() as u32) < min {
+            return Err(if min == 1 {
+                format!("Elige una opción de {}", group.name)
+            } else {
+                format!("Elige al menos {} opciones de {}", min, group.name)
+            });
+        }
+        if max.is_some_and(|max| chosen.len() as u32 > max) {
+            return Err(format!("Demasiadas opciones en {}", group.name));
+        }
+
+        modifiers.extend(chosen.into_iter().map(|option| OrderItemModifier {
+            group_id: Some(group.id),
+            option_id: Some(option.id),
+            name: option.name.clone(),
+            price_delta: option.price_delta,
+            product_id: option.product_id,
+            quantity: option.quantity,
+            variant: group.variant,
+        }));
+    }
+    Ok(modifiers)
+}
+
+pub fn unit_price(base_price: f64, modifiers: &[OrderItemModifier]) -> f64 {
+    base_price + modifiers.iter().map(|modifier| modifier.price_delta).sum::<f64>()
+}
+
+/// Product name with its variant: "Cerveza (Jarra)".
+pub fn item_name(product_name: &str, modifiers: &[OrderItemModifier]) -> String {
+    let variants: Vec<&str> = modifiers
+        .iter()
+        .filter(|modifier| modifier.variant)
+        .map(|modifier| modifier.name.as_str())
+        .collect();
+    if variants.is_empty() {
+        product_name.to_string()
+    } else {
+        format!("{} ({})", product_name, variants.join(", "))
+    }
+}
+
+/// Names of the modifiers that are not already part of the item name.
+pub fn extras(modifiers: &[OrderItemModifier]) -> Vec<String> {
+    modifiers
+        .iter()
+        .filter(|modifier| !modifier.variant)
+        .map(|modifier| modifier.name.clone())
+        .collect()
+}
+
+/// Tells apart lines of the same product with different choices.
+pub fn key(modifiers: &[OrderItemModifier]) -> String {
+    let mut names: Vec<&str> = modifiers.iter().map(|modifier| modifier.name.as_str()).collect();
+    names.sort_unstable();
+    names.join("|")
+}
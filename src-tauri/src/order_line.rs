@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::bundle;
+use crate::models::OrderItem;
+use crate::modifier;
+
+/// What a line contains: product, modifiers and bundle choices.
+pub fn content_key(item: &OrderItem) -> (i64, String) {
+    let mut key = modifier::key(&item.modifiers);
+    if !item.components.is_empty() {
+        key.push('#');
+        key.push_str(&bundle::key(&item.components));
+    }
+    (item.id, key)
+}
+
+/// Identifies an order line: its line id when it has one, its content
+/// otherwise.
+pub fn line_key(item: &OrderItem) -> (i64, String) {
+    match &item.line_id {
+        Some(line_id) => (item.id, format!("@{}", line_id)),
+        None => content_key(item),
+    }
+}
+
+pub fn new_line_id() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos())
+        .unwrap_or_default();
+    format!("{:x}-{:x}", nanos, NEXT.fetch_add(1, Ordering::Relaxed))
+}
+
+fn normalize_notes(notes: Option<&str>) -> Option<String> {
+    notes.map(str::trim).filter(|notes| !notes.is_empty()).map(str::to_string)
+}
+
+/// Gives every incoming line a line id. Lines the frontend sent without one
+/// take the id of an unclaimed stored line with the same content, and lines
+/// without notes keep the stored ones; an empty note clears them.
+pub fn assign_line_ids(previous: &[OrderItem], items: &[OrderItem]) -> Vec<OrderItem> {
+    let mut unclaimed: Vec<&OrderItem> = previous.iter().collect();
+    let mut claim = |matches: &dyn Fn(&OrderItem) -> bool| {
+        let index = unclaimed.iter().position(|line| matches(line))?;
+        Some(unclaimed.remove(index))
+    };
+
+    items
+        .iter()
+        .map(|item| {
+            let mut item = item.clone();
+            let stored = match &item.line_id {
+                Some(line_id) => claim(&|line| line.line_id.as_ref() == Some(line_id)),
+                None => claim(&|line| content_key(line) == content_key(&item)),
+            };
+            if let Some(stored) = stored {
+                item.line_id = item.line_id.or_else(|| stored.line_id.clone());
+                item.notes = item.notes.or_else(|| stored.notes.clone());
+            }
+            item.notes = normalize_notes(item.notes.as_deref());
+            item.line_id = item.line_id.or_else(|| Some(new_line_id()));
+            item
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn line(id: i64, line_id: Option<&str>, notes: Option<&str>) -> OrderItem {
+        serde_json::from_value(json!({
+            "id": id, "name": "Hamburguesa", "price": 9.5, "quantity": 1.0,
+            "lineId": line_id, "notes": notes,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn lines_without_id_claim_a_stored_line_with_the_same_content() {
+        let stored = [line(1, Some("a"), Some("poco hecha")), line(2, Some("b"), None)];
+        let assigned = assign_line_ids(&stored, &[line(1, None, None), line(3, None, None)]);
+
+        assert_eq!(assigned[0].line_id.as_deref(), Some("a"));
+        assert_eq!(assigned[0].notes.as_deref(), Some("poco hecha"));
+        assert!(assigned[1].line_id.as_deref().is_some_and(|id| id != "a" && id != "b"));
+    }
+
+    #[test]
+    fn an_empty_note_clears_the_stored_one() {
+        let stored = [line(1, Some("a"), Some("poco hecha"))];
+        let assigned = assign_line_ids(&stored, &[line(1, Some("a"), Some("  "))]);
+        assert_eq!(assigned[0].notes, None);
+    }
+
+    #[test]
+    fn line_keys_prefer_the_line_id() {
+        assert_eq!(line_key(&line(1, Some("a"), None)), (1, "@a".to_string()));
+        assert_eq!(line_key(&line(1, None, None)), content_key(&line(1, None, None)));
+        assert_ne!(new_line_id(), new_line_id());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::kitchen::compute_delta;
+use crate::models::OrderItem;
+use crate::pricing::format_quantity;
+use crate::printer::ticket::format_money;
+
+// Totals within half a cent are the same amount
+pub fn same_total(a: f64, b: f64) -> bool {
+    (a - b).abs() < 0.005
+}
+
+/// Summary of what changed in an order's `items` and `total` since the
+/// pre-bill was printed, or None when it still matches.
+pub fn describe_changes(prebill_items: &[OrderItem], prebill_total: f64, items: &[OrderItem], total: f64) -> Option<String> {
+    // Notes don't change what is charged
+    let mut changes: Vec<String> = compute_delta(prebill_items, items)
+        .iter()
+        .filter(|delta| delta.quantity != 0.0)
+        .map(|delta| {
+            let sign = if delta.quantity > 0.0 { "+" } else { "-" };
+            let quantity = format_quantity(delta.quantity.abs(), delta.unit);
+            if delta.modifiers.is_empty() {
+                format!("{}{} {}", sign, quantity, delta.name)
+            } else {
+                format!("{}{} {} ({})", sign, quantity, delta.name, delta.modifiers.join(", "))
+            }
+        })
+        .collect();
+
+    if !same_total(prebill_total, total) {
+        changes.push(format!(
+            "total {} → {}",
+            format_money(prebill_total),
+            format_money(total)
+        ));
+    }
+
+    if changes.is_empty() {
+        None
+    } else {
+        Some(changes.join(", "))
+    }
+}
@@ -0,0 +1,198 @@
+use serde::Serialize;
+
+use crate::bundle;
+use crate::models::{Order, OrderItem, SaleUnit};
+use crate::printer::ticket::format_money;
+
+/// Rounds to cents, halves away from zero. Goes through a micro-cent step
+/// first so products like 0,25 kg × 18,90 € (4,7249999… in binary) round
+/// to 4,73 as they would by hand.
+pub fn round_money(amount: f64) -> f64 {
+    ((amount * 1_000_000.0).round() / 10_000.0).round() / 100.0
+}
+
+/// Whole units, or grams / millilitres for products sold by weight or volume.
+pub fn round_quantity(quantity: f64, unit: SaleUnit) -> f64 {
+    match unit {
+        SaleUnit::Unit => quantity.round(),
+        SaleUnit::Kg | SaleUnit::L => (quantity * 1000.0).round() / 1000.0,
+    }
+}
+
+/// Line amount, rounded once per line.
+pub fn line_total(item: &OrderItem) -> f64 {
+    round_money(item.price * item.quantity)
+}
+
+/// Sum of the rounded line amounts.
+pub fn order_total(items: &[OrderItem]) -> f64 {
+    round_money(items.iter().map(line_total).sum())
+}
+
+/// (base, tax) of a total with `tax_rate` IVA included. The base is rounded
+/// and the tax is the remainder, so both always add up to the total.
+pub fn tax_breakdown(total: f64, tax_rate: f64) -> (f64, f64) {
+    let base = round_money(total / (1.0 + tax_rate / 100.0));
+    (base, round_money(total - base))
+}
+
+/// Base and IVA of the part of an order taxed at `rate`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxLine {
+    pub rate: f64,
+    pub base: f64,
+    pub tax_amount: f64,
+}
+
+/// Breakdown of an order by IVA rate, lowest rate first. Bundles are taxed
+/// per component at its share of the bundle price; lines without a rate take
+/// `default_rate`.
+pub fn tax_lines(items: &[OrderItem], default_rate: f64) -> Vec<TaxLine> {
+    let mut totals: Vec<(f64, f64)> = Vec::new();
+    for item in &bundle::expand_items(items) {
+        let rate = item.tax_rate.unwrap_or(default_rate);
+        match totals.iter_mut().find(|(existing, _)| (existing - rate).abs() < 1e-9) {
+            Some((_, total)) => *total += line_total(item),
+            None => totals.push((rate, line_total(item))),
+        }
+    }
+    totals.sort_by(|a, b| a.0.total_cmp(&b.0));
+    totals
+        .into_iter()
+        .map(|(rate, total)| {
+            let (base, tax_amount) = tax_breakdown(round_money(total), rate);
+            TaxLine { rate, base, tax_amount }
+        })
+        .collect()
+}
+
+/// "2" for units, "0,250 kg" for products sold by weight or volume.
+pub fn format_quantity(quantity: f64, unit: SaleUnit) -> String {
+    match unit {
+        SaleUnit::Unit => format!("{}", quantity.round() as i64),
+        SaleUnit::Kg | SaleUnit::L => format!("{:.3} {}", quantity, unit.as_str()).replace('.', ","),
+    }
+}
+
+/// "18,90 €/kg" for products sold by weight or volume.
+pub fn unit_price_label(price: f64, unit: SaleUnit) -> Option<String> {
+    (unit != SaleUnit::Unit).then(|| format!("{}/{}", format_money(price), unit.as_str()))
+}
+
+/// Checks the quantities of an order and rounds them to the unit of each
+/// line; lines left at zero are dropped. The total sent must match the rounded
+/// line amounts within a cent, and is replaced by them.
+pub fn normalize_order(order: &Order) -> Result<Order, String> {
+    let mut order = order.clone();
+    let mut items = Vec::with_capacity(order.items.len());
+    for (index, item) in order.items.iter().enumerate() {
+        let line = format!("Línea {} ({})", index + 1, item.name);
+        if !item.quantity.is_finite() || item.quantity < 0.0 {
+            return Err(format!("{}: la cantidad no puede ser negativa", line));
+        }
+        if item.quantity == 0.0 {
+            continue;
+        }
+        let quantity = round_quantity(item.quantity, item.unit);
+        if item.unit == SaleUnit::Unit && (quantity - item.quantity).abs() > 1e-9 {
+            return Err(format!("{}: se vende por unidades", line));
+        }
+        if quantity <= 0.0 {
+            return Err(format!("{}: cantidad demasiado pequeña", line));
+        }
+        items.push(OrderItem { quantity, ..item.clone() });
+    }
+    order.items = items;
+
+    let total = order_total(&order.items);
+    if (order.total - total).abs() > 0.01 + 1e-9 {
+        return Err(format!(
+            "El total del pedido ({}) no coincide con el de sus líneas ({})",
+            format_money(order.total),
+            format_money(total)
+        ));
+    }
+    order.total = total;
+    Ok(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(name: &str, price: f64, quantity: f64, unit: &str) -> OrderItem {
+        serde_json::from_value(json!({ "id": 1, "name": name, "price": price, "quantity": quantity, "unit": unit }))
+            .unwrap()
+    }
+
+    fn order(total: f64, items: Vec<OrderItem>) -> Order {
+        serde_json::from_value(json!({ "id": 1, "date": "2026-10-18", "total": total, "status": "inProgress", "items": items }))
+            .unwrap()
+    }
+
+    #[test]
+    fn rounds_money_half_away_from_zero() {
+        assert_eq!(round_money(0.25 * 18.9), 4.73);
+        assert_eq!(round_money(1.005), 1.01);
+        assert_eq!(round_money(-2.675), -2.68);
+    }
+
+    #[test]
+    fn rounds_quantities_to_the_unit() {
+        assert_eq!(round_quantity(2.4, SaleUnit::Unit), 2.0);
+        assert_eq!(round_quantity(0.2504, SaleUnit::Kg), 0.25);
+        assert_eq!(format_quantity(0.25, SaleUnit::Kg), "0,250 kg");
+    }
+
+    #[test]
+    fn tax_breakdown_adds_up_to_the_total() {
+        assert_eq!(tax_breakdown(11.33, 10.0), (10.3, 1.03));
+        let (base, tax) = tax_breakdown(0.01, 21.0);
+        assert_eq!(round_money(base + tax), 0.01);
+    }
+
+    #[test]
+    fn tax_lines_split_bundles_by_component_rate() {
+        let mut menu = item("Menú", 12.0, 2.0, "unit");
+        menu.components = serde_json::from_value(json!([
+            { "productId": 1, "name": "Paella", "listPrice": 10.0, "allocatedPrice": 10.0 },
+            { "productId": 2, "name": "Vino", "listPrice": 2.0, "allocatedPrice": 2.0, "taxRate": 21.0 },
+        ]))
+        .unwrap();
+        let lines = tax_lines(&[menu, item("Caña", 2.5, 1.0, "unit")], 10.0);
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].rate, 10.0);
+        assert_eq!(round_money(lines[0].base + lines[0].tax_amount), 22.5);
+        assert_eq!((lines[1].rate, lines[1].base, lines[1].tax_amount), (21.0, 3.31, 0.69));
+    }
+
+    #[test]
+    fn normalize_order_drops_empty_lines_and_checks_the_total() {
+        let items = vec![
+            item("Jamón", 18.9, 0.25, "kg"),
+            item("Caña", 2.2, 0.0, "unit"),
+            item("Agua", 1.5, 2.0, "unit"),
+        ];
+        let normalized = normalize_order(&order(7.725, items.clone())).unwrap();
+        assert_eq!(normalized.items.len(), 2);
+        assert_eq!(normalized.total, 7.73);
+
+        assert!(normalize_order(&order(8.0, items.clone())).unwrap_err().contains("no coincide"));
+
+        let mut negative = items;
+        negative[2].quantity = -1.0;
+        assert_eq!(
+            normalize_order(&order(4.73, negative)).unwrap_err(),
+            "Línea 3 (Agua): la cantidad no puede ser negativa"
+        );
+    }
+
+    #[test]
+    fn normalize_order_rejects_fractions_of_units() {
+        let err = normalize_order(&order(3.0, vec![item("Agua", 2.0, 1.5, "unit")])).unwrap_err();
+        assert_eq!(err, "Línea 1 (Agua): se vende por unidades");
+    }
+}
@@ -0,0 +1,280 @@
+pub mod escpos;
+pub mod label;
+pub mod template;
+pub mod ticket;
+pub mod transport;
+
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::models::printer::{PrinterConfig, PrinterRole, PrinterSettings};
+use escpos::{CodePage, EscPosBuilder};
+use ticket::TicketOptions;
+use transport::PrinterInterface;
+
+pub const CONFIG_FILE: &str = "printers.json";
+/// Single-printer settings read by the thermal-printer-cli sidecar. Kept in
+/// sync with the ticket printer from `printers.json`.
+pub const SETTINGS_FILE: &str = "printerSettings.json";
+const DEFAULT_WIDTH: usize = 48;
+const DEFAULT_TIMEOUT_MS: u64 = 3000;
+const MAX_TIMEOUT_MS: u64 = 60_000;
+const PRINTER_TYPES: [&str; 4] = ["epson", "tanca", "star", "daruma"];
+const BREAK_LINES: [&str; 3] = ["NONE", "CHARACTER", "WORD"];
+const LABEL_DPIS: [u32; 3] = [203, 300, 600];
+// Every code page the settings screen offers (CharacterSet in ThermalPrinter.ts).
+// The sidecar handles all of them; tickets rendered here fail for the ones
+// CodePage can't encode.
+const CHARACTER_SETS: [&str; 37] = [
+    "PC437_USA", "PC850_MULTILINGUAL", "PC860_PORTUGUESE", "PC863_CANADIAN_FRENCH", "PC865_NORDIC",
+    "PC851_GREEK", "PC857_TURKISH", "PC737_GREEK", "ISO8859_7_GREEK", "WPC1252", "PC866_CYRILLIC2",
+    "PC852_LATIN2", "SLOVENIA", "PC858_EURO", "WPC775_BALTIC_RIM", "PC855_CYRILLIC", "PC861_ICELANDIC",
+    "PC862_HEBREW", "PC864_ARABIC", "PC869_GREEK", "ISO8859_2_LATIN2", "ISO8859_15_LATIN9",
+    "PC1125_UKRANIAN", "WPC1250_LATIN2", "WPC1251_CYRILLIC", "WPC1253_GREEK", "WPC1254_TURKISH",
+    "WPC1255_HEBREW", "WPC1256_ARABIC", "WPC1257_BALTIC_RIM", "WPC1258_VIETNAMESE", "KZ1048_KAZAKHSTAN",
+    "JAPAN", "KOREA", "CHINA", "HK_TW", "TCVN_VIETNAMESE",
+];
+
+/// Configured printers. On first use a legacy `printerSettings.json` is
+/// migrated into a single ticket printer.
+pub fn load_configs(app_dir: &Path) -> Result<Vec<PrinterConfig>, String> {
+    let path = app_dir.join(CONFIG_FILE);
+    if path.exists() {
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read printer configuration: {}", e))?;
+        return serde_json::from_str(&contents)
+            .map_err(|e| format!("Invalid printer configuration: {}", e));
+    }
+
+    let legacy_path = app_dir.join(SETTINGS_FILE);
+    let Ok(contents) = fs::read_to_string(&legacy_path) else {
+        return Ok(Vec::new());
+    };
+    let settings: PrinterSettings = match serde_json::from_str(&contents) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Ignoring invalid {}: {}", legacy_path.display(), e);
+            return Ok(Vec::new());
+        }
+    };
+
+    let configs = vec![PrinterConfig {
+        name: "Ticket".to_string(),
+        role: PrinterRole::Ticket,
+        enabled: true,
+        settings,
+    }];
+    write_configs(app_dir, &configs)?;
+    Ok(configs)
+}
+
+pub fn save_configs(app_dir: &Path, configs: &[PrinterConfig]) -> Result<(), String> {
+    validate_configs(configs)?;
+    write_configs(app_dir, configs)
+}
+
+fn write_configs(app_dir: &Path, configs: &[PrinterConfig]) -> Result<(), String> {
+    fs::create_dir_all(app_dir).map_err(|e| format!("Failed to create app directory: {}", e))?;
+
+    let contents = serde_json::to_string_pretty(configs).map_err(|e| e.to_string())?;
+    fs::write(app_dir.join(CONFIG_FILE), contents)
+        .map_err(|e| format!("Failed to write printer configuration: {}", e))?;
+
+    // Without an enabled ticket printer the sidecar must not keep printing to the old one
+    let settings_path = app_dir.join(SETTINGS_FILE);
+    match ticket_printer(configs) {
+        Some(ticket) => {
+            let contents = serde_json::to_string(&ticket.settings).map_err(|e| e.to_string())?;
+            fs::write(&settings_path, contents)
+                .map_err(|e| format!("Failed to write {}: {}", SETTINGS_FILE, e))?;
+        }
+        None if settings_path.exists() => {
+            fs::remove_file(&settings_path)
+                .map_err(|e| format!("Failed to remove {}: {}", SETTINGS_FILE, e))?;
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+pub fn ticket_printer(configs: &[PrinterConfig]) -> Option<&PrinterConfig> {
+    configs
+        .iter()
+        .find(|config| config.enabled && config.role == PrinterRole::Ticket)
+}
+
+/// A label printer by name, or the first enabled one.
+pub fn label_printer<'a>(configs: &'a [PrinterConfig], printer_name: Option<&str>) -> Option<&'a PrinterConfig> {
+    configs.iter().find(|config| {
+        config.enabled
+            && config.role == PrinterRole::Labels
+            && printer_name.is_none_or(|name| config.name.eq_ignore_ascii_case(name.trim()))
+    })
+}
+
+/// The receipt printer.
+pub fn load_ticket_printer(app_dir: &Path) -> Result<PrinterConfig, String> {
+    let configs = load_configs(app_dir)?;
+    ticket_printer(&configs)
+        .cloned()
+        .ok_or_else(|| "No ticket printer is configured".to_string())
+}
+
+pub fn load_settings(app_dir: &Path) -> Result<PrinterSettings, String> {
+    load_ticket_printer(app_dir).map(|config| config.settings)
+}
+
+pub fn validate_configs(configs: &[PrinterConfig]) -> Result<(), String> {
+    for (i, config) in configs.iter().enumerate() {
+        validate_config(config)?;
+
+        let name = config.name.trim();
+        if configs[..i].iter().any(|other| other.name.trim().eq_ignore_ascii_case(name)) {
+            return Err(format!("Ya existe una impresora llamada '{}'", name));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_config(config: &PrinterConfig) -> Result<(), String> {
+    let name = config.name.trim();
+    if name.is_empty() {
+        return Err("El nombre de la impresora es obligatorio".to_string());
+    }
+    let invalid = |reason: String| Err(format!("Impresora '{}': {}", name, reason));
+    let settings = &config.settings;
+
+    if let Err(e) = PrinterInterface::parse(&settings.interface) {
+        return invalid(format!("interfaz no válida ({})", e));
+    }
+    if let Some(printer_type) = &settings.printer_type {
+        if !PRINTER_TYPES.contains(&printer_type.to_lowercase().as_str()) {
+            return invalid(format!("tipo de impresora desconocido '{}'", printer_type));
+        }
+    }
+    if let Some(character_set) = &settings.character_set {
+        if !CHARACTER_SETS.contains(&character_set.as_str()) {
+            return invalid(format!("juego de caracteres desconocido '{}'", character_set));
+        }
+    }
+    if let Some(line_character) = &settings.line_character {
+        if line_character.chars().count() != 1 {
+            return invalid("el carácter de línea debe ser un único carácter".to_string());
+        }
+    }
+    if let Some(break_line) = &settings.break_line {
+        if !BREAK_LINES.contains(&break_line.as_str()) {
+            return invalid(format!("modo de salto de línea desconocido '{}'", break_line));
+        }
+    }
+    if let Some(timeout) = settings.options.as_ref().and_then(|options| options.timeout) {
+        if timeout == 0 || timeout > MAX_TIMEOUT_MS {
+            return invalid(format!("el tiempo de espera debe estar entre 1 y {} ms", MAX_TIMEOUT_MS));
+        }
+    }
+    if let Some(width) = settings.paper_width {
+        if !(16..=80).contains(&width) {
+            return invalid("el ancho de papel debe estar entre 16 y 80 caracteres".to_string());
+        }
+    }
+    if let Some(pin) = settings.drawer_pin {
+        if pin != 2 && pin != 5 {
+            return invalid("el cajón portamonedas usa el pin 2 o el 5".to_string());
+        }
+    }
+    if let Some(label) = &settings.label {
+        if !(10..=120).contains(&label.width_mm) || !(10..=300).contains(&label.height_mm) {
+            return invalid("la etiqueta debe medir entre 10 y 120 mm de ancho y entre 10 y 300 mm de alto".to_string());
+        }
+        if !LABEL_DPIS.contains(&label.dpi) {
+            return invalid(format!("resolución de etiqueta no soportada ({} ppp)", label.dpi));
+        }
+    }
+    if let Some(logo_path) = settings.logo_path.as_deref().filter(|p| !p.is_empty()) {
+        if !Path::new(logo_path).is_file() {
+            return invalid(format!("no se encuentra el logo {}", logo_path));
+        }
+    }
+    Ok(())
+}
+
+pub fn drawer_kick(settings: &PrinterSettings) -> Vec<u8> {
+    let mut printer = EscPosBuilder::new(DEFAULT_WIDTH, CodePage::Ascii);
+    printer.drawer_kick(settings.drawer_pin.unwrap_or(2));
+    printer.build()
+}
+
+pub fn timeout(settings: &PrinterSettings) -> Duration {
+    let millis = settings
+        .options
+        .as_ref()
+        .and_then(|options| options.timeout)
+        .unwrap_or(DEFAULT_TIMEOUT_MS);
+    Duration::from_millis(millis)
+}
+
+pub fn ticket_options(settings: &PrinterSettings) -> Result<TicketOptions, String> {
+    let code_page = if settings.remove_special_characters {
+        CodePage::Ascii
+    } else {
+        match settings.character_set.as_deref() {
+            Some(name) => CodePage::from_name(name).ok_or_else(|| {
+                format!(
+                    "El juego de caracteres '{}' no se puede usar para imprimir tickets; elige PC437_USA, PC850_MULTILINGUAL, PC852_LATIN2, PC858_EURO o WPC1252",
+                    name
+                )
+            })?,
+            None => CodePage::Pc858,
+        }
+    };
+
+    let logo = match settings.logo_path.as_deref().filter(|p| !p.is_empty()) {
+        Some(path) => Some(image::open(path).map_err(|e| format!("Failed to load logo {}: {}", path, e))?),
+        None => None,
+    };
+
+    Ok(TicketOptions {
+        width: settings.paper_width.unwrap_or(DEFAULT_WIDTH),
+        code_page,
+        line_character: settings
+            .line_character
+            .as_deref()
+            .and_then(|c| c.chars().next())
+            .unwrap_or('-'),
+        logo,
+    })
+}
+
+/// Rendering options of a named printer (the ticket printer when None or not
+/// found), or defaults when nothing is configured.
+pub fn printer_options(app_dir: &Path, printer_name: Option<&str>) -> Result<TicketOptions, String> {
+    let configs = load_configs(app_dir)?;
+    let config = printer_name
+        .and_then(|name| configs.iter().find(|config| config.name.eq_ignore_ascii_case(name)))
+        .or_else(|| ticket_printer(&configs));
+
+    match config {
+        Some(config) => ticket_options(&config.settings),
+        None => Ok(TicketOptions::default()),
+    }
+}
+
+/// Options without the logo for a kitchen or bar printer. Uses the printer
+/// configured with the same interface, then the ticket printer, then defaults.
+pub fn comanda_options(app_dir: &Path, interface: &str) -> TicketOptions {
+    let configs = load_configs(app_dir).unwrap_or_default();
+    let config = configs
+        .iter()
+        .find(|config| config.enabled && config.settings.interface.trim() == interface.trim())
+        .or_else(|| ticket_printer(&configs));
+
+    config
+        .map(|config| {
+            let mut settings = config.settings.clone();
+            settings.logo_path = None;
+            settings
+        })
+        .and_then(|settings| ticket_options(&settings).ok())
+        .unwrap_or_default()
+}
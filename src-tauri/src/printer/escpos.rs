@@ -0,0 +1,377 @@
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use crate::barcode::is_valid_gtin;
+
+const ESC: u8 = 0x1B;
+const GS: u8 = 0x1D;
+const LF: u8 = 0x0A;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Printer code pages able to print Spanish text. Names match the
+/// `characterSet` values used by the frontend printer settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodePage {
+    /// Plain ASCII, every accented character is replaced (`removeSpecialCharacters`)
+    Ascii,
+    Pc437,
+    Pc850,
+    Pc852,
+    Pc858,
+    Wpc1252,
+}
+
+impl CodePage {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PC437_USA" => Some(CodePage::Pc437),
+            "PC850_MULTILINGUAL" => Some(CodePage::Pc850),
+            "PC852_LATIN2" => Some(CodePage::Pc852),
+            "PC858_EURO" => Some(CodePage::Pc858),
+            "WPC1252" => Some(CodePage::Wpc1252),
+            _ => None,
+        }
+    }
+
+    // ESC t n values from the Epson ESC/POS reference
+    fn table(self) -> u8 {
+        match self {
+            CodePage::Ascii | CodePage::Pc437 => 0,
+            CodePage::Pc850 => 2,
+            CodePage::Wpc1252 => 16,
+            CodePage::Pc852 => 18,
+            CodePage::Pc858 => 19,
+        }
+    }
+
+    fn encode_char(self, c: char) -> Option<u8> {
+        if c.is_ascii() {
+            return Some(c as u8);
+        }
+        match self {
+            CodePage::Ascii => None,
+            CodePage::Wpc1252 => match c {
+                '€' => Some(0x80),
+                '\u{A0}'..='\u{FF}' => Some(c as u32 as u8),
+                _ => None,
+            },
+            CodePage::Pc437 | CodePage::Pc850 | CodePage::Pc858 => {
+                let common = match c {
+                    'Ç' => 0x80,
+                    'ü' => 0x81,
+                    'é' => 0x82,
+                    'à' => 0x85,
+                    'ç' => 0x87,
+                    'è' => 0x8A,
+                    'É' => 0x90,
+                    'ò' => 0x95,
+                    'Ü' => 0x9A,
+                    'á' => 0xA0,
+                    'í' => 0xA1,
+                    'ó' => 0xA2,
+                    'ú' => 0xA3,
+                    'ñ' => 0xA4,
+                    'Ñ' => 0xA5,
+                    'ª' => 0xA6,
+                    'º' => 0xA7,
+                    '¿' => 0xA8,
+                    '¡' => 0xAD,
+                    _ => 0,
+                };
+                if common != 0 {
+                    return Some(common);
+                }
+                match (self, c) {
+                    (CodePage::Pc437, _) => None,
+                    (_, 'Á') => Some(0xB5),
+                    (_, 'Í') => Some(0xD6),
+                    (_, 'Ó') => Some(0xE0),
+                    (_, 'Ú') => Some(0xE9),
+                    (CodePage::Pc858, '€') => Some(0xD5),
+                    _ => None,
+                }
+            }
+            CodePage::Pc852 => match c {
+                'ü' => Some(0x81),
+                'é' => Some(0x82),
+                'ç' => Some(0x87),
+                'É' => Some(0x90),
+                'Ü' => Some(0x9A),
+                'á' => Some(0xA0),
+                'í' => Some(0xA1),
+                'ó' => Some(0xA2),
+                'ú' => Some(0xA3),
+                'Á' => Some(0xB5),
+                'Í' => Some(0xD6),
+                'Ó' => Some(0xE0),
+                'Ú' => Some(0xE9),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        let mut out = Vec::with_capacity(text.len());
+        for c in text.chars() {
+            match self.encode_char(c) {
+                Some(b) => out.push(b),
+                None => out.extend_from_slice(fallback(c).as_bytes()),
+            }
+        }
+        out
+    }
+}
+
+// Closest ASCII spelling for characters missing from the active code page
+fn fallback(c: char) -> &'static str {
+    match c {
+        'á' | 'à' | 'â' | 'ä' | 'ª' => "a",
+        'é' | 'è' | 'ê' | 'ë' => "e",
+        'í' | 'ì' | 'î' | 'ï' => "i",
+        'ó' | 'ò' | 'ô' | 'ö' | 'º' => "o",
+        'ú' | 'ù' | 'û' | 'ü' => "u",
+        'Á' | 'À' | 'Â' | 'Ä' => "A",
+        'É' | 'È' | 'Ê' | 'Ë' => "E",
+        'Í' | 'Ì' | 'Î' | 'Ï' => "I",
+        'Ó' | 'Ò' | 'Ô' | 'Ö' => "O",
+        'Ú' | 'Ù' | 'Û' | 'Ü' => "U",
+        'ñ' => "n",
+        'Ñ' => "N",
+        'ç' => "c",
+        'Ç' => "C",
+        '¿' | '¡' => "",
+        '€' => "EUR",
+        '·' => ".",
+        '–' | '—' => "-",
+        '‘' | '’' => "'",
+        '“' | '”' => "\"",
+        _ => "?",
+    }
+}
+
+/// Incremental ESC/POS command buffer.
+pub struct EscPosBuilder {
+    buf: Vec<u8>,
+    code_page: CodePage,
+    width: usize,
+}
+
+impl EscPosBuilder {
+    /// `width` is the number of Font A characters per line (48 on 80mm, 32 on 58mm).
+    pub fn new(width: usize, code_page: CodePage) -> Self {
+        let mut builder = EscPosBuilder {
+            buf: Vec::new(),
+            code_page,
+            width,
+        };
+        builder.buf.extend_from_slice(&[ESC, b'@']);
+        builder.buf.extend_from_slice(&[ESC, b't', code_page.table()]);
+        builder
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn align(&mut self, align: Align) -> &mut Self {
+        let n = match align {
+            Align::Left => 0,
+            Align::Center => 1,
+            Align::Right => 2,
+        };
+        self.buf.extend_from_slice(&[ESC, b'a', n]);
+        self
+    }
+
+    pub fn bold(&mut self, on: bool) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'E', on as u8]);
+        self
+    }
+
+    /// Character magnification, 1..=8 in each direction.
+    pub fn size(&mut self, width: u8, height: u8) -> &mut Self {
+        let w = width.clamp(1, 8) - 1;
+        let h = height.clamp(1, 8) - 1;
+        self.buf.extend_from_slice(&[GS, b'!', (w << 4) | h]);
+        self
+    }
+
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        let encoded = self.code_page.encode(text);
+        self.buf.extend_from_slice(&encoded);
+        self
+    }
+
+    pub fn line(&mut self, text: &str) -> &mut Self {
+        self.text(text);
+        self.buf.push(LF);
+        self
+    }
+
+    pub fn separator(&mut self, c: char) -> &mut Self {
+        let line = c.to_string().repeat(self.width);
+        self.line(&line)
+    }
+
+    /// Left and right text on the same line, truncating the left side if needed.
+    pub fn columns(&mut self, left: &str, right: &str) -> &mut Self {
+        let line = two_columns(left, right, self.width);
+        self.line(&line)
+    }
+
+    pub fn feed(&mut self, lines: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[ESC, b'd', lines]);
+        self
+    }
+
+    /// Feeds to the cutter and performs a partial cut.
+    pub fn cut(&mut self) -> &mut Self {
+        self.buf.extend_from_slice(&[GS, b'V', 66, 0]);
+        self
+    }
+
+    /// Cash drawer pulse (ESC p) on connector pin 2 or 5.
+    pub fn drawer_kick(&mut self, pin: u8) -> &mut Self {
+        let m = if pin == 5 { 1 } else { 0 };
+        self.buf.extend_from_slice(&[ESC, b'p', m, 25, 250]);
+        self
+    }
+
+    /// Barcode with its digits printed below: EAN-13 when `code` is a valid
+    /// EAN-13, CODE128 (set B) otherwise. `height` is in dots.
+    pub fn barcode(&mut self, code: &str, height: u8) -> &mut Self {
+        self.buf.extend_from_slice(&[GS, b'h', height.max(1)]);
+        self.buf.extend_from_slice(&[GS, b'w', 2]);
+        self.buf.extend_from_slice(&[GS, b'H', 2]);
+
+        if is_ean13(code) {
+            self.buf.extend_from_slice(&[GS, b'k', 67, 13]);
+            self.buf.extend_from_slice(code.as_bytes());
+        } else {
+            let data: Vec<u8> = code.bytes().filter(|b| (0x20..0x7F).contains(b)).take(253).collect();
+            self.buf.extend_from_slice(&[GS, b'k', 73, (data.len() + 2) as u8, b'{', b'B']);
+            self.buf.extend_from_slice(&data);
+        }
+        self.buf.push(LF);
+        self
+    }
+
+    pub fn raw(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf.extend_from_slice(bytes);
+        self
+    }
+
+    /// Prints an image as a 1-bit raster (GS v 0), scaled down to `max_dots` wide.
+    pub fn image(&mut self, image: &DynamicImage, max_dots: u32) -> &mut Self {
+        let image = if image.width() > max_dots {
+            let height = image.height() * max_dots / image.width();
+            image.resize(max_dots, height.max(1), FilterType::Triangle)
+        } else {
+            image.clone()
+        };
+        let luma = image.to_luma8();
+        let (width, height) = luma.dimensions();
+        let bytes_per_row = width.div_ceil(8);
+
+        self.buf.extend_from_slice(&[
+            GS,
+            b'v',
+            b'0',
+            0,
+            (bytes_per_row & 0xFF) as u8,
+            (bytes_per_row >> 8) as u8,
+            (height & 0xFF) as u8,
+            (height >> 8) as u8,
+        ]);
+
+        for y in 0..height {
+            for byte in 0..bytes_per_row {
+                let mut packed = 0u8;
+                for bit in 0..8 {
+                    let x = byte * 8 + bit;
+                    if x < width && luma.get_pixel(x, y)[0] < 128 {
+                        packed |= 0x80 >> bit;
+                    }
+                }
+                self.buf.push(packed);
+            }
+        }
+        self
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        self.buf.clone()
+    }
+}
+
+/// 13 digits with a valid GS1 check digit.
+pub fn is_ean13(code: &str) -> bool {
+    code.len() == 13 && is_valid_gtin(code)
+}
+
+pub fn two_columns(left: &str, right: &str, width: usize) -> String {
+    let right_len = right.chars().count();
+    let available = width.saturating_sub(right_len + 1);
+    let left: String = left.chars().take(available).collect();
+    let padding = width.saturating_sub(left.chars().count() + right_len);
+    format!("{}{}{}", left, " ".repeat(padding.max(1)), right)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GrayImage, Luma};
+
+    #[test]
+    fn code_pages_encode_spanish_text() {
+        assert_eq!(CodePage::Pc858.encode("Año ¿€?"), [b'A', 0xA4, b'o', b' ', 0xA8, 0xD5, b'?']);
+        assert_eq!(CodePage::Pc850.encode("Ñ€"), [0xA5, b'E', b'U', b'R']);
+        assert_eq!(CodePage::Wpc1252.encode("ñ€"), [0xF1, 0x80]);
+        assert_eq!(CodePage::Pc437.encode("Álvaro"), b"Alvaro");
+        assert_eq!(CodePage::Ascii.encode("Café ¡ya!"), b"Cafe ya!");
+    }
+
+    #[test]
+    fn builder_selects_the_code_page() {
+        let bytes = EscPosBuilder::new(48, CodePage::Pc858).build();
+        assert_eq!(bytes, [ESC, b'@', ESC, b't', 19]);
+        assert_eq!(CodePage::from_name("WPC1252"), Some(CodePage::Wpc1252));
+        assert_eq!(CodePage::from_name("PC999"), None);
+    }
+
+    #[test]
+    fn raster_image_header_and_bits() {
+        // 10x2: first row black from x=0 to x=8, second row white
+        let mut image = GrayImage::from_pixel(10, 2, Luma([255]));
+        for x in 0..9 {
+            image.put_pixel(x, 0, Luma([0]));
+        }
+
+        let mut builder = EscPosBuilder::new(48, CodePage::Ascii);
+        let prefix = builder.build().len();
+        builder.image(&DynamicImage::ImageLuma8(image), 576);
+        let bytes = &builder.build()[prefix..];
+
+        assert_eq!(&bytes[..8], [GS, b'v', b'0', 0, 2, 0, 2, 0]);
+        assert_eq!(&bytes[8..], [0xFF, 0x80, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn raster_image_is_scaled_to_the_paper() {
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(1000, 100, Luma([0])));
+        let mut builder = EscPosBuilder::new(48, CodePage::Ascii);
+        let prefix = builder.build().len();
+        builder.image(&image, 576);
+        let bytes = &builder.build()[prefix..];
+
+        // 576 dots = 72 bytes per row, 57 rows
+        assert_eq!(&bytes[4..8], [72, 0, 57, 0]);
+        assert_eq!(bytes.len(), 8 + 72 * 57);
+    }
+}
@@ -0,0 +1,161 @@
+//! Price labels for products and shelves: ZPL for label printers, ESC/POS
+//! for receipt printers (one label per cut).
+
+use super::escpos::{is_ean13, Align, EscPosBuilder};
+use super::ticket::{format_money, TicketOptions};
+use crate::models::printer::LabelSettings;
+use crate::models::Product;
+
+const MARGIN_MM: f64 = 2.0;
+const ESCPOS_BARCODE_HEIGHT: u8 = 80;
+
+/// Printable fields of one product label.
+#[derive(Debug, Clone)]
+pub struct Label {
+    pub name: String,
+    pub price: String,
+    pub unit_price: Option<String>,
+    pub barcode: Option<String>,
+    pub allergens: Vec<String>,
+}
+
+impl Label {
+    pub fn from_product(product: &Product) -> Self {
+        Label {
+            name: product.name.clone(),
+            price: format_money(product.price),
+            unit_price: unit_price(product),
+            barcode: product
+                .barcode
+                .as_deref()
+                .map(str::trim)
+                .filter(|code| !code.is_empty())
+                .map(str::to_string),
+            allergens: product
+                .allergens
+                .iter()
+                .flatten()
+                .map(|allergen| allergen.trim().to_string())
+                .filter(|allergen| !allergen.is_empty())
+                .collect(),
+        }
+    }
+
+    fn allergens_line(&self) -> Option<String> {
+        (!self.allergens.is_empty()).then(|| format!("Alérgenos: {}", self.allergens.join(", ")))
+    }
+}
+
+/// Price per kilogram or litre ("12,50 €/kg") from the product net content.
+pub fn unit_price(product: &Product) -> Option<String> {
+    let quantity = product.net_quantity.filter(|quantity| *quantity > 0.0)?;
+    let (factor, unit) = match product.net_unit.as_deref()?.trim().to_lowercase().as_str() {
+        "kg" => (1.0, "kg"),
+        "g" => (0.001, "kg"),
+        "l" => (1.0, "l"),
+        "cl" => (0.01, "l"),
+        "ml" => (0.001, "l"),
+        _ => return None,
+    };
+    Some(format!("{}/{}", format_money(product.price / (quantity * factor)), unit))
+}
+
+// Field data with ^FH: the ZPL control characters are sent as hex escapes
+fn zpl_field(text: &str) -> String {
+    let escaped = text
+        .replace('_', "_5F")
+        .replace('^', "_5E")
+        .replace('~', "_7E");
+    format!("^FH_^FD{}^FS", escaped)
+}
+
+/// One ^XA..^XZ format per product, UTF-8 encoded (^CI28).
+pub fn render_zpl(labels: &[Label], settings: &LabelSettings, copies: u32) -> String {
+    let dots = |mm: f64| (mm * settings.dpi as f64 / 25.4).round() as u32;
+    let width = dots(settings.width_mm as f64);
+    let height = dots(settings.height_mm as f64);
+    let margin = dots(MARGIN_MM);
+    let text_width = width.saturating_sub(2 * margin);
+
+    let name_height = dots(3.5);
+    let price_height = dots(8.0);
+    let small_height = dots(2.5);
+    let barcode_height = dots(8.0);
+
+    let mut out = String::new();
+    for label in labels {
+        out.push_str(&format!("^XA^CI28^PW{}^LL{}\n", width, height));
+
+        let mut y = margin;
+        out.push_str(&format!(
+            "^FO{},{}^A0N,{},{}^FB{},2,0,L,0{}\n",
+            margin, y, name_height, name_height, text_width, zpl_field(&label.name)
+        ));
+        y += name_height * 2 + dots(1.0);
+
+        out.push_str(&format!(
+            "^FO{},{}^A0N,{},{}{}\n",
+            margin, y, price_height, price_height, zpl_field(&label.price)
+        ));
+        y += price_height + dots(1.0);
+
+        if let Some(unit_price) = &label.unit_price {
+            out.push_str(&format!(
+                "^FO{},{}^A0N,{},{}{}\n",
+                margin, y, small_height, small_height, zpl_field(unit_price)
+            ));
+            y += small_height + dots(1.0);
+        }
+
+        if let Some(allergens) = label.allergens_line() {
+            out.push_str(&format!(
+                "^FO{},{}^A0N,{},{}^FB{},2,0,L,0{}\n",
+                margin, y, small_height, small_height, text_width, zpl_field(&allergens)
+            ));
+        }
+
+        if let Some(barcode) = &label.barcode {
+            // Leave room for the human readable line below the bars
+            let top = height.saturating_sub(margin + barcode_height + dots(3.0));
+            if is_ean13(barcode) {
+                // ^BE takes the first 12 digits and adds the check digit itself
+                out.push_str(&format!(
+                    "^BY2^FO{},{}^BEN,{},Y,N{}\n",
+                    margin, top, barcode_height, zpl_field(&barcode[..12])
+                ));
+            } else {
+                out.push_str(&format!(
+                    "^BY2^FO{},{}^BCN,{},Y,N,N{}\n",
+                    margin, top, barcode_height, zpl_field(barcode)
+                ));
+            }
+        }
+
+        out.push_str(&format!("^PQ{}\n^XZ\n", copies.max(1)));
+    }
+    out
+}
+
+pub fn render_escpos(labels: &[Label], options: &TicketOptions, copies: u32) -> Vec<u8> {
+    let mut printer = EscPosBuilder::new(options.width, options.code_page);
+
+    for label in labels {
+        for _ in 0..copies.max(1) {
+            printer.align(Align::Center);
+            printer.bold(true).line(&label.name).bold(false);
+            printer.size(2, 2).line(&label.price).size(1, 1);
+            if let Some(unit_price) = &label.unit_price {
+                printer.line(unit_price);
+            }
+            if let Some(barcode) = &label.barcode {
+                printer.barcode(barcode, ESCPOS_BARCODE_HEIGHT);
+            }
+            if let Some(allergens) = label.allergens_line() {
+                printer.align(Align::Left).line(&allergens);
+            }
+            printer.feed(3).cut();
+        }
+    }
+
+    printer.build()
+}
@@ -0,0 +1,829 @@
+//! Line-based receipt templates.
+//!
+//! ```text
+//! # comment
+//! {{ order.id }}                       value from the context
+//! {{ item.total | money | upper }}     filters: money, upper, lower
+//! {% if order.tableNumber %} ... {% else %} ... {% endif %}
+//! {% if order.status == "paid" %}      also !=, and `not path`
+//! {% for item in order.items %} ... {% endfor %}
+//! left text || right text              two columns
+//! @left / @center / @right             alignment of the following lines
+//! @bold / @nobold
+//! @size W H / @normal                  character magnification (1-8)
+//! @line [c]                            separator, optionally with another character
+//! @logo / @feed N / @cut
+//! ```
+
+use base64::Engine;
+use font8x8::{UnicodeFonts, BASIC_FONTS, LATIN_FONTS};
+use image::{DynamicImage, GrayImage, ImageOutputFormat, Luma};
+use serde_json::{json, Map, Value};
+use std::io::Cursor;
+
+use super::escpos::{two_columns, Align, EscPosBuilder};
+use super::ticket::{format_money, TicketOptions};
+use crate::models::prebill::PrebillInfo;
+use crate::models::printer::InvoiceData;
+use crate::models::{BusinessProfile, Customer, Order, OrderItem};
+use crate::pricing::{format_quantity, line_total, round_money, tax_lines, unit_price_label};
+
+pub const TEMPLATE_KINDS: [&str; 3] = ["ticket", "prebill", "invoice"];
+
+const DEFAULT_TICKET: &str = include_str!("templates/ticket.tpl");
+const DEFAULT_PREBILL: &str = include_str!("templates/prebill.tpl");
+const DEFAULT_INVOICE: &str = include_str!("templates/invoice.tpl");
+
+// Preview cell: 12x24 dots per Font A character, like the printer
+const CELL_WIDTH: u32 = 12;
+const CELL_HEIGHT: u32 = 24;
+const MARGIN: u32 = 8;
+const EURO_GLYPH: [u8; 8] = [0x3C, 0x42, 0x1F, 0x02, 0x1F, 0x42, 0x3C, 0x00];
+
+pub fn default_template(kind: &str) -> Option<&'static str> {
+    match kind {
+        "ticket" => Some(DEFAULT_TICKET),
+        "prebill" => Some(DEFAULT_PREBILL),
+        "invoice" => Some(DEFAULT_INVOICE),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Style {
+    pub align: Align,
+    pub bold: bool,
+    pub width: u8,
+    pub height: u8,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            align: Align::Left,
+            bold: false,
+            width: 1,
+            height: 1,
+        }
+    }
+}
+
+/// Rendered template, independent of the output format.
+#[derive(Debug, Clone)]
+pub enum Block {
+    Text { text: String, style: Style },
+    Columns { left: String, right: String, style: Style },
+    Separator(Option<char>),
+    Logo(Align),
+    Feed(u8),
+    Cut,
+}
+
+enum Node {
+    Line { number: usize, text: String },
+    If { condition: String, then: Vec<Node>, otherwise: Vec<Node> },
+    For { variable: String, path: String, body: Vec<Node> },
+}
+
+enum Tag<'a> {
+    If(&'a str),
+    Else,
+    EndIf,
+    For(&'a str, &'a str),
+    EndFor,
+}
+
+fn parse_tag(line: &str, number: usize) -> Result<Option<Tag<'_>>, String> {
+    let Some(inner) = line.strip_prefix("{%").and_then(|l| l.strip_suffix("%}")) else {
+        return Ok(None);
+    };
+    let inner = inner.trim();
+    let tag = match inner.split_once(char::is_whitespace) {
+        Some(("if", condition)) => Tag::If(condition.trim()),
+        Some(("for", rest)) => match rest.split_once(" in ") {
+            Some((variable, path)) => Tag::For(variable.trim(), path.trim()),
+            None => return Err(format!("Línea {}: se esperaba {{% for x in lista %}}", number)),
+        },
+        _ => match inner {
+            "else" => Tag::Else,
+            "endif" => Tag::EndIf,
+            "endfor" => Tag::EndFor,
+            other => return Err(format!("Línea {}: etiqueta desconocida '{}'", number, other)),
+        },
+    };
+    Ok(Some(tag))
+}
+
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut lines = source.lines().enumerate().map(|(i, line)| (i + 1, line));
+        let (nodes, end) = parse_nodes(&mut lines)?;
+        match end {
+            None => Ok(Template { nodes }),
+            Some((number, _)) => Err(format!("Línea {}: cierre de bloque sin apertura", number)),
+        }
+    }
+
+    pub fn render(&self, context: &Value) -> Result<Vec<Block>, String> {
+        let mut renderer = Renderer {
+            scopes: vec![context.clone()],
+            style: Style::default(),
+            blocks: Vec::new(),
+        };
+        renderer.render_nodes(&self.nodes)?;
+        Ok(renderer.blocks)
+    }
+}
+
+// Line number and name of the else/endif/endfor that ended a block
+type Closing = Option<(usize, &'static str)>;
+
+fn parse_nodes<'a, I>(lines: &mut I) -> Result<(Vec<Node>, Closing), String>
+where
+    I: Iterator<Item = (usize, &'a str)>,
+{
+    let mut nodes = Vec::new();
+
+    while let Some((number, raw)) = lines.next() {
+        let line = raw.trim();
+        if line.starts_with('#') {
+            continue;
+        }
+
+        match parse_tag(line, number)? {
+            None => nodes.push(Node::Line {
+                number,
+                text: raw.trim_end().to_string(),
+            }),
+            Some(Tag::If(condition)) => {
+                let (then, end) = parse_nodes(lines)?;
+                let otherwise = match end {
+                    Some((_, "else")) => match parse_nodes(lines)? {
+                        (otherwise, Some((_, "endif"))) => otherwise,
+                        _ => return Err(format!("Línea {}: falta {{% endif %}}", number)),
+                    },
+                    Some((_, "endif")) => Vec::new(),
+                    _ => return Err(format!("Línea {}: falta {{% endif %}}", number)),
+                };
+                nodes.push(Node::If {
+                    condition: condition.to_string(),
+                    then,
+                    otherwise,
+                });
+            }
+            Some(Tag::For(variable, path)) => {
+                let (body, end) = parse_nodes(lines)?;
+                if !matches!(end, Some((_, "endfor"))) {
+                    return Err(format!("Línea {}: falta {{% endfor %}}", number));
+                }
+                nodes.push(Node::For {
+                    variable: variable.to_string(),
+                    path: path.to_string(),
+                    body,
+                });
+            }
+            Some(Tag::Else) => return Ok((nodes, Some((number, "else")))),
+            Some(Tag::EndIf) => return Ok((nodes, Some((number, "endif")))),
+            Some(Tag::EndFor) => return Ok((nodes, Some((number, "endfor")))),
+        }
+    }
+
+    Ok((nodes, None))
+}
+
+struct Renderer {
+    scopes: Vec<Value>,
+    style: Style,
+    blocks: Vec<Block>,
+}
+
+impl Renderer {
+    fn lookup(&self, path: &str) -> Value {
+        let mut segments = path.split('.');
+        let first = segments.next().unwrap_or_default();
+
+        let root = self
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(first))
+            .cloned()
+            .unwrap_or(Value::Null);
+
+        segments.fold(root, |value, segment| match segment.parse::<usize>() {
+            Ok(index) => value.get(index).cloned().unwrap_or(Value::Null),
+            Err(_) => value.get(segment).cloned().unwrap_or(Value::Null),
+        })
+    }
+
+    fn operand(&self, expression: &str) -> Value {
+        let expression = expression.trim();
+        match expression.strip_prefix('"').and_then(|e| e.strip_suffix('"')) {
+            Some(literal) => Value::String(literal.to_string()),
+            None => match expression.parse::<f64>() {
+                Ok(number) => json!(number),
+                Err(_) => self.lookup(expression),
+            },
+        }
+    }
+
+    fn condition(&self, condition: &str) -> bool {
+        if let Some((left, right)) = condition.split_once("==") {
+            return loosely_equal(&self.operand(left), &self.operand(right));
+        }
+        if let Some((left, right)) = condition.split_once("!=") {
+            return !loosely_equal(&self.operand(left), &self.operand(right));
+        }
+        match condition.strip_prefix("not ") {
+            Some(path) => !truthy(&self.lookup(path.trim())),
+            None => truthy(&self.lookup(condition.trim())),
+        }
+    }
+
+    fn interpolate(&self, text: &str, number: usize) -> Result<String, String> {
+        let mut out = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let end = after
+                .find("}}")
+                .ok_or_else(|| format!("Línea {}: falta '}}}}'", number))?;
+
+            let mut parts = after[..end].split('|');
+            let mut value = display(&self.lookup(parts.next().unwrap_or_default().trim()));
+            for filter in parts {
+                value = match filter.trim() {
+                    "money" => value.parse::<f64>().map(format_money).unwrap_or(value),
+                    "upper" => value.to_uppercase(),
+                    "lower" => value.to_lowercase(),
+                    other => return Err(format!("Línea {}: filtro desconocido '{}'", number, other)),
+                };
+            }
+            out.push_str(&value);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn directive(&mut self, directive: &str, number: usize) -> Result<(), String> {
+        let mut parts = directive.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        let mut argument = |default: u8| -> Result<u8, String> {
+            match parts.next() {
+                Some(value) => value
+                    .parse()
+                    .map_err(|_| format!("Línea {}: número no válido '{}'", number, value)),
+                None => Ok(default),
+            }
+        };
+
+        match name {
+            "left" => self.style.align = Align::Left,
+            "center" => self.style.align = Align::Center,
+            "right" => self.style.align = Align::Right,
+            "bold" => self.style.bold = true,
+            "nobold" => self.style.bold = false,
+            "normal" => {
+                self.style.width = 1;
+                self.style.height = 1;
+            }
+            "size" => {
+                let width = argument(1)?.clamp(1, 8);
+                let height = argument(width)?.clamp(1, 8);
+                self.style.width = width;
+                self.style.height = height;
+            }
+            "feed" => {
+                let lines = argument(1)?;
+                self.blocks.push(Block::Feed(lines));
+            }
+            "line" => {
+                let c = directive[name.len()..].trim().chars().next();
+                self.blocks.push(Block::Separator(c));
+            }
+            "logo" => self.blocks.push(Block::Logo(self.style.align)),
+            "cut" => self.blocks.push(Block::Cut),
+            other => return Err(format!("Línea {}: directiva desconocida '@{}'", number, other)),
+        }
+        Ok(())
+    }
+
+    fn render_nodes(&mut self, nodes: &[Node]) -> Result<(), String> {
+        for node in nodes {
+            match node {
+                Node::Line { number, text } => {
+                    if let Some(directive) = text.trim().strip_prefix('@') {
+                        self.directive(directive, *number)?;
+                        continue;
+                    }
+                    let text = self.interpolate(text, *number)?;
+                    let block = match text.split_once("||") {
+                        Some((left, right)) => Block::Columns {
+                            left: left.trim().to_string(),
+                            right: right.trim().to_string(),
+                            style: self.style,
+                        },
+                        None => Block::Text {
+                            text,
+                            style: self.style,
+                        },
+                    };
+                    self.blocks.push(block);
+                }
+                Node::If { condition, then, otherwise } => {
+                    if self.condition(condition) {
+                        self.render_nodes(then)?;
+                    } else {
+                        self.render_nodes(otherwise)?;
+                    }
+                }
+                Node::For { variable, path, body } => {
+                    let items = match self.lookup(path) {
+                        Value::Array(items) => items,
+                        _ => Vec::new(),
+                    };
+                    for item in items {
+                        let mut scope = Map::new();
+                        scope.insert(variable.clone(), item);
+                        self.scopes.push(Value::Object(scope));
+                        let result = self.render_nodes(body);
+                        self.scopes.pop();
+                        result?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.trim().is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(_) => true,
+    }
+}
+
+fn loosely_equal(left: &Value, right: &Value) -> bool {
+    match (left.as_f64(), right.as_f64()) {
+        (Some(l), Some(r)) => l == r,
+        _ => display(left) == display(right),
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if n.is_f64() && f.fract() != 0.0 => f.to_string(),
+            Some(f) if n.is_f64() => format!("{}", f as i64),
+            _ => n.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+// Modifiers printed under the item line, with their price when they change it
+fn extra_lines(item: &OrderItem) -> Vec<String> {
+    let unit_price = unit_price_label(item.price, item.unit);
+    let components = item.components.iter().map(|component| format!("- {}", component.name));
+    let modifiers = item.modifiers
+        .iter()
+        .filter(|modifier| !modifier.variant)
+        .map(|modifier| {
+            if modifier.price_delta > 0.0 {
+                format!("+ {} ({})", modifier.name, format_money(modifier.price_delta))
+            } else if modifier.price_delta < 0.0 {
+                format!("+ {} (-{})", modifier.name, format_money(-modifier.price_delta))
+            } else {
+                format!("+ {}", modifier.name)
+            }
+        });
+    unit_price.into_iter().chain(components).chain(modifiers).collect()
+}
+
+fn payment_method_label(method: &str) -> &str {
+    match method {
+        "efectivo" => "Efectivo",
+        "tarjeta" => "Tarjeta",
+        other => other,
+    }
+}
+
+/// Template context: `order`, `business`, `customer`, `invoice`, `prebill`
+/// and `now`, with a few derived fields so templates don't need arithmetic.
+pub fn build_context(
+    order: &Order,
+    profile: Option<&BusinessProfile>,
+    invoice: Option<&InvoiceData>,
+    customer: Option<&Customer>,
+    prebill: Option<&PrebillInfo>,
+) -> Value {
+    let mut order_value = serde_json::to_value(order).unwrap_or(Value::Null);
+    if let Some(items) = order_value.get_mut("items").and_then(Value::as_array_mut) {
+        for (item, source) in items.iter_mut().zip(&order.items) {
+            // Formatted so templates print "2" or "0,250 kg" as they are
+            item["quantity"] = json!(format_quantity(source.quantity, source.unit));
+            item["total"] = json!(line_total(source));
+            item["extras"] = json!(extra_lines(source));
+        }
+    }
+    order_value["paymentMethodLabel"] = json!(payment_method_label(&order.payment_method));
+    order_value["isPaid"] = json!(order.status == "paid");
+    order_value["tableLabel"] = json!(if order.table_number > 0 {
+        format!("Mesa {}", order.table_number)
+    } else {
+        "Barra".to_string()
+    });
+
+    let business = profile.map(|profile| {
+        let mut value = serde_json::to_value(profile).unwrap_or(Value::Null);
+        let display_name = profile
+            .nombre_comercial
+            .as_deref()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or(&profile.nombre_razon);
+        value["displayName"] = json!(display_name);
+        value["showRazonSocial"] = json!(display_name != profile.nombre_razon);
+        value
+    });
+
+    let invoice = invoice.map(|invoice| {
+        let mut value = serde_json::to_value(invoice).unwrap_or(Value::Null);
+        let taxes = tax_lines(&order.items, invoice.tax_rate);
+        value["base"] = json!(round_money(taxes.iter().map(|tax| tax.base).sum()));
+        value["taxAmount"] = json!(round_money(taxes.iter().map(|tax| tax.tax_amount).sum()));
+        value["taxes"] = json!(taxes);
+        value["simplified"] = json!(invoice.invoice_type == "F2");
+        value
+    });
+
+    let prebill = prebill.map(|prebill| {
+        let mut value = serde_json::to_value(prebill).unwrap_or(Value::Null);
+        value["copy"] = json!(prebill.print_count > 1);
+        value
+    });
+
+    json!({
+        "order": order_value,
+        "business": business,
+        "invoice": invoice,
+        "customer": customer,
+        "prebill": prebill,
+        "now": chrono::Local::now().format("%d/%m/%Y %H:%M").to_string(),
+    })
+}
+
+/// Order used by previews when the caller doesn't provide one.
+pub fn sample_order() -> Result<Order, String> {
+    serde_json::from_value(json!({
+        "id": 1024,
+        "date": chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        "total": 13.7,
+        "change": 6.3,
+        "totalPaid": 20.0,
+        "itemCount": 4,
+        "tableNumber": 5,
+        "paymentMethod": "efectivo",
+        "status": "paid",
+        "items": [
+            { "id": 1, "name": "Caña", "price": 2.2, "quantity": 2, "category": "Bebidas" },
+            { "id": 2, "name": "Tostada con tomate", "price": 3.5, "quantity": 1, "category": "Desayunos" },
+            { "id": 3, "name": "Café con leche", "price": 1.9, "quantity": 2, "category": "Cafés" }
+        ]
+    }))
+    .map_err(|e| format!("Invalid sample order: {}", e))
+}
+
+pub fn to_escpos(blocks: &[Block], options: &TicketOptions) -> Vec<u8> {
+    let mut printer = EscPosBuilder::new(options.width, options.code_page);
+
+    let apply = |printer: &mut EscPosBuilder, style: &Style| {
+        printer
+            .align(style.align)
+            .bold(style.bold)
+            .size(style.width, style.height);
+    };
+
+    for block in blocks {
+        match block {
+            Block::Text { text, style } => {
+                apply(&mut printer, style);
+                printer.line(text);
+            }
+            Block::Columns { left, right, style } => {
+                apply(&mut printer, style);
+                let line = two_columns(left, right, options.width / style.width as usize);
+                printer.line(&line);
+            }
+            Block::Separator(c) => {
+                apply(&mut printer, &Style::default());
+                printer.separator(c.unwrap_or(options.line_character));
+            }
+            Block::Logo(align) => {
+                if let Some(logo) = &options.logo {
+                    printer.align(*align).image(logo, options.logo_dots());
+                }
+            }
+            Block::Feed(lines) => {
+                printer.feed(*lines);
+            }
+            Block::Cut => {
+                printer.cut();
+            }
+        }
+    }
+
+    printer.build()
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() || width == 0 {
+        return vec![String::new()];
+    }
+    chars.chunks(width).map(|chunk| chunk.iter().collect()).collect()
+}
+
+fn aligned(text: &str, align: Align, width: usize) -> String {
+    let padding = width.saturating_sub(text.chars().count());
+    match align {
+        Align::Left => text.to_string(),
+        Align::Center => format!("{}{}", " ".repeat(padding / 2), text),
+        Align::Right => format!("{}{}", " ".repeat(padding), text),
+    }
+}
+
+// Magnified characters take `width` columns on paper, so in the text preview
+// each one is followed by spaces up to that width
+fn magnified(text: &str, width: u8) -> String {
+    if width <= 1 {
+        return text.to_string();
+    }
+    let gap = " ".repeat(width as usize - 1);
+    text.chars().flat_map(|c| std::iter::once(c).chain(gap.chars())).collect()
+}
+
+/// Plain-text rendering for previews. Magnified text is letter-spaced so it
+/// covers the same columns as on paper.
+pub fn to_text(blocks: &[Block], options: &TicketOptions) -> String {
+    let width = options.width;
+    let mut lines = Vec::new();
+
+    for block in blocks {
+        match block {
+            Block::Text { text, style } => {
+                let columns = width / style.width as usize;
+                for line in wrap(text, columns) {
+                    lines.push(magnified(&aligned(&line, style.align, columns), style.width));
+                }
+            }
+            Block::Columns { left, right, style } => {
+                let columns = width / style.width as usize;
+                lines.push(magnified(&two_columns(left, right, columns), style.width));
+            }
+            Block::Separator(c) => lines.push(c.unwrap_or(options.line_character).to_string().repeat(width)),
+            Block::Logo(align) => {
+                if options.logo.is_some() {
+                    lines.push(aligned("[LOGO]", *align, width));
+                }
+            }
+            Block::Feed(count) => lines.extend((0..*count).map(|_| String::new())),
+            Block::Cut => lines.push("- ".repeat(width / 2)),
+        }
+    }
+
+    lines.join("\n")
+}
+
+fn glyph(c: char) -> [u8; 8] {
+    if c == '€' {
+        return EURO_GLYPH;
+    }
+    BASIC_FONTS
+        .get(c)
+        .or_else(|| LATIN_FONTS.get(c))
+        .or_else(|| BASIC_FONTS.get('?'))
+        .unwrap_or_default()
+}
+
+enum Row {
+    Text { text: String, x: u32, style: Style },
+    Image { image: GrayImage, x: u32 },
+    Rule { dashed: bool },
+    Blank(u32),
+}
+
+impl Row {
+    fn height(&self) -> u32 {
+        match self {
+            Row::Text { style, .. } => CELL_HEIGHT * style.height as u32,
+            Row::Image { image, .. } => image.height(),
+            Row::Rule { .. } => CELL_HEIGHT,
+            Row::Blank(height) => *height,
+        }
+    }
+}
+
+fn offset(content: u32, canvas: u32, align: Align) -> u32 {
+    match align {
+        Align::Left => 0,
+        Align::Center => canvas.saturating_sub(content) / 2,
+        Align::Right => canvas.saturating_sub(content),
+    }
+}
+
+/// Bitmap rendering at printer resolution using an 8x8 font, for the
+/// template editor. Returns base64 PNG data.
+pub fn to_png(blocks: &[Block], options: &TicketOptions) -> Result<String, String> {
+    let canvas = options.logo_dots();
+    let mut rows = Vec::new();
+
+    for block in blocks {
+        match block {
+            Block::Text { text, style } => {
+                let per_line = options.width / style.width as usize;
+                for line in wrap(text, per_line) {
+                    let content = line.chars().count() as u32 * CELL_WIDTH * style.width as u32;
+                    rows.push(Row::Text {
+                        x: offset(content, canvas, style.align),
+                        text: line,
+                        style: *style,
+                    });
+                }
+            }
+            Block::Columns { left, right, style } => {
+                let per_line = options.width / style.width as usize;
+                rows.push(Row::Text {
+                    text: two_columns(left, right, per_line),
+                    x: 0,
+                    style: *style,
+                });
+            }
+            Block::Separator(_) => rows.push(Row::Rule { dashed: false }),
+            Block::Logo(align) => {
+                if let Some(logo) = &options.logo {
+                    let logo = if logo.width() > canvas {
+                        let height = (logo.height() * canvas / logo.width()).max(1);
+                        logo.resize(canvas, height, image::imageops::FilterType::Triangle)
+                    } else {
+                        logo.clone()
+                    };
+                    let mut image = logo.to_luma8();
+                    for pixel in image.pixels_mut() {
+                        pixel[0] = if pixel[0] < 128 { 0 } else { 255 };
+                    }
+                    rows.push(Row::Image {
+                        x: offset(image.width(), canvas, *align),
+                        image,
+                    });
+                }
+            }
+            Block::Feed(count) => rows.push(Row::Blank(CELL_HEIGHT * *count as u32)),
+            Block::Cut => rows.push(Row::Rule { dashed: true }),
+        }
+    }
+
+    let height = rows.iter().map(Row::height).sum::<u32>() + MARGIN * 2;
+    let mut image = GrayImage::from_pixel(canvas + MARGIN * 2, height, Luma([255]));
+    let mut y = MARGIN;
+
+    for row in &rows {
+        match row {
+            Row::Text { text, x, style } => {
+                let (sx, sy) = (style.width as u32, style.height as u32 * 2);
+                let cell = CELL_WIDTH * style.width as u32;
+                for (i, c) in text.chars().enumerate() {
+                    let origin_x = MARGIN + x + i as u32 * cell + 2 * sx;
+                    let origin_y = y + 4 * style.height as u32;
+                    for (gy, bits) in glyph(c).iter().enumerate() {
+                        for gx in 0..8u32 {
+                            if bits & (1 << gx) == 0 {
+                                continue;
+                            }
+                            for dy in 0..sy {
+                                for dx in 0..sx + style.bold as u32 {
+                                    let px = origin_x + gx * sx + dx;
+                                    let py = origin_y + gy as u32 * sy + dy;
+                                    if px < image.width() && py < image.height() {
+                                        image.put_pixel(px, py, Luma([0]));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            Row::Image { image: logo, x } => {
+                image::imageops::overlay(&mut image, logo, (MARGIN + x) as i64, y as i64);
+            }
+            Row::Rule { dashed } => {
+                let py = y + CELL_HEIGHT / 2;
+                for px in MARGIN..MARGIN + canvas {
+                    if !dashed || (px / 6) % 2 == 0 {
+                        image.put_pixel(px, py, Luma([0]));
+                    }
+                }
+            }
+            Row::Blank(_) => {}
+        }
+        y += row.height();
+    }
+
+    let mut png = Vec::new();
+    DynamicImage::ImageLuma8(image)
+        .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+        .map_err(|e| format!("Failed to encode preview: {}", e))?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(source: &str, context: Value) -> Vec<Block> {
+        Template::parse(source).unwrap().render(&context).unwrap()
+    }
+
+    fn texts(blocks: &[Block]) -> Vec<String> {
+        blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Text { text, .. } => Some(text.clone()),
+                Block::Columns { left, right, .. } => Some(format!("{}|{}", left, right)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn conditionals() {
+        let source = "{% if order.tableNumber %}\nMesa {{ order.tableNumber }}\n{% else %}\nBarra\n{% endif %}\n\
+                      {% if order.status == \"paid\" %}\nPagado\n{% endif %}\n\
+                      {% if not order.notes %}\nSin notas\n{% endif %}";
+        let blocks = render(source, json!({ "order": { "tableNumber": 5, "status": "paid" } }));
+        assert_eq!(texts(&blocks), ["Mesa 5", "Pagado", "Sin notas"]);
+
+        let blocks = render(source, json!({ "order": { "tableNumber": 0, "status": "open", "notes": "x" } }));
+        assert_eq!(texts(&blocks), ["Barra"]);
+    }
+
+    #[test]
+    fn loops_see_outer_scope() {
+        let source = "{% for item in order.items %}\n{{ item.name }} || {{ order.id }}\n{% endfor %}";
+        let blocks = render(source, json!({ "order": { "id": 7, "items": [{ "name": "Caña" }, { "name": "Café" }] } }));
+        assert_eq!(texts(&blocks), ["Caña|7", "Café|7"]);
+    }
+
+    #[test]
+    fn filters() {
+        let blocks = render(
+            "{{ total | money }}\n{{ name | upper }}\n{{ name | lower }}",
+            json!({ "total": 2.5, "name": "Caña" }),
+        );
+        assert_eq!(texts(&blocks), [format_money(2.5), "CAÑA".to_string(), "caña".to_string()]);
+
+        let error = Template::parse("{{ name | reverse }}").unwrap().render(&json!({})).unwrap_err();
+        assert!(error.contains("reverse"), "{}", error);
+    }
+
+    #[test]
+    fn parse_errors_name_the_line() {
+        assert!(Template::parse("{% if a %}\nx").err().unwrap().contains("Línea 1"));
+        assert!(Template::parse("x\n{% endfor %}").err().unwrap().contains("Línea 2"));
+        assert!(Template::parse("{% while a %}").is_err());
+    }
+
+    #[test]
+    fn default_templates_render_the_sample_order() {
+        let order = sample_order().unwrap();
+        let context = build_context(&order, None, None, None, None);
+        for kind in TEMPLATE_KINDS {
+            let template = Template::parse(default_template(kind).unwrap()).unwrap();
+            assert!(!template.render(&context).unwrap().is_empty(), "{}", kind);
+        }
+    }
+
+    #[test]
+    fn text_preview_aligns_magnified_lines() {
+        let options = TicketOptions { width: 20, ..TicketOptions::default() };
+        let blocks = render("@center\n@size 2 2\nTOTAL\n@right\nAB || 9", json!({}));
+        let text = to_text(&blocks, &options);
+        let lines: Vec<&str> = text.lines().collect();
+        // 10 magnified columns: "TOTAL" starts at column 2, i.e. 4 on paper
+        assert_eq!(lines[0].trim_end(), "    T O T A L");
+        assert_eq!(lines[1].trim_end(), "A B               9");
+    }
+}
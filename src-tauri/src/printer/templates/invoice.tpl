@@ -0,0 +1,57 @@
+# Factura completa (F1) o simplificada (F2)
+@center
+@logo
+{% if business %}
+@bold
+@size 2 2
+{{ business.displayName }}
+@normal
+@nobold
+{{ business.nombreRazon }}
+NIF: {{ business.nif }}
+{{ business.direccion }}
+{{ business.codigoPostal }} {{ business.poblacion }}
+{% endif %}
+@line
+@bold
+{% if invoice.simplified %}
+FACTURA SIMPLIFICADA
+{% else %}
+FACTURA
+{% endif %}
+@nobold
+@left
+Nº {{ invoice.number }} || {{ invoice.date }}
+{% if customer %}
+@line
+Cliente: {{ customer.nombreFiscal }}
+NIF: {{ customer.cifNif }}
+{% if customer.direccion %}
+{{ customer.direccion }}
+{% endif %}
+{{ customer.codigoPostal }} {{ customer.poblacion }}
+{% else %}
+{% if invoice.recipientNif %}
+@line
+NIF cliente: {{ invoice.recipientNif }}
+{% endif %}
+{% endif %}
+@line
+{% for item in order.items %}
+{{ item.quantity }} x {{ item.name }} || {{ item.total | money }}
+{% for extra in item.extras %}
+  {{ extra }}
+{% endfor %}
+{% endfor %}
+@line
+{% for tax in invoice.taxes %}
+Base imponible {{ tax.rate }}% || {{ tax.base | money }}
+IVA {{ tax.rate }}% || {{ tax.taxAmount | money }}
+{% endfor %}
+@bold
+@size 1 2
+TOTAL || {{ order.total | money }}
+@normal
+@nobold
+@feed 3
+@cut
@@ -0,0 +1,43 @@
+# Cuenta (pre-factura) de un pedido en curso
+@center
+{% if business %}
+@bold
+@size 2 2
+{{ business.displayName }}
+@normal
+@nobold
+NIF: {{ business.nif }}
+{% endif %}
+@line
+@bold
+@size 2 2
+CUENTA
+@normal
+@nobold
+@left
+{{ order.tableLabel }} || {{ now }}
+Pedido #{{ order.id }}
+{% if prebill.copy %}
+Copia nº {{ prebill.printCount }}
+{% endif %}
+@line
+{% for item in order.items %}
+{{ item.quantity }} x {{ item.name }} || {{ item.total | money }}
+{% for extra in item.extras %}
+  {{ extra }}
+{% endfor %}
+{% endfor %}
+@line
+@bold
+@size 1 2
+TOTAL || {{ order.total | money }}
+@normal
+@nobold
+@line
+@center
+IVA incluido
+@bold
+NO VÁLIDO COMO FACTURA
+@nobold
+@feed 3
+@cut
@@ -0,0 +1,52 @@
+# Ticket de venta
+@center
+@logo
+{% if business %}
+@bold
+@size 2 2
+{{ business.displayName }}
+@normal
+@nobold
+{% if business.showRazonSocial %}
+{{ business.nombreRazon }}
+{% endif %}
+NIF: {{ business.nif }}
+{% if business.direccion %}
+{{ business.direccion }}
+{% endif %}
+{{ business.codigoPostal }} {{ business.poblacion }}
+{% if business.telefono %}
+Tel: {{ business.telefono }}
+{% endif %}
+{% endif %}
+@left
+@line
+Ticket #{{ order.id }} || {{ order.date }}
+{% if order.tableNumber %}
+Mesa: {{ order.tableNumber }}
+{% endif %}
+@line
+{% for item in order.items %}
+{{ item.quantity }} x {{ item.name }} || {{ item.total | money }}
+{% for extra in item.extras %}
+  {{ extra }}
+{% endfor %}
+{% endfor %}
+@line
+@bold
+@size 1 2
+TOTAL || {{ order.total | money }}
+@normal
+@nobold
+{% if order.paymentMethod %}
+Pagado ({{ order.paymentMethodLabel }}) || {{ order.totalPaid | money }}
+{% endif %}
+{% if order.change %}
+Cambio || {{ order.change | money }}
+{% endif %}
+@line
+@center
+IVA incluido
+¡Gracias por su visita!
+@feed 3
+@cut
@@ -0,0 +1,114 @@
+use image::DynamicImage;
+
+use super::escpos::{Align, CodePage, EscPosBuilder};
+use crate::models::kitchen::Comanda;
+use crate::pricing::format_quantity;
+
+pub struct TicketOptions {
+    pub width: usize,
+    pub code_page: CodePage,
+    pub line_character: char,
+    pub logo: Option<DynamicImage>,
+}
+
+impl Default for TicketOptions {
+    fn default() -> Self {
+        TicketOptions {
+            width: 48,
+            code_page: CodePage::Pc858,
+            line_character: '-',
+            logo: None,
+        }
+    }
+}
+
+impl TicketOptions {
+    /// Raster width for the logo: 12 dots per Font A character (576 on 80mm)
+    pub fn logo_dots(&self) -> u32 {
+        (self.width * 12) as u32
+    }
+}
+
+pub fn format_money(amount: f64) -> String {
+    format!("{:.2} €", amount).replace('.', ",")
+}
+
+/// Kitchen/bar ticket: large item lines, removed items flagged for cancellation.
+pub fn render_comanda(comanda: &Comanda, options: &TicketOptions, reprint: bool) -> Vec<u8> {
+    let mut printer = EscPosBuilder::new(options.width, options.code_page);
+
+    printer.align(Align::Center);
+    if reprint {
+        printer.bold(true).line("*** REIMPRESIÓN ***").bold(false);
+    }
+    printer
+        .bold(true)
+        .size(2, 2)
+        .line(&comanda.station_name.to_uppercase())
+        .size(1, 1)
+        .bold(false)
+        .align(Align::Left);
+
+    let table = if comanda.table_number > 0 {
+        format!("Mesa {}", comanda.table_number)
+    } else {
+        "Barra".to_string()
+    };
+    printer
+        .columns(&format!("Pedido #{}", comanda.order_id), &comanda.created_at)
+        .bold(true)
+        .size(2, 2)
+        .line(&table)
+        .size(1, 1)
+        .bold(false)
+        .separator(options.line_character);
+
+    for item in &comanda.items {
+        if item.quantity == 0.0 {
+            printer.bold(true).line(&format!("CAMBIO NOTA {}", item.name)).bold(false);
+            if item.notes.is_none() {
+                printer.line("    * Sin nota");
+            }
+        } else if item.quantity < 0.0 {
+            printer
+                .bold(true)
+                .line(&format!("ANULAR {} x {}", format_quantity(-item.quantity, item.unit), item.name))
+                .bold(false);
+        } else {
+            printer
+                .size(1, 2)
+                .line(&format!("{} x {}", format_quantity(item.quantity, item.unit), item.name))
+                .size(1, 1);
+        }
+        for modifier in &item.modifiers {
+            printer.line(&format!("    {}", modifier));
+        }
+        if let Some(notes) = &item.notes {
+            printer.bold(true).line(&format!("    * {}", notes)).bold(false);
+        }
+    }
+
+    printer.feed(3).cut();
+    printer.build()
+}
+
+pub fn render_test_page(options: &TicketOptions) -> Vec<u8> {
+    let mut printer = EscPosBuilder::new(options.width, options.code_page);
+
+    printer
+        .align(Align::Center)
+        .bold(true)
+        .size(2, 2)
+        .line("TPV El Haido")
+        .size(1, 1)
+        .bold(false)
+        .line("Página de prueba")
+        .separator(options.line_character)
+        .align(Align::Left)
+        .line("áéíóú ÁÉÍÓÚ ñÑ ü ç ¿? ¡! €")
+        .columns("Izquierda", "Derecha")
+        .feed(3)
+        .cut();
+
+    printer.build()
+}
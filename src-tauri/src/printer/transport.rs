@@ -0,0 +1,157 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::PathBuf;
+use std::time::Duration;
+
+pub const DEFAULT_RAW_PORT: u16 = 9100;
+
+/// Where ESC/POS bytes are delivered. Parsed from the printer `interface`
+/// setting: `tcp://host[:port]` for network printers (IPv6 as `[addr]:port`
+/// or a bare address), anything else is an existing device or file path
+/// (`/dev/usb/lp0`, `COM3`, `/tmp/ticket.bin`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrinterInterface {
+    Tcp { host: String, port: u16 },
+    Device(PathBuf),
+}
+
+impl PrinterInterface {
+    pub fn parse(interface: &str) -> Result<Self, String> {
+        let interface = interface.trim();
+        if interface.is_empty() {
+            return Err("Printer interface is not configured".to_string());
+        }
+
+        if let Some(address) = interface.strip_prefix("tcp://") {
+            let (host, port) = parse_address(address.trim_end_matches('/'))?;
+            if host.is_empty() {
+                return Err(format!("Invalid printer address: {}", interface));
+            }
+            return Ok(PrinterInterface::Tcp { host: host.to_string(), port });
+        }
+
+        Ok(PrinterInterface::Device(PathBuf::from(interface)))
+    }
+
+    pub fn send(&self, data: &[u8], timeout: Duration) -> Result<(), String> {
+        match self {
+            PrinterInterface::Tcp { host, port } => {
+                let address = (host.as_str(), *port)
+                    .to_socket_addrs()
+                    .map_err(|e| format!("Failed to resolve printer {}:{}: {}", host, port, e))?
+                    .next()
+                    .ok_or_else(|| format!("Failed to resolve printer {}:{}", host, port))?;
+
+                let mut stream = TcpStream::connect_timeout(&address, timeout)
+                    .map_err(|e| format!("Failed to connect to printer {}: {}", address, e))?;
+                stream
+                    .set_write_timeout(Some(timeout))
+                    .map_err(|e| e.to_string())?;
+                stream
+                    .write_all(data)
+                    .and_then(|_| stream.flush())
+                    .map_err(|e| format!("Failed to send data to printer {}: {}", address, e))
+            }
+            PrinterInterface::Device(path) => {
+                // Never create the path: a typo must fail instead of printing to a new file
+                let mut device = OpenOptions::new()
+                    .append(true)
+                    .open(path)
+                    .map_err(|e| format!("Failed to open printer device {}: {}", path.display(), e))?;
+                device
+                    .write_all(data)
+                    .and_then(|_| device.flush())
+                    .map_err(|e| format!("Failed to write to printer device {}: {}", path.display(), e))
+            }
+        }
+    }
+
+    /// Opens and closes a connection without sending anything.
+    pub fn test_connection(&self, timeout: Duration) -> Result<(), String> {
+        match self {
+            PrinterInterface::Tcp { .. } => self.send(&[], timeout),
+            PrinterInterface::Device(path) => {
+                if path.exists() {
+                    Ok(())
+                } else {
+                    Err(format!("Printer device {} not found", path.display()))
+                }
+            }
+        }
+    }
+}
+
+// Splits `host[:port]`. IPv6 hosts are written in brackets when a port
+// follows; a bare address with several colons is taken whole.
+fn parse_address(address: &str) -> Result<(&str, u16), String> {
+    let parse_port = |port: &str| {
+        port.parse::<u16>()
+            .map_err(|_| format!("Invalid printer port: {}", port))
+    };
+
+    if let Some(rest) = address.strip_prefix('[') {
+        let (host, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| format!("Invalid printer address: {}", address))?;
+        return match rest {
+            "" => Ok((host, DEFAULT_RAW_PORT)),
+            _ => match rest.strip_prefix(':') {
+                Some(port) => Ok((host, parse_port(port)?)),
+                None => Err(format!("Invalid printer address: {}", address)),
+            },
+        };
+    }
+
+    match address.split_once(':') {
+        Some((host, port)) if !port.contains(':') => Ok((host, parse_port(port)?)),
+        _ => Ok((address, DEFAULT_RAW_PORT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp(host: &str, port: u16) -> PrinterInterface {
+        PrinterInterface::Tcp { host: host.to_string(), port }
+    }
+
+    #[test]
+    fn parses_network_addresses() {
+        assert_eq!(PrinterInterface::parse("tcp://192.168.1.50").unwrap(), tcp("192.168.1.50", 9100));
+        assert_eq!(PrinterInterface::parse("tcp://printer.local:9101/").unwrap(), tcp("printer.local", 9101));
+        assert_eq!(PrinterInterface::parse("tcp://[fe80::1]:9101").unwrap(), tcp("fe80::1", 9101));
+        assert_eq!(PrinterInterface::parse("tcp://[::1]").unwrap(), tcp("::1", 9100));
+        assert_eq!(PrinterInterface::parse("tcp://fe80::1").unwrap(), tcp("fe80::1", 9100));
+        assert!(PrinterInterface::parse("tcp://host:abc").is_err());
+        assert!(PrinterInterface::parse("tcp://[::1").is_err());
+        assert!(PrinterInterface::parse("tcp://:9100").is_err());
+    }
+
+    #[test]
+    fn sends_bytes_over_tcp() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let receiver = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut received = Vec::new();
+            std::io::Read::read_to_end(&mut stream, &mut received).unwrap();
+            received
+        });
+
+        let printer = PrinterInterface::parse(&format!("tcp://127.0.0.1:{}", port)).unwrap();
+        printer.send(b"\x1b@Hola\n", Duration::from_secs(2)).unwrap();
+
+        assert_eq!(receiver.join().unwrap(), b"\x1b@Hola\n");
+    }
+
+    #[test]
+    fn device_paths_are_not_created() {
+        let path = std::env::temp_dir().join("tpv-missing-printer-device");
+        let _ = std::fs::remove_file(&path);
+        let device = PrinterInterface::parse(path.to_str().unwrap()).unwrap();
+        assert!(device.send(b"x", Duration::from_secs(1)).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,48 @@
+use crate::models::purchasing::PurchaseOrderLine;
+
+/// Orders whose lines can still be edited.
+pub fn is_editable(status: &str) -> bool {
+    status == "draft" || status == "sent"
+}
+
+/// Orders that can take deliveries.
+pub fn can_receive(status: &str) -> bool {
+    matches!(status, "draft" | "sent" | "partial")
+}
+
+/// Units of the line still to be delivered.
+pub fn pending_quantity(line: &PurchaseOrderLine) -> f64 {
+    (line.quantity - line.received_quantity).max(0.0)
+}
+
+/// A delivery of `quantity` would take the line above the ordered quantity.
+pub fn exceeds_pending(line: &PurchaseOrderLine, quantity: f64) -> bool {
+    quantity > pending_quantity(line) + 0.000001
+}
+
+/// Status after a delivery: received once every line is complete.
+pub fn status_after_receipt(lines: &[PurchaseOrderLine]) -> &'static str {
+    if lines.iter().all(|line| line.received_quantity >= line.quantity) {
+        "received"
+    } else {
+        "partial"
+    }
+}
+
+/// Units to order so that stock plus pending deliveries covers the minimum
+/// stock and `cover_days` of sales, rounded up to whole units and never
+/// below the usual reorder quantity. Zero when nothing is needed.
+pub fn suggested_quantity(
+    stock: f64,
+    pending: f64,
+    min_stock: f64,
+    reorder_quantity: f64,
+    daily_sales: f64,
+    cover_days: f64,
+) -> f64 {
+    let shortfall = min_stock + daily_sales * cover_days - stock - pending;
+    if shortfall <= 0.0 {
+        return 0.0;
+    }
+    shortfall.ceil().max(reorder_quantity)
+}
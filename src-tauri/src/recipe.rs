@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use crate::models::recipe::{MarginGrouping, MarginLine, RecipeItem};
+use crate::bundle;
+use crate::models::OrderItem;
+
+/// Stock consumed by `items` per product id: the ingredients of products
+/// with a recipe, the product itself otherwise, plus whatever the chosen
+/// modifiers take. A variant with a stock product replaces the product's own,
+/// and bundles consume their components.
+pub fn consumption(items: &[OrderItem], recipes: &HashMap<i64, Vec<RecipeItem>>) -> HashMap<i64, f64> {
+    let mut consumed: HashMap<i64, f64> = HashMap::new();
+    for (product_id, quantity) in sold_parts(items) {
+        for (product_id, quantity) in expand(product_id, quantity, recipes) {
+            *consumed.entry(product_id).or_insert(0.0) += quantity;
+        }
+    }
+    consumed
+}
+
+/// Products whose recipe `consumption` looks up for `items`.
+pub fn sold_products(items: &[OrderItem]) -> Vec<i64> {
+    let mut product_ids: Vec<i64> = sold_parts(items).into_iter().map(|(product_id, _)| product_id).collect();
+    product_ids.sort_unstable();
+    product_ids.dedup();
+    product_ids
+}
+
+// (product id, quantity) sold by `items`, before applying recipes
+fn sold_parts(items: &[OrderItem]) -> Vec<(i64, f64)> {
+    let mut parts = Vec::new();
+    for item in &bundle::expand_items(items) {
+        let quantity = item.quantity;
+        if !replaced_by_variant(item) {
+            parts.push((item.id, quantity));
+        }
+        parts.extend(
+            item.modifiers
+                .iter()
+                .filter_map(|modifier| modifier.product_id.map(|product_id| (product_id, modifier.quantity * quantity))),
+        );
+    }
+    parts
+}
+
+fn replaced_by_variant(item: &OrderItem) -> bool {
+    item.modifiers.iter().any(|modifier| modifier.variant && modifier.product_id.is_some())
+}
+
+/// Unit cost of an order item from the unit costs of its product and of the
+/// stock its modifiers take, or of its components for a bundle. None while
+/// any of them is unknown.
+pub fn item_unit_cost(item: &OrderItem, unit_costs: &HashMap<i64, f64>) -> Option<f64> {
+    if !item.components.is_empty() {
+        return item
+            .components
+            .iter()
+            .try_fold(0.0, |cost, component| Some(cost + unit_costs.get(&component.product_id)?));
+    }
+    let base = if replaced_by_variant(item) { 0.0 } else { *unit_costs.get(&item.id)? };
+    item.modifiers.iter().try_fold(base, |cost, modifier| match modifier.product_id {
+        Some(product_id) => Some(cost + unit_costs.get(&product_id)? * modifier.quantity),
+        None => Some(cost),
+    })
+}
+
+/// (product id, quantity) taken from stock by `quantity` units of a product.
+pub fn expand(product_id: i64, quantity: f64, recipes: &HashMap<i64, Vec<RecipeItem>>) -> Vec<(i64, f64)> {
+    match recipes.get(&product_id) {
+        Some(recipe) if !recipe.is_empty() => recipe
+            .iter()
+            .map(|ingredient| (ingredient.ingredient_id, ingredient.quantity * quantity))
+            .collect(),
+        _ => vec![(product_id, quantity)],
+    }
+}
+
+/// Cost of one unit from its recipe, and whether some ingredient had no cost.
+pub fn recipe_cost(recipe: &[RecipeItem]) -> (f64, bool) {
+    recipe.iter().fold((0.0, false), |(cost, missing), item| match item.unit_cost {
+        Some(unit_cost) => (cost + unit_cost * item.quantity, missing),
+        None => (cost, true),
+    })
+}
+
+/// Unit cost of a product: its recipe cost when it has a recipe, its own
+/// cost price otherwise. None while any of those costs is unknown.
+pub fn unit_cost(cost_price: Option<f64>, recipe: Option<&Vec<RecipeItem>>) -> Option<f64> {
+    match recipe {
+        Some(recipe) if !recipe.is_empty() => match recipe_cost(recipe) {
+            (cost, false) => Some(cost),
+            (_, true) => None,
+        },
+        _ => cost_price,
+    }
+}
+
+/// Sale price without IVA.
+pub fn net_price(price: f64, tax_rate: f64) -> f64 {
+    price / (1.0 + tax_rate / 100.0)
+}
+
+/// An order item line of a paid order, as read for the margin report.
+#[derive(Debug, Clone)]
+pub struct SoldItem {
+    pub date: String,
+    pub product_id: i64,
+    pub name: String,
+    pub category: String,
+    pub price: f64,
+    pub quantity: f64,
+    pub cost_price: Option<f64>,
+}
+
+/// Stock lost to waste or staff consumption, as read for the margin report.
+#[derive(Debug, Clone)]
+pub struct LostItem {
+    pub date: String,
+    pub product_id: i64,
+    pub name: String,
+    pub category: String,
+    pub quantity: f64,
+    pub unit_cost: Option<f64>,
+}
+
+fn group_key(grouping: MarginGrouping, product_id: i64, name: &str, category: &str, date: &str) -> (String, String) {
+    match grouping {
+        MarginGrouping::Product => (product_id.to_string(), name.to_string()),
+        MarginGrouping::Category => (category.to_string(), category.to_string()),
+        MarginGrouping::Day => {
+            let day: String = date.chars().take(10).collect();
+            (day.clone(), day)
+        }
+        MarginGrouping::Month => {
+            let month: String = date.chars().take(7).collect();
+            (month.clone(), month)
+        }
+    }
+}
+
+/// Groups sold items and works out their margins. Losses add to the group's
+/// `loss_cost` without being deducted from the sales margin. Products and
+/// categories come out with the lowest margin first, periods in date order.
+pub fn margin_lines(items: &[SoldItem], losses: &[LostItem], grouping: MarginGrouping, tax_rate: f64) -> Vec<MarginLine> {
+    struct Totals {
+        label: String,
+        quantity: f64,
+        revenue: f64,
+        costed_revenue: f64,
+        cost: Option<f64>,
+        uncosted_quantity: f64,
+        loss_cost: f64,
+    }
+
+    fn empty(label: String) -> Totals {
+        Totals {
+            label,
+            quantity: 0.0,
+            revenue: 0.0,
+            costed_revenue: 0.0,
+            cost: None,
+            uncosted_quantity: 0.0,
+            loss_cost: 0.0,
+        }
+    }
+
+    let mut groups: HashMap<String, Totals> = HashMap::new();
+    for item in items {
+        let (key, label) = group_key(grouping, item.product_id, &item.name, &item.category, &item.date);
+        let totals = groups.entry(key).or_insert_with(|| empty(label));
+
+        let revenue = net_price(item.price, tax_rate) * item.quantity;
+        totals.quantity += item.quantity;
+        totals.revenue += revenue;
+        match item.cost_price {
+            Some(cost_price) => {
+                totals.costed_revenue += revenue;
+                totals.cost = Some(totals.cost.unwrap_or(0.0) + cost_price * item.quantity);
+            }
+            None => totals.uncosted_quantity += item.quantity,
+        }
+    }
+
+    for loss in losses {
+        let (key, label) = group_key(grouping, loss.product_id, &loss.name, &loss.category, &loss.date);
+        groups.entry(key).or_insert_with(|| empty(label)).loss_cost += loss.unit_cost.unwrap_or(0.0) * loss.quantity;
+    }
+
+    let mut lines: Vec<MarginLine> = groups
+        .into_iter()
+        .map(|(key, totals)| {
+            let margin = totals.cost.map(|cost| totals.costed_revenue - cost);
+            MarginLine {
+                key,
+                label: totals.label,
+                quantity: totals.quantity,
+                revenue: totals.revenue,
+                cost: totals.cost,
+                margin,
+                margin_percent: margin
+                    .filter(|_| totals.costed_revenue > 0.0)
+                    .map(|margin| margin / totals.costed_revenue * 100.0),
+                uncosted_quantity: totals.uncosted_quantity,
+                loss_cost: totals.loss_cost,
+            }
+        })
+        .collect();
+
+    match grouping {
+        MarginGrouping::Product | MarginGrouping::Category => lines.sort_by(|a, b| {
+            match (a.margin_percent, b.margin_percent) {
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then_with(|| a.label.cmp(&b.label))
+        }),
+        MarginGrouping::Day | MarginGrouping::Month => lines.sort_by(|a, b| a.key.cmp(&b.key)),
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(value: serde_json::Value) -> OrderItem {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn ingredient(ingredient_id: i64, quantity: f64, unit_cost: Option<f64>) -> RecipeItem {
+        RecipeItem { ingredient_id, ingredient_name: String::new(), quantity, unit_cost }
+    }
+
+    #[test]
+    fn consumption_applies_recipes_and_modifiers() {
+        let recipes = HashMap::from([(1, vec![ingredient(10, 0.05, None), ingredient(11, 0.2, None)])]);
+        let items = [
+            item(json!({ "id": 1, "name": "Gin tonic", "price": 8.0, "quantity": 2.0,
+                         "modifiers": [{ "name": "Extra de tónica", "productId": 11, "quantity": 1.0 }] })),
+            item(json!({ "id": 2, "name": "Caña", "price": 2.0, "quantity": 3.0,
+                         "modifiers": [{ "name": "Tercio", "productId": 3, "quantity": 1.0, "variant": true }] })),
+        ];
+        let consumed = consumption(&items, &recipes);
+
+        assert_eq!(consumed[&10], 0.1);
+        assert_eq!(consumed[&11], 2.4);
+        assert_eq!(consumed[&3], 3.0);
+        assert!(!consumed.contains_key(&1));
+        assert!(!consumed.contains_key(&2));
+        assert_eq!(sold_products(&items), [1, 3, 11]);
+    }
+
+    #[test]
+    fn unit_costs_need_every_cost() {
+        assert_eq!(unit_cost(Some(1.5), None), Some(1.5));
+        assert_eq!(unit_cost(Some(1.5), Some(&vec![ingredient(10, 2.0, Some(0.5))])), Some(1.0));
+        assert_eq!(unit_cost(Some(1.5), Some(&vec![ingredient(10, 2.0, None)])), None);
+
+        let costs = HashMap::from([(1, 2.0), (5, 0.5)]);
+        let burger = item(json!({ "id": 1, "name": "Hamburguesa", "price": 9.0, "quantity": 1.0,
+                                  "modifiers": [{ "name": "Queso", "productId": 5, "quantity": 2.0 }] }));
+        assert_eq!(item_unit_cost(&burger, &costs), Some(3.0));
+        let unknown = item(json!({ "id": 7, "name": "Ensalada", "price": 7.0, "quantity": 1.0 }));
+        assert_eq!(item_unit_cost(&unknown, &costs), None);
+    }
+
+    #[test]
+    fn net_price_removes_the_tax() {
+        assert!((net_price(11.0, 10.0) - 10.0).abs() < 1e-9);
+    }
+}
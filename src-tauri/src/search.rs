@@ -0,0 +1,30 @@
+/// FTS5 query for what was typed in the search box: every word has to match
+/// the start of a word in the name, brand, category or barcodes. Words are
+/// quoted so nothing typed is read as FTS5 syntax. None when there is
+/// nothing to search for.
+pub fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term.to_lowercase()))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_word_is_a_quoted_prefix() {
+        assert_eq!(fts_query("Coca-Cola Zero").as_deref(), Some("\"coca\"* \"cola\"* \"zero\"*"));
+        assert_eq!(fts_query("Jamón").as_deref(), Some("\"jamón\"*"));
+    }
+
+    #[test]
+    fn fts_syntax_is_not_passed_through() {
+        assert_eq!(fts_query("a\" OR b*").as_deref(), Some("\"a\"* \"or\"* \"b\"*"));
+        assert_eq!(fts_query("  -,  "), None);
+        assert_eq!(fts_query(""), None);
+    }
+}
@@ -0,0 +1,169 @@
+use crate::models::stock::{LossLine, LossSummary, NewStockMovement, StockMovement};
+
+/// Orders take their items out of stock once they are paid.
+pub fn consumes_stock(order_status: &str) -> bool {
+    order_status == "paid"
+}
+
+/// Stock change of a movement entered by hand. Sales only come from orders.
+pub fn signed_quantity(movement_type: &str, quantity: f64) -> Result<f64, String> {
+    if !quantity.is_finite() || quantity == 0.0 {
+        return Err("La cantidad debe ser distinta de cero".to_string());
+    }
+    match movement_type {
+        "purchase" | "return" => Ok(quantity.abs()),
+        "waste" | "comp" => Ok(-quantity.abs()),
+        "adjustment" => Ok(quantity),
+        "sale" => Err("Las ventas se registran al cobrar los pedidos".to_string()),
+        _ => Err(format!("Tipo de movimiento desconocido: {}", movement_type)),
+    }
+}
+
+/// Stock taken out without a sale: waste (broken, spilled, expired) and
+/// comp (staff meals, invitations). Costed, but never part of sales.
+pub fn is_loss(movement_type: &str) -> bool {
+    matches!(movement_type, "waste" | "comp")
+}
+
+/// (movement type, reason code, label) of the accepted loss reasons.
+pub const LOSS_REASONS: &[(&str, &str, &str)] = &[
+    ("waste", "breakage", "Rotura"),
+    ("waste", "spillage", "Derrame"),
+    ("waste", "expired", "Caducado"),
+    ("waste", "kitchen_error", "Error de cocina"),
+    ("waste", "other", "Otra merma"),
+    ("comp", "staff_meal", "Consumo de personal"),
+    ("comp", "invitation", "Invitación"),
+    ("comp", "other", "Otro consumo interno"),
+];
+
+pub fn loss_reason_label(movement_type: &str, reason_code: &str) -> Option<&'static str> {
+    LOSS_REASONS
+        .iter()
+        .find(|(kind, code, _)| *kind == movement_type && *code == reason_code)
+        .map(|(_, _, label)| *label)
+}
+
+/// Checks that a waste or comp movement says why and who is responsible.
+pub fn validate_loss(movement: &NewStockMovement) -> Result<(), String> {
+    let code = movement.reason_code.as_deref().unwrap_or_default();
+    if loss_reason_label(&movement.movement_type, code).is_none() {
+        return Err(format!("Motivo no válido: {}", code));
+    }
+    if movement.user_id.is_none() {
+        return Err("Indica el usuario responsable".to_string());
+    }
+    Ok(())
+}
+
+/// Totals of loss movements by reason, product and responsible user.
+/// Movements carry their signed quantity, so losses are negative.
+pub fn loss_summary(movements: &[StockMovement]) -> LossSummary {
+    fn add(lines: &mut Vec<LossLine>, key: String, label: String, movement: &StockMovement) {
+        let index = match lines.iter().position(|line| line.key == key) {
+            Some(index) => index,
+            None => {
+                lines.push(LossLine {
+                    key,
+                    label,
+                    movements: 0,
+                    quantity: 0.0,
+                    cost: 0.0,
+                    uncosted_quantity: 0.0,
+                });
+                lines.len() - 1
+            }
+        };
+        let line = &mut lines[index];
+        let quantity = -movement.quantity;
+        line.movements += 1;
+        line.quantity += quantity;
+        match movement.unit_cost {
+            Some(unit_cost) => line.cost += unit_cost * quantity,
+            None => line.uncosted_quantity += quantity,
+        }
+    }
+
+    let mut summary = LossSummary::default();
+    for movement in movements.iter().filter(|movement| is_loss(&movement.movement_type)) {
+        let code = movement.reason_code.clone().unwrap_or_default();
+        let reason = loss_reason_label(&movement.movement_type, &code).unwrap_or("Sin motivo");
+        add(&mut summary.by_reason, format!("{}:{}", movement.movement_type, code), reason.to_string(), movement);
+        add(
+            &mut summary.by_product,
+            movement.product_id.to_string(),
+            movement.product_name.clone().unwrap_or_default(),
+            movement,
+        );
+        add(
+            &mut summary.by_user,
+            movement.user_id.map(|id| id.to_string()).unwrap_or_default(),
+            movement.user_name.clone().unwrap_or_else(|| "Sin usuario".to_string()),
+            movement,
+        );
+
+        let quantity = -movement.quantity;
+        match movement.unit_cost {
+            Some(unit_cost) => summary.total_cost += unit_cost * quantity,
+            None => summary.uncosted_movements += 1,
+        }
+    }
+
+    for lines in [&mut summary.by_reason, &mut summary.by_product, &mut summary.by_user] {
+        lines.sort_by(|a, b| b.cost.total_cmp(&a.cost).then_with(|| a.label.cmp(&b.label)));
+    }
+    summary
+}
+
+/// A change from above the minimum to at or below it.
+pub fn crossed_minimum(previous_stock: f64, stock: f64, min_stock: f64) -> bool {
+    previous_stock > min_stock && stock <= min_stock
+}
+
+pub fn suggested_quantity(stock: f64, min_stock: f64, reorder_quantity: Option<f64>) -> f64 {
+    reorder_quantity.unwrap_or(0.0).max(min_stock - stock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn loss(movement_type: &str, reason_code: Option<&str>, user_id: Option<i64>) -> NewStockMovement {
+        NewStockMovement {
+            product_id: 1,
+            movement_type: movement_type.to_string(),
+            quantity: 1.0,
+            reason: None,
+            reason_code: reason_code.map(str::to_string),
+            user_id,
+            order_id: None,
+        }
+    }
+
+    #[test]
+    fn movement_signs() {
+        assert_eq!(signed_quantity("purchase", -3.0), Ok(3.0));
+        assert_eq!(signed_quantity("waste", 2.0), Ok(-2.0));
+        assert_eq!(signed_quantity("adjustment", -1.5), Ok(-1.5));
+        assert!(signed_quantity("sale", 1.0).is_err());
+        assert!(signed_quantity("purchase", 0.0).is_err());
+        assert!(signed_quantity("robo", 1.0).is_err());
+    }
+
+    #[test]
+    fn losses_need_a_reason_and_a_user() {
+        assert!(validate_loss(&loss("waste", Some("breakage"), Some(1))).is_ok());
+        assert!(validate_loss(&loss("waste", Some("staff_meal"), Some(1))).is_err());
+        assert!(validate_loss(&loss("comp", Some("staff_meal"), None)).is_err());
+        assert_eq!(loss_reason_label("comp", "invitation"), Some("Invitación"));
+    }
+
+    #[test]
+    fn minimum_stock() {
+        assert!(crossed_minimum(6.0, 5.0, 5.0));
+        assert!(!crossed_minimum(5.0, 4.0, 5.0));
+        assert_eq!(suggested_quantity(2.0, 5.0, Some(12.0)), 12.0);
+        assert_eq!(suggested_quantity(-4.0, 5.0, Some(6.0)), 9.0);
+        assert_eq!(suggested_quantity(2.0, 5.0, None), 3.0);
+    }
+}
@@ -0,0 +1,379 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const NIF_LETTERS: &[u8; 23] = b"TRWAGMYFPDXBNJZSQVHLCKE";
+const CIF_CONTROL_LETTERS: &[u8; 10] = b"JABCDEFGHI";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaxIdKind {
+    Nif,
+    Nie,
+    Cif,
+    EuVat,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaxId {
+    pub kind: TaxIdKind,
+    /// Uppercased value without spaces, dots or hyphens
+    pub value: String,
+    /// ISO country code (ES for Spanish identifiers)
+    pub country: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaxIdError {
+    Empty,
+    InvalidLength { expected: usize, found: usize },
+    InvalidFormat(String),
+    InvalidCheckDigit { expected: char, found: char },
+    UnknownCountry(String),
+    RecipientRequired(String),
+}
+
+impl fmt::Display for TaxIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaxIdError::Empty => write!(f, "El NIF/CIF es obligatorio"),
+            TaxIdError::InvalidLength { expected, found } => write!(
+                f,
+                "El NIF/CIF debe tener {} caracteres (tiene {})",
+                expected, found
+            ),
+            TaxIdError::InvalidFormat(reason) => write!(f, "Formato de NIF/CIF inválido: {}", reason),
+            TaxIdError::InvalidCheckDigit { expected, found } => write!(
+                f,
+                "Dígito de control incorrecto: se esperaba '{}' y se encontró '{}'",
+                expected, found
+            ),
+            TaxIdError::UnknownCountry(code) => {
+                write!(f, "Código de país '{}' no pertenece a la UE", code)
+            }
+            TaxIdError::RecipientRequired(invoice_type) => write!(
+                f,
+                "Las facturas {} requieren un NIF válido del destinatario",
+                invoice_type
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TaxIdError {}
+
+pub fn normalize(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-' && *c != '.')
+        .flat_map(|c| c.to_uppercase())
+        .collect()
+}
+
+/// Validates a Spanish NIF, NIE or CIF, or an intra-EU VAT number
+/// (two-letter country prefix, e.g. "FR12345678901").
+pub fn validate_tax_id(value: &str) -> Result<TaxId, TaxIdError> {
+    let value = normalize(value);
+    if value.is_empty() {
+        return Err(TaxIdError::Empty);
+    }
+
+    // "ES" followed by a Spanish identifier is the intra-EU form of a NIF/CIF
+    let bytes = value.as_bytes();
+    if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1].is_ascii_alphabetic() {
+        let country = &value[..2];
+        if country == "ES" {
+            let mut tax_id = validate_spanish_tax_id(&value[2..])?;
+            tax_id.kind = TaxIdKind::EuVat;
+            tax_id.value = value;
+            return Ok(tax_id);
+        }
+        return validate_eu_vat(&value);
+    }
+
+    validate_spanish_tax_id(&value)
+}
+
+pub fn validate_spanish_tax_id(value: &str) -> Result<TaxId, TaxIdError> {
+    let value = normalize(value);
+    if value.is_empty() {
+        return Err(TaxIdError::Empty);
+    }
+    if !value.is_ascii() {
+        return Err(TaxIdError::InvalidFormat(
+            "solo se admiten letras y dígitos".to_string(),
+        ));
+    }
+    if value.len() != 9 {
+        return Err(TaxIdError::InvalidLength {
+            expected: 9,
+            found: value.chars().count(),
+        });
+    }
+
+    let kind = match value.as_bytes()[0] {
+        b'0'..=b'9' | b'K' | b'L' | b'M' => {
+            validate_nif(&value)?;
+            TaxIdKind::Nif
+        }
+        b'X' | b'Y' | b'Z' => {
+            validate_nie(&value)?;
+            TaxIdKind::Nie
+        }
+        b'A' | b'B' | b'C' | b'D' | b'E' | b'F' | b'G' | b'H' | b'J' | b'N' | b'P' | b'Q'
+        | b'R' | b'S' | b'U' | b'V' | b'W' => {
+            validate_cif(&value)?;
+            TaxIdKind::Cif
+        }
+        other => {
+            return Err(TaxIdError::InvalidFormat(format!(
+                "la letra inicial '{}' no corresponde a ningún tipo de NIF",
+                other as char
+            )))
+        }
+    };
+
+    Ok(TaxId {
+        kind,
+        value,
+        country: "ES".to_string(),
+    })
+}
+
+fn nif_letter(number: u32) -> char {
+    NIF_LETTERS[(number % 23) as usize] as char
+}
+
+fn check_control(expected: char, found: char) -> Result<(), TaxIdError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(TaxIdError::InvalidCheckDigit { expected, found })
+    }
+}
+
+fn parse_digits(digits: &str, what: &str) -> Result<u32, TaxIdError> {
+    if !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(TaxIdError::InvalidFormat(format!(
+            "{} debe contener solo dígitos",
+            what
+        )));
+    }
+    digits
+        .parse()
+        .map_err(|_| TaxIdError::InvalidFormat(format!("{} no es numérico", what)))
+}
+
+// DNI-based NIF: 8 digits + letter. K, L and M prefixes replace the first digit
+// and use the same letter algorithm over the remaining 7 digits.
+fn validate_nif(value: &str) -> Result<(), TaxIdError> {
+    let digits = if value.as_bytes()[0].is_ascii_digit() {
+        &value[..8]
+    } else {
+        &value[1..8]
+    };
+    let number = parse_digits(digits, "el número del NIF")?;
+    let found = value.as_bytes()[8] as char;
+    if !found.is_ascii_alphabetic() {
+        return Err(TaxIdError::InvalidFormat(
+            "el NIF debe terminar en letra".to_string(),
+        ));
+    }
+    check_control(nif_letter(number), found)
+}
+
+// NIE: X/Y/Z + 7 digits + letter, the prefix maps to 0/1/2
+fn validate_nie(value: &str) -> Result<(), TaxIdError> {
+    let prefix = match value.as_bytes()[0] {
+        b'X' => 0,
+        b'Y' => 1,
+        _ => 2,
+    };
+    let number = parse_digits(&value[1..8], "el número del NIE")?;
+    let found = value.as_bytes()[8] as char;
+    if !found.is_ascii_alphabetic() {
+        return Err(TaxIdError::InvalidFormat(
+            "el NIE debe terminar en letra".to_string(),
+        ));
+    }
+    check_control(nif_letter(prefix * 10_000_000 + number), found)
+}
+
+// CIF: entity letter + 7 digits + control digit or letter
+fn validate_cif(value: &str) -> Result<(), TaxIdError> {
+    let entity = value.as_bytes()[0];
+    let digits = &value[1..8];
+    parse_digits(digits, "el número del CIF")?;
+
+    let mut sum = 0;
+    for (i, b) in digits.bytes().enumerate() {
+        let d = (b - b'0') as u32;
+        if i % 2 == 0 {
+            let doubled = d * 2;
+            sum += doubled / 10 + doubled % 10;
+        } else {
+            sum += d;
+        }
+    }
+    let control_digit = (10 - sum % 10) % 10;
+    let expected_digit = char::from_digit(control_digit, 10).unwrap_or('0');
+    let expected_letter = CIF_CONTROL_LETTERS[control_digit as usize] as char;
+    let found = value.as_bytes()[8] as char;
+
+    match entity {
+        // Entities without legal personality, public bodies and foreign entities
+        b'N' | b'P' | b'Q' | b'R' | b'S' | b'W' => check_control(expected_letter, found),
+        // Companies (S.A., S.L.), communities of goods and associations
+        b'A' | b'B' | b'E' | b'H' => check_control(expected_digit, found),
+        _ => {
+            if found == expected_digit || found == expected_letter {
+                Ok(())
+            } else if found.is_ascii_digit() {
+                check_control(expected_digit, found)
+            } else {
+                check_control(expected_letter, found)
+            }
+        }
+    }
+}
+
+// Format patterns per member state: 'd' digit, 'a' letter, 'x' digit or letter,
+// any other character must appear literally
+fn eu_vat_patterns(country: &str) -> Option<&'static [&'static str]> {
+    let patterns: &'static [&'static str] = match country {
+        "AT" => &["Udddddddd"],
+        "BE" => &["0ddddddddd", "1ddddddddd"],
+        "BG" => &["ddddddddd", "dddddddddd"],
+        "CY" => &["dddddddda"],
+        "CZ" => &["dddddddd", "ddddddddd", "dddddddddd"],
+        "DE" => &["ddddddddd"],
+        "DK" => &["dddddddd"],
+        "EE" => &["ddddddddd"],
+        "EL" => &["ddddddddd"],
+        "FI" => &["dddddddd"],
+        "FR" => &["xxddddddddd"],
+        "HR" => &["ddddddddddd"],
+        "HU" => &["dddddddd"],
+        "IE" => &["ddddddda", "dddddddaa", "dxddddda"],
+        "IT" => &["ddddddddddd"],
+        "LT" => &["ddddddddd", "dddddddddddd"],
+        "LU" => &["dddddddd"],
+        "LV" => &["ddddddddddd"],
+        "MT" => &["dddddddd"],
+        "NL" => &["dddddddddBdd"],
+        "PL" => &["dddddddddd"],
+        "PT" => &["ddddddddd"],
+        "RO" => &[
+            "dd", "ddd", "dddd", "ddddd", "dddddd", "ddddddd", "dddddddd", "ddddddddd",
+            "dddddddddd",
+        ],
+        "SE" => &["dddddddddd01"],
+        "SI" => &["dddddddd"],
+        "SK" => &["dddddddddd"],
+        "XI" => &["ddddddddd", "dddddddddddd", "GDddd", "HAddd"],
+        _ => return None,
+    };
+    Some(patterns)
+}
+
+fn matches_pattern(value: &str, pattern: &str) -> bool {
+    value.len() == pattern.len()
+        && value.bytes().zip(pattern.bytes()).all(|(c, p)| match p {
+            b'd' => c.is_ascii_digit(),
+            b'a' => c.is_ascii_uppercase(),
+            b'x' => c.is_ascii_digit() || c.is_ascii_uppercase(),
+            literal => c == literal,
+        })
+}
+
+fn validate_eu_vat(value: &str) -> Result<TaxId, TaxIdError> {
+    let country = &value[..2];
+    let number = &value[2..];
+    let patterns = eu_vat_patterns(country)
+        .ok_or_else(|| TaxIdError::UnknownCountry(country.to_string()))?;
+
+    if !patterns.iter().any(|p| matches_pattern(number, p)) {
+        return Err(TaxIdError::InvalidFormat(format!(
+            "'{}' no tiene el formato de NIF-IVA de {}",
+            number, country
+        )));
+    }
+
+    Ok(TaxId {
+        kind: TaxIdKind::EuVat,
+        value: value.to_string(),
+        country: country.to_string(),
+    })
+}
+
+/// Identity checks before issuing an invoice: the issuer must always hold a
+/// valid Spanish NIF, and F1/F3 invoices must identify the recipient.
+pub fn validate_invoice_parties(
+    invoice_type: &str,
+    issuer_nif: &str,
+    recipient_nif: Option<&str>,
+) -> Result<(), String> {
+    validate_spanish_tax_id(issuer_nif).map_err(|e| format!("NIF del emisor: {}", e))?;
+
+    let recipient = recipient_nif.filter(|nif| !nif.trim().is_empty());
+    match (invoice_type, recipient) {
+        ("F1" | "F3", None) => Err(TaxIdError::RecipientRequired(invoice_type.to_string()).to_string()),
+        (_, Some(nif)) => validate_tax_id(nif)
+            .map(|_| ())
+            .map_err(|e| format!("NIF del destinatario: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nif_check_letter() {
+        assert_eq!(nif_letter(12345678), 'Z');
+        assert_eq!(validate_tax_id("12345678-z").unwrap().kind, TaxIdKind::Nif);
+        assert_eq!(
+            validate_tax_id("12345678A").unwrap_err(),
+            TaxIdError::InvalidCheckDigit { expected: 'Z', found: 'A' }
+        );
+    }
+
+    #[test]
+    fn nie_replaces_prefix_letter() {
+        assert_eq!(validate_tax_id("X1234567L").unwrap().kind, TaxIdKind::Nie);
+        assert_eq!(validate_tax_id("Y1234567X").unwrap().kind, TaxIdKind::Nie);
+        assert!(validate_tax_id("X1234567A").is_err());
+    }
+
+    #[test]
+    fn cif_control_digit_and_letter() {
+        // A/B: digit control, P: letter control, G: either
+        assert_eq!(validate_tax_id("A58818501").unwrap().kind, TaxIdKind::Cif);
+        assert!(validate_tax_id("A58818502").is_err());
+        assert!(validate_tax_id("B12345674").is_ok());
+        assert!(validate_tax_id("B1234567D").is_err());
+        assert_eq!(validate_tax_id("P2807900B").unwrap().kind, TaxIdKind::Cif);
+        assert!(validate_tax_id("P28079002").is_err());
+        assert!(validate_tax_id("G12345674").is_ok());
+        assert!(validate_tax_id("G1234567D").is_ok());
+    }
+
+    #[test]
+    fn eu_vat_numbers() {
+        let es = validate_tax_id("ES B12345674").unwrap();
+        assert_eq!(es.kind, TaxIdKind::EuVat);
+        assert_eq!(es.value, "ESB12345674");
+        assert_eq!(validate_tax_id("FR12345678901").unwrap().country, "FR");
+        assert!(matches!(validate_tax_id("US123456789"), Err(TaxIdError::UnknownCountry(_))));
+        assert!(validate_spanish_tax_id("FR12345678901").is_err());
+    }
+
+    #[test]
+    fn invoice_parties() {
+        assert!(validate_invoice_parties("F2", "B12345674", None).is_ok());
+        assert!(validate_invoice_parties("F1", "B12345674", Some(" ")).is_err());
+        assert!(validate_invoice_parties("F1", "B12345674", Some("12345678Z")).is_ok());
+        assert!(validate_invoice_parties("F2", "12345678A", None).is_err());
+    }
+}
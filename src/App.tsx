@@ -47,6 +47,7 @@ import {
   PrinterTypes,
   type ThermalPrinterServiceOptions,
 } from '@/models/ThermalPrinter';
+import { invoiceBuilderService } from '@/services/invoice-builder.service';
 import { getPlatformService } from '@/services/platform';
 import useStore from '@/store/store';
 import type { LicenseStatus } from '@/types/license';
@@ -227,6 +228,18 @@ function App() {
       console.log('[App] Order history loaded:', paidOrders.length);
     }
 
+    // Move the fiscal data of older versions from the AEAT config into the database
+    await invoiceBuilderService.migrateLegacyBusinessData().catch((error) => {
+      console.warn('[App] Business data migration failed:', error);
+    });
+
+    // Initialize customers from database
+    const customersResult = await store.storageAdapter().getCustomers?.();
+    if (customersResult?.ok) {
+      store.setCustomers(customersResult.value);
+      console.log('[App] Customers loaded:', customersResult.value.length);
+    }
+
     // Initialize users from database
     if (store.state.users.length === 0) {
       const usersResult = await store.storageAdapter().getUsers();
@@ -23,7 +23,7 @@ import {
   WifiOff,
   X,
 } from 'lucide-solid';
-import { createSignal, Show } from 'solid-js';
+import { createSignal, onMount, Show } from 'solid-js';
 import { Button } from '@/components/ui/button';
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '@/components/ui/card';
 import { Input } from '@/components/ui/input';
@@ -38,7 +38,9 @@ import {
 import { Switch } from '@/components/ui/switch';
 import { toast } from '@/components/ui/use-toast';
 import { useAEAT } from '@/hooks/useAEAT';
-import type { AEATEnvironment, AEATMode } from '@/models/AEAT';
+import type { AEATBusinessData, AEATEnvironment, AEATMode, BusinessProfile } from '@/models/AEAT';
+import { invoiceBuilderService } from '@/services/invoice-builder.service';
+import { isTauri } from '@/services/platform';
 
 // ==================== Types ====================
 
@@ -85,8 +87,52 @@ export default function AEATSettings(props: AEATSettingsProps) {
     config().externalUrl || 'http://localhost:3001'
   );
 
+  // Datos fiscales: en escritorio se guardan en el perfil de la base de datos
+  const [businessData, setBusinessData] = createSignal<AEATBusinessData>(config().businessData);
+  const [businessProfile, setBusinessProfile] = createSignal<BusinessProfile | null>(null);
+  const [isSavingBusinessData, setIsSavingBusinessData] = createSignal(false);
+
+  onMount(async () => {
+    try {
+      const profile = await invoiceBuilderService.getBusinessProfile();
+      if (profile) {
+        setBusinessProfile(profile);
+        setBusinessData(invoiceBuilderService.businessDataFromProfile(profile));
+      }
+    } catch (error) {
+      console.error('[AEATSettings] Error loading business profile:', error);
+    }
+  });
+
   // ==================== Handlers ====================
 
+  const updateBusinessData = (updates: Partial<AEATBusinessData>) => {
+    setBusinessData((prev) => ({ ...prev, ...updates }));
+  };
+
+  const handleSaveBusinessData = async () => {
+    if (!isTauri()) {
+      updateConfig({ businessData: businessData() });
+      toast({ title: 'Datos fiscales guardados', duration: 3000 });
+      return;
+    }
+
+    setIsSavingBusinessData(true);
+    try {
+      await invoiceBuilderService.saveBusinessData(businessData(), businessProfile());
+      setBusinessProfile(await invoiceBuilderService.getBusinessProfile());
+      toast({ title: 'Datos fiscales guardados', duration: 3000 });
+    } catch (error) {
+      toast({
+        title: 'No se pudieron guardar los datos fiscales',
+        description: String(error),
+        duration: 5000,
+      });
+    } finally {
+      setIsSavingBusinessData(false);
+    }
+  };
+
   const handleModeChange = (mode: AEATMode) => {
     updateConfig({ mode });
 
@@ -474,7 +520,7 @@ export default function AEATSettings(props: AEATSettingsProps) {
           </CardHeader>
           <CardContent class="space-y-4">
             {/* Aviso si faltan datos obligatorios */}
-            <Show when={!config().businessData.nif || !config().businessData.nombreRazon}>
+            <Show when={!businessData().nif || !businessData().nombreRazon}>
               <div class="flex items-start gap-2 p-3 bg-yellow-500/10 border border-yellow-500/20 rounded-lg">
                 <AlertTriangle class="h-4 w-4 text-yellow-500 mt-0.5" />
                 <div class="text-sm">
@@ -491,14 +537,9 @@ export default function AEATSettings(props: AEATSettingsProps) {
                 <Label for="nif">NIF/CIF *</Label>
                 <Input
                   id="nif"
-                  value={config().businessData.nif}
+                  value={businessData().nif}
                   onInput={(e) =>
-                    updateConfig({
-                      businessData: {
-                        ...config().businessData,
-                        nif: e.currentTarget.value.toUpperCase(),
-                      },
-                    })
+                    updateBusinessData({ nif: e.currentTarget.value.toUpperCase() })
                   }
                   placeholder="B12345678"
                   maxLength={9}
@@ -510,14 +551,9 @@ export default function AEATSettings(props: AEATSettingsProps) {
                 <Label for="nombreRazon">Razon Social *</Label>
                 <Input
                   id="nombreRazon"
-                  value={config().businessData.nombreRazon}
+                  value={businessData().nombreRazon}
                   onInput={(e) =>
-                    updateConfig({
-                      businessData: {
-                        ...config().businessData,
-                        nombreRazon: e.currentTarget.value,
-                      },
-                    })
+                    updateBusinessData({ nombreRazon: e.currentTarget.value })
                   }
                   placeholder="Mi Empresa S.L."
                 />
@@ -530,14 +566,9 @@ export default function AEATSettings(props: AEATSettingsProps) {
                 <Label for="serieFactura">Serie de Factura</Label>
                 <Input
                   id="serieFactura"
-                  value={config().businessData.serieFactura}
+                  value={businessData().serieFactura}
                   onInput={(e) =>
-                    updateConfig({
-                      businessData: {
-                        ...config().businessData,
-                        serieFactura: e.currentTarget.value,
-                      },
-                    })
+                    updateBusinessData({ serieFactura: e.currentTarget.value })
                   }
                   placeholder="TPV-"
                 />
@@ -549,12 +580,10 @@ export default function AEATSettings(props: AEATSettingsProps) {
               <div class="space-y-2">
                 <Label for="tipoFactura">Tipo de Factura</Label>
                 <Select
-                  value={config().businessData.tipoFactura}
+                  value={businessData().tipoFactura}
                   onChange={(v: string | null) =>
                     v &&
-                    updateConfig({
-                      businessData: { ...config().businessData, tipoFactura: v as 'F1' | 'F2' },
-                    })
+                    updateBusinessData({ tipoFactura: v as 'F1' | 'F2' })
                   }
                 >
                   <SelectTrigger id="tipoFactura">
@@ -575,19 +604,23 @@ export default function AEATSettings(props: AEATSettingsProps) {
               <Label for="descripcionOperacion">Descripcion de Operaciones</Label>
               <Input
                 id="descripcionOperacion"
-                value={config().businessData.descripcionOperacion}
+                value={businessData().descripcionOperacion}
                 onInput={(e) =>
-                  updateConfig({
-                    businessData: {
-                      ...config().businessData,
-                      descripcionOperacion: e.currentTarget.value,
-                    },
-                  })
+                  updateBusinessData({ descripcionOperacion: e.currentTarget.value })
                 }
                 placeholder="Venta TPV"
               />
               <p class="text-xs text-muted-foreground">Descripcion por defecto en las facturas</p>
             </div>
+
+            <div class="flex justify-end">
+              <Button onClick={handleSaveBusinessData} disabled={isSavingBusinessData()}>
+                <Show when={isSavingBusinessData()} fallback={<Check class="h-4 w-4 mr-2" />}>
+                  <Loader2 class="h-4 w-4 mr-2 animate-spin" />
+                </Show>
+                Guardar datos fiscales
+              </Button>
+            </div>
           </CardContent>
         </Card>
       </Show>
@@ -610,13 +643,13 @@ export default function AEATSettings(props: AEATSettingsProps) {
                 id="autoSendInvoices"
                 checked={config().autoSendInvoices}
                 onChange={(checked: boolean) => updateConfig({ autoSendInvoices: checked })}
-                disabled={!config().businessData.nif || !config().businessData.nombreRazon}
+                disabled={!businessData().nif || !businessData().nombreRazon}
               />
             </div>
             <Show
               when={
                 config().autoSendInvoices &&
-                (!config().businessData.nif || !config().businessData.nombreRazon)
+                (!businessData().nif || !businessData().nombreRazon)
               }
             >
               <p class="text-xs text-yellow-600">
@@ -655,11 +688,15 @@ export default function AEATSettings(props: AEATSettingsProps) {
           <CardContent class="space-y-4">
             <div class="p-3 bg-muted/50 rounded-lg space-y-2">
               <p class="text-sm">
-                Los certificados digitales se configuran en el servidor AEAT Bridge.
+                {config().mode === 'external'
+                  ? 'Los certificados digitales se configuran en el servidor AEAT Bridge.'
+                  : 'El sidecar usa el certificado importado en la aplicación.'}
               </p>
               <p class="text-xs text-muted-foreground">
-                Para {config().mode === 'external' ? 'el servidor externo' : 'el sidecar'},
-                configure las variables de entorno{' '}
+                {config().mode === 'external'
+                  ? 'Para el servidor externo'
+                  : 'Si no hay ninguno importado'}
+                , configure las variables de entorno{' '}
                 <code class="bg-muted px-1 rounded">PFX_PATH</code> y{' '}
                 <code class="bg-muted px-1 rounded">PFX_PASSWORD</code> en el archivo{' '}
                 <code class="bg-muted px-1 rounded">.env</code>
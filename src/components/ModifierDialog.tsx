@@ -0,0 +1,121 @@
+import { createEffect, createSignal, For, Show } from 'solid-js';
+import { Button } from '@/components/ui/button';
+import {
+  Dialog,
+  DialogContent,
+  DialogDescription,
+  DialogFooter,
+  DialogHeader,
+  DialogTitle,
+} from '@/components/ui/dialog';
+import type ModifierGroup from '@/models/Modifier';
+import type Product from '@/models/Product';
+import { selectionLimits } from '@/services/modifier.service';
+
+interface ModifierDialogProps {
+  product: Product | null;
+  groups: ModifierGroup[];
+  onClose: () => void;
+  onConfirm: (optionIds: number[]) => void;
+}
+
+function formatDelta(value: number): string {
+  const amount = new Intl.NumberFormat('es-ES', {
+    style: 'currency',
+    currency: 'EUR',
+    minimumFractionDigits: 2,
+  }).format(Math.abs(value));
+  return value < 0 ? `-${amount}` : `+${amount}`;
+}
+
+function ModifierDialog(props: ModifierDialogProps) {
+  const [selected, setSelected] = createSignal<number[]>([]);
+
+  // Each product starts with nothing chosen
+  createEffect(() => {
+    if (props.product) {
+      setSelected([]);
+    }
+  });
+
+  const chosenIn = (group: ModifierGroup) =>
+    group.options.filter((option) => selected().includes(option.id)).length;
+
+  const toggle = (group: ModifierGroup, optionId: number) => {
+    const { max } = selectionLimits(group);
+    const groupIds = group.options.map((option) => option.id);
+    setSelected((current) => {
+      if (current.includes(optionId)) {
+        return current.filter((id) => id !== optionId);
+      }
+      // A group that takes a single option swaps it
+      if (max === 1) {
+        return [...current.filter((id) => !groupIds.includes(id)), optionId];
+      }
+      if (max !== undefined && chosenIn(group) >= max) {
+        return current;
+      }
+      return [...current, optionId];
+    });
+  };
+
+  const missing = () =>
+    props.groups.find((group) => chosenIn(group) < selectionLimits(group).min) ?? null;
+
+  return (
+    <Dialog open={props.product !== null} onOpenChange={(open) => !open && props.onClose()}>
+      <DialogContent class="sm:max-w-lg">
+        <DialogHeader>
+          <DialogTitle class="text-xl">{props.product?.name}</DialogTitle>
+          <DialogDescription>Elige las opciones de la línea</DialogDescription>
+        </DialogHeader>
+
+        <div class="space-y-4 py-2 max-h-[60vh] overflow-y-auto">
+          <For each={props.groups}>
+            {(group) => (
+              <div class="space-y-2">
+                <p class="text-sm font-medium">
+                  {group.name}
+                  <Show when={selectionLimits(group).min > 0}>
+                    <span class="text-muted-foreground"> (obligatorio)</span>
+                  </Show>
+                </p>
+                <div class="grid grid-cols-2 gap-2">
+                  <For each={group.options}>
+                    {(option) => (
+                      <Button
+                        variant={selected().includes(option.id) ? 'default' : 'outline'}
+                        class="h-14 justify-between touch-manipulation"
+                        onClick={() => toggle(group, option.id)}
+                      >
+                        <span class="truncate">{option.name}</span>
+                        <Show when={option.priceDelta !== 0}>
+                          <span class="text-xs ml-2">{formatDelta(option.priceDelta)}</span>
+                        </Show>
+                      </Button>
+                    )}
+                  </For>
+                </div>
+              </div>
+            )}
+          </For>
+        </div>
+
+        <DialogFooter class="gap-2">
+          <Button variant="outline" class="h-12 flex-1" onClick={() => props.onClose()}>
+            Cancelar
+          </Button>
+          <Button
+            class="h-12 flex-1"
+            disabled={missing() !== null}
+            onClick={() => props.onConfirm(selected())}
+          >
+            {missing() ? `Elige ${missing()?.name}` : 'Añadir'}
+          </Button>
+        </DialogFooter>
+      </DialogContent>
+    </Dialog>
+  );
+}
+
+export default ModifierDialog;
@@ -19,7 +19,7 @@ interface OrderPanelProps {
   onNewOrder: () => void;
   onTableChange: (tableId: number) => void;
   onPaymentStart: () => void;
-  onRemoveFromOrder: (orderId: number, productId: number) => void;
+  onRemoveFromOrder: (orderId: number, item: OrderItem) => void;
   onAddToOrder: (orderId: number, product: OrderItem | Product) => void;
   disableAnimations?: boolean;
 }
@@ -16,7 +16,7 @@ import type { OrderItem } from '@/models/Order';
 
 type OrderTableProps = {
   order: Order;
-  handleRemoveFromOrder: (orderId: number, productId: number) => void;
+  handleRemoveFromOrder: (orderId: number, item: OrderItem) => void;
   handleAddToOrder: (orderId: number, product: OrderItem) => void;
   disableAnimations?: boolean;
 };
@@ -53,6 +53,13 @@ function OrderTable(props: OrderTableProps) {
     }).format(value);
   };
 
+  // Extras chosen on the line; the variant is already part of the name
+  const modifierNames = (item: OrderItem) =>
+    (item.modifiers ?? [])
+      .filter((modifier) => !modifier.variant)
+      .map((modifier) => modifier.name)
+      .join(', ');
+
   // Desktop table view button sizes
   const buttonSize = createMemo(() => (isTouch() ? 'h-11 w-11' : 'h-8 w-8'));
   const iconSize = createMemo(() => (isTouch() ? 'h-5 w-5' : 'h-4 w-4'));
@@ -72,6 +79,12 @@ function OrderTable(props: OrderTableProps) {
                 <div class="flex items-center justify-between">
                   <div class="flex-1 min-w-0">
                     <p class="font-medium text-sm text-card-foreground truncate">{item.name}</p>
+                    <Show when={modifierNames(item)}>
+                      <p class="text-xs text-muted-foreground truncate">{modifierNames(item)}</p>
+                    </Show>
+                    <Show when={item.notes}>
+                      <p class="text-xs italic text-muted-foreground truncate">{item.notes}</p>
+                    </Show>
                     <p class="text-xs text-muted-foreground mt-1">
                       {formatCurrency(item.price * item.quantity)}
                     </p>
@@ -86,7 +99,7 @@ function OrderTable(props: OrderTableProps) {
                         variant="outline"
                         size="sm"
                         class="h-11 w-11 p-0 text-destructive hover:text-destructive/80 hover:bg-destructive/10"
-                        onClick={() => props.handleRemoveFromOrder(props.order.id, item.id)}
+                        onClick={() => props.handleRemoveFromOrder(props.order.id, item)}
                       >
                         <MinusIcon class="h-5 w-5" />
                       </Button>
@@ -138,6 +151,16 @@ function OrderTable(props: OrderTableProps) {
                         <span class="font-medium text-sm leading-tight block truncate">
                           {item.name}
                         </span>
+                        <Show when={modifierNames(item)}>
+                          <span class="text-xs text-muted-foreground block truncate">
+                            {modifierNames(item)}
+                          </span>
+                        </Show>
+                        <Show when={item.notes}>
+                          <span class="text-xs italic text-muted-foreground block truncate">
+                            {item.notes}
+                          </span>
+                        </Show>
                       </TableCell>
                       <TableCell class="text-foreground text-center text-base w-[15%] px-1">
                         <div class="flex items-center justify-center h-8 w-full">
@@ -163,7 +186,7 @@ function OrderTable(props: OrderTableProps) {
                             variant="ghost"
                             size="sm"
                             class="h-7 w-7 p-0 rounded-full bg-destructive text-destructive-foreground hover:bg-destructive/90 touch-manipulation"
-                            onClick={() => props.handleRemoveFromOrder(props.order.id, item.id)}
+                            onClick={() => props.handleRemoveFromOrder(props.order.id, item)}
                           >
                             <MinusIcon class="h-4 w-4" />
                           </Button>
@@ -213,6 +236,16 @@ function OrderTable(props: OrderTableProps) {
                       >
                         {item.name}
                       </Motion.span>
+                      <Show when={modifierNames(item)}>
+                        <span class="text-xs text-muted-foreground block truncate">
+                          {modifierNames(item)}
+                        </span>
+                      </Show>
+                      <Show when={item.notes}>
+                        <span class="text-xs italic text-muted-foreground block truncate">
+                          {item.notes}
+                        </span>
+                      </Show>
                     </TableCell>
                     <TableCell class="text-foreground text-center text-base w-[15%] px-1">
                       <Motion.div
@@ -262,7 +295,7 @@ function OrderTable(props: OrderTableProps) {
                           variant="outline"
                           size="sm"
                           class={`${buttonSize()} p-0 text-destructive hover:text-destructive/80 hover:bg-destructive/10 border-destructive/20`}
-                          onClick={() => props.handleRemoveFromOrder(props.order.id, item.id)}
+                          onClick={() => props.handleRemoveFromOrder(props.order.id, item)}
                         >
                           <MinusIcon class={iconSize()} />
                         </Button>
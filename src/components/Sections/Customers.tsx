@@ -18,6 +18,7 @@ import {
   DialogTitle,
 } from '@/components/ui/dialog';
 import { Input } from '@/components/ui/input';
+import { toast } from '@/components/ui/use-toast';
 import { cn } from '@/lib/utils';
 import type Customer from '@/models/Customer';
 import useStore from '@/store/store';
@@ -90,6 +91,7 @@ function Customers() {
   const handleSave = async () => {
     const data = formData();
     const editing = editingCustomer();
+    let result: Awaited<ReturnType<typeof store.addCustomer>>;
 
     if (editing) {
       // Update existing customer
@@ -98,7 +100,7 @@ function Customers() {
         ...data,
         updatedAt: new Date().toISOString(),
       } as Customer;
-      await store.updateCustomer(updatedCustomer);
+      result = await store.updateCustomer(updatedCustomer);
     } else {
       // Create new customer
       const newCustomer: Customer = {
@@ -114,7 +116,17 @@ function Customers() {
         activo: data.activo ?? true,
         createdAt: new Date().toISOString(),
       };
-      await store.addCustomer(newCustomer);
+      result = await store.addCustomer(newCustomer);
+    }
+
+    // El backend valida el NIF/CIF: si lo rechaza, el formulario sigue abierto
+    if (result && !result.ok) {
+      toast({
+        title: 'No se pudo guardar el cliente',
+        description: result.error.message,
+        duration: 4000,
+      });
+      return;
     }
 
     setIsDialogOpen(false);
@@ -124,7 +136,14 @@ function Customers() {
   const handleDelete = async () => {
     const customer = customerToDelete();
     if (customer) {
-      await store.deleteCustomer(customer.id);
+      const result = await store.deleteCustomer(customer.id);
+      if (result && !result.ok) {
+        toast({
+          title: 'No se pudo eliminar el cliente',
+          description: result.error.message,
+          duration: 4000,
+        });
+      }
     }
     setIsDeleteDialogOpen(false);
     setCustomerToDelete(null);
@@ -3,6 +3,7 @@ import { createEffect, createMemo, createSignal, For, Show } from 'solid-js';
 import { connectToThermalPrinter } from '@/assets/utils/utils';
 import CategorySidebar from '@/components/CategorySidebar';
 import ConfirmPaymentDialog from '@/components/ConfirmPaymentDialog';
+import ModifierDialog from '@/components/ModifierDialog';
 import OrderPanel from '@/components/OrderPanel';
 import PaymentModal from '@/components/PaymentModal';
 import ProductGrid from '@/components/Product';
@@ -21,10 +22,12 @@ import { toast } from '@/components/ui/use-toast';
 import { usePerformanceConfig } from '@/hooks/usePerformanceConfig';
 import { useResponsive } from '@/hooks/useResponsive';
 import { cn } from '@/lib/utils';
+import type ModifierGroup from '@/models/Modifier';
 import type Order from '@/models/Order';
 import type { OrderItem } from '@/models/Order';
 import type Product from '@/models/Product';
 import type { ThermalPrinterServiceOptions } from '@/models/ThermalPrinter';
+import { buildOrderItem, getProductModifierGroups } from '@/services/modifier.service';
 import useStore from '@/store/store';
 import '@/styles/neworder.css';
 
@@ -40,6 +43,11 @@ function NewOrder() {
   const [orderToClose, setOrderToClose] = createSignal<Order | null>(null);
   const [selectedCategory, setSelectedCategory] = createSignal<string | null>('Fijados');
   const [isOrderSheetOpen, setIsOrderSheetOpen] = createSignal(false);
+  const [modifierTarget, setModifierTarget] = createSignal<{
+    orderId: number;
+    product: Product;
+  } | null>(null);
+  const [modifierGroups, setModifierGroups] = createSignal<ModifierGroup[]>([]);
 
   // Responsive getters
   const isMobile = () => responsive.isMobile();
@@ -80,12 +88,47 @@ function NewOrder() {
     }
   });
 
-  const handleAddToOrder = (orderId: number, product: OrderItem | Product) => {
-    store.addToOrder(orderId, product);
+  // Products with variant or modifier groups are added through the options
+  // dialog; the backend builds the line with its name and price
+  const handleAddToOrder = async (orderId: number, product: OrderItem | Product) => {
+    if ('quantity' in product) {
+      store.addToOrder(orderId, product);
+      return;
+    }
+    try {
+      const groups = await getProductModifierGroups(product.id);
+      if (groups.length === 0) {
+        store.addToOrder(orderId, product);
+        return;
+      }
+      setModifierGroups(groups);
+      setModifierTarget({ orderId, product });
+    } catch (error) {
+      console.error('Error al cargar las opciones del producto:', error);
+      store.addToOrder(orderId, product);
+    }
   };
 
-  const handleRemoveFromOrder = (orderId: number, productId: number) => {
-    store.removeFromOrder(orderId, productId);
+  const handleConfirmModifiers = async (optionIds: number[]) => {
+    const target = modifierTarget();
+    if (!target) {
+      return;
+    }
+    try {
+      const item = await buildOrderItem(target.product.id, optionIds);
+      store.addToOrder(target.orderId, item);
+      setModifierTarget(null);
+    } catch (error) {
+      toast({
+        title: 'No se pudo añadir',
+        description: String(error),
+        duration: 3000,
+      });
+    }
+  };
+
+  const handleRemoveFromOrder = (orderId: number, item: OrderItem) => {
+    store.removeFromOrder(orderId, item);
   };
 
   const handleTicketPrintingComplete = async (shouldPrintTicket: boolean) => {
@@ -452,6 +495,13 @@ function NewOrder() {
       </Show>
 
       {/* Modals */}
+      <ModifierDialog
+        product={modifierTarget()?.product ?? null}
+        groups={modifierGroups()}
+        onClose={() => setModifierTarget(null)}
+        onConfirm={handleConfirmModifiers}
+      />
+
       <ConfirmPaymentDialog
         isOpen={isConfirmPaymentDialogOpen()}
         onClose={() => setIsConfirmPaymentDialogOpen(false)}
@@ -93,12 +93,15 @@ const OrderHistory: Component<OrderHistoryProps> = (props) => {
   // AEAT hooks
   const { isEnabled: isAEATEnabled, isConnected: isAEATConnected } = useAEAT();
   const { emitInvoice, isEmitting } = useEmitInvoice();
+  // Cliente destinatario de la factura (obligatorio para facturas completas F1)
+  const [invoiceCustomerId, setInvoiceCustomerId] = createSignal<number | undefined>();
+  const activeCustomers = () => store.state.customers.filter((c) => c.activo);
 
   // Handler for emitting invoice
   const handleEmitInvoice = async () => {
     if (!props.selectedOrder) return;
 
-    const result = await emitInvoice(props.selectedOrder);
+    const result = await emitInvoice(props.selectedOrder, invoiceCustomerId());
     if (result.success) {
       // Update the selected order with AEAT info
       props.setSelectedOrder(result.order);
@@ -469,7 +472,9 @@ const OrderHistory: Component<OrderHistoryProps> = (props) => {
                 <For each={sortedAndFilteredOrders()}>
                   {(order) => (
                     <TableRow class="hover:bg-muted/50 touch-manipulation">
-                      <TableCell class="border border-border">{order.date}</TableCell>
+                      <TableCell class="border border-border">
+                        {new Date(order.date).toLocaleString()}
+                      </TableCell>
                       <TableCell class="border border-border">{order.total.toFixed(2)}€</TableCell>
                       <TableCell class="border border-border">{order.itemCount}</TableCell>
                       <TableCell class="border border-border">
@@ -581,7 +586,7 @@ const OrderHistory: Component<OrderHistoryProps> = (props) => {
                         <div>
                           <Label class="text-sm">Fecha</Label>
                           <Input
-                            value={selectedOrder().date}
+                            value={new Date(selectedOrder().date).toLocaleString()}
                             readOnly
                             class={cn('bg-muted', responsive.isMobile() ? 'h-10 text-sm' : '')}
                           />
@@ -733,6 +738,27 @@ const OrderHistory: Component<OrderHistoryProps> = (props) => {
             </Button>
             {/* Emit Invoice Button - only for paid orders with AEAT enabled */}
             <Show when={props.selectedOrder?.status === 'paid' && isAEATEnabled()}>
+              <Show when={props.selectedOrder?.aeat?.invoiceStatus !== 'accepted'}>
+                <select
+                  aria-label="Cliente de la factura"
+                  class="w-full h-10 rounded-md border border-border bg-background px-3 text-sm"
+                  value={invoiceCustomerId() ?? ''}
+                  onChange={(e) =>
+                    setInvoiceCustomerId(
+                      e.currentTarget.value ? Number(e.currentTarget.value) : undefined
+                    )
+                  }
+                >
+                  <option value="">Sin cliente (factura simplificada)</option>
+                  <For each={activeCustomers()}>
+                    {(customer) => (
+                      <option value={customer.id}>
+                        {customer.nombreFiscal} · {customer.cifNif}
+                      </option>
+                    )}
+                  </For>
+                </select>
+              </Show>
               <TooltipProvider>
                 <Tooltip>
                   <TooltipTrigger as="div" class={cn(responsive.isMobile() ? 'w-full' : '')}>
@@ -867,7 +867,7 @@ const SettingsPanel: Component<SettingsPanelProps> = (props) => {
                     onClick={() => {
                       const openCashDrawer = async () => {
                         try {
-                          await openCashDrawerOnly();
+                          await openCashDrawerOnly(state.selectedUser?.id);
                           toast({
                             title: 'Caja abierta',
                             description: 'El cajón de efectivo se ha abierto correctamente.',
@@ -9,7 +9,8 @@
  */
 
 import { err, isErr, ok, type Result, tryCatchAsync } from '@mks2508/no-throw';
-import { type Child, Command } from '@tauri-apps/plugin-shell';
+import { invoke } from '@tauri-apps/api/core';
+import { listen, type UnlistenFn } from '@tauri-apps/api/event';
 import { createEffect, createSignal, onCleanup, onMount } from 'solid-js';
 import { config } from '@/lib/config';
 import { AEATErrorCode, type AEATResultError } from '@/lib/error-codes';
@@ -20,6 +21,11 @@ import { isTauri } from '@/services/platform';
 
 type SidecarResult<T> = Result<T, AEATResultError>;
 
+interface SidecarOutput {
+  stderr: boolean;
+  line: string;
+}
+
 interface UseAEATSidecarOptions {
   port?: number;
   autoStart?: boolean;
@@ -89,7 +95,9 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
   });
 
   // Refs (using signals for mutable state)
-  let childProcessRef: Child | null = null;
+  // El proceso lo arranca el backend; aquí solo se guarda su pid
+  let sidecarPid: number | null = null;
+  let unlistenSidecar: UnlistenFn[] = [];
   let healthCheckIntervalRef: ReturnType<typeof setInterval> | null = null;
   let restartAttemptsCount = 0;
   let isStarting = false;
@@ -123,19 +131,16 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
     setState((prev) => ({ ...prev, status: 'starting', error: undefined }));
 
     const startSidecar = async () => {
-      // Crear comando del sidecar
-      const command = Command.sidecar('sidecars/aeat-bridge', ['--port', port.toString()]);
-
       // Configurar listeners
-      const handleClose = (data: { code: number | null }) => {
-        console.log(`[AEAT Sidecar] Process closed with code ${data.code}`);
-        childProcessRef = null;
+      const handleClose = (code: number | null) => {
+        console.log(`[AEAT Sidecar] Process closed with code ${code}`);
+        sidecarPid = null;
 
-        if (data.code !== null && data.code !== 0 && state().status === 'running') {
+        if (code !== null && code !== 0 && state().status === 'running') {
           setState((prev) => ({
             ...prev,
             status: 'error',
-            error: `Process exited with code ${data.code}`,
+            error: `Process exited with code ${code}`,
           }));
 
           // Auto-restart si no hemos excedido el límite
@@ -150,35 +155,32 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
           setState((prev) => ({ ...prev, status: 'stopped' }));
         }
       };
-      command.on('close', handleClose);
-
-      command.on('error', (error) => {
-        console.error('[AEAT Sidecar] Process error:', error);
-        setState((prev) => ({
-          ...prev,
-          status: 'error',
-          error: error,
-        }));
-      });
-
-      command.stdout.on('data', (line) => {
-        console.log(`[AEAT Sidecar stdout] ${line}`);
-      });
 
-      command.stderr.on('data', (line) => {
-        console.error(`[AEAT Sidecar stderr] ${line}`);
-      });
+      if (unlistenSidecar.length === 0) {
+        unlistenSidecar = await Promise.all([
+          listen<number | null>('aeat-sidecar-terminated', (event) => handleClose(event.payload)),
+          listen<SidecarOutput>('aeat-sidecar-output', ({ payload }) => {
+            if (payload.stderr) {
+              console.error(`[AEAT Sidecar stderr] ${payload.line}`);
+            } else {
+              console.log(`[AEAT Sidecar stdout] ${payload.line}`);
+            }
+          }),
+        ]);
+      }
 
-      // Spawn el proceso
-      const child = await command.spawn();
-      childProcessRef = child;
+      // El backend arranca el sidecar con el certificado importado (si no hay,
+      // el sidecar usa su .env). La contraseña del certificado no pasa por aquí.
+      const pid = await invoke<number>('start_aeat_sidecar', { port });
+      sidecarPid = pid;
 
       // Esperar a que el servicio esté listo
       const isReady = await waitForServiceReady(port);
 
       if (!isReady) {
         // Matar el proceso si no está listo
-        await child.kill();
+        await invoke('stop_aeat_sidecar');
+        sidecarPid = null;
         throw new Error('Service failed to start within timeout');
       }
 
@@ -186,7 +188,7 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
       restartAttemptsCount = 0;
       setState({
         status: 'running',
-        pid: child.pid,
+        pid,
         port,
         startedAt: new Date(),
       });
@@ -218,7 +220,7 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
       });
     }
 
-    if (state().status === 'stopped' || !childProcessRef) {
+    if (state().status === 'stopped' || sidecarPid === null) {
       setState((prev) => ({ ...prev, status: 'stopped' }));
       return ok(undefined);
     }
@@ -226,10 +228,8 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
     setState((prev) => ({ ...prev, status: 'stopping' }));
 
     const result = await tryCatchAsync(async () => {
-      if (childProcessRef) {
-        await childProcessRef.kill();
-        childProcessRef = null;
-      }
+      await invoke('stop_aeat_sidecar');
+      sidecarPid = null;
       setState({ status: 'stopped', port });
     }, AEATErrorCode.SidecarStopFailed);
 
@@ -293,8 +293,11 @@ export function useAEATSidecar(options: UseAEATSidecarOptions = {}): UseAEATSide
    * Cleanup al desmontar
    */
   onCleanup(() => {
-    if (childProcessRef) {
-      childProcessRef.kill().catch(console.error);
+    if (sidecarPid !== null) {
+      invoke('stop_aeat_sidecar').catch(console.error);
+    }
+    for (const unlisten of unlistenSidecar) {
+      unlisten();
     }
     if (healthCheckIntervalRef) {
       clearInterval(healthCheckIntervalRef);
@@ -4,6 +4,7 @@
  * Hook para emitir facturas a AEAT VERI*FACTU
  */
 
+import { invoke } from '@tauri-apps/api/core';
 import { createSignal } from 'solid-js';
 import { toast } from '@/components/ui/use-toast';
 import { useAEAT } from '@/hooks/useAEAT';
@@ -11,6 +12,7 @@ import type { RegistrarFacturaResponse } from '@/models/AEAT';
 import type Order from '@/models/Order';
 import type { OrderAEATInfo, TaxBreakdownItem } from '@/models/Order';
 import { invoiceBuilderService } from '@/services/invoice-builder.service';
+import { isTauri } from '@/services/platform';
 import useStore from '@/store/store';
 
 // ==================== Types ====================
@@ -24,8 +26,8 @@ export interface EmitInvoiceResult {
 }
 
 export interface UseEmitInvoiceReturn {
-  /** Emite una factura para un pedido */
-  emitInvoice: (order: Order) => Promise<EmitInvoiceResult>;
+  /** Emite una factura para un pedido, opcionalmente a nombre de un cliente */
+  emitInvoice: (order: Order, customerId?: number) => Promise<EmitInvoiceResult>;
   /** Indica si se está emitiendo una factura */
   isEmitting: boolean;
   /** Último error ocurrido */
@@ -105,7 +107,7 @@ export function useEmitInvoice(): UseEmitInvoiceReturn {
   /**
    * Emite una factura para un pedido
    */
-  const emitInvoice = async (order: Order): Promise<EmitInvoiceResult> => {
+  const emitInvoice = async (order: Order, customerId?: number): Promise<EmitInvoiceResult> => {
     setIsEmitting(true);
     setLastError(null);
 
@@ -135,7 +137,18 @@ export function useEmitInvoice(): UseEmitInvoiceReturn {
       }
 
       // 3. Validar datos del negocio
-      const businessValidation = invoiceBuilderService.validateBusinessData(config().businessData);
+      const businessData = await invoiceBuilderService.loadBusinessData(config().businessData);
+      if (!businessData) {
+        const error = 'Configure los datos fiscales del negocio en Ajustes antes de facturar.';
+        setLastError(error);
+        toast({
+          title: 'Datos fiscales incompletos',
+          description: error,
+          variant: 'destructive',
+        });
+        return { success: false, order, error };
+      }
+      const businessValidation = invoiceBuilderService.validateBusinessData(businessData);
       if (!businessValidation.isValid) {
         const error = `Datos fiscales incompletos: ${businessValidation.errors.join(', ')}`;
         setLastError(error);
@@ -147,6 +160,26 @@ export function useEmitInvoice(): UseEmitInvoiceReturn {
         return { success: false, order, error };
       }
 
+      // 3b. Validar NIF del emisor y del destinatario (F1/F3 exigen destinatario)
+      if (isTauri()) {
+        try {
+          await invoke('validate_invoice_identities', {
+            invoiceType: businessData.tipoFactura,
+            customerId: customerId ?? null,
+            recipientNif: null,
+          });
+        } catch (identityError) {
+          const error = String(identityError);
+          setLastError(error);
+          toast({
+            title: 'Identificación fiscal no válida',
+            description: error,
+            variant: 'destructive',
+          });
+          return { success: false, order, error };
+        }
+      }
+
       // 4. Validar pedido
       const orderValidation = invoiceBuilderService.validateOrder(order);
       if (!orderValidation.isValid) {
@@ -168,10 +201,14 @@ export function useEmitInvoice(): UseEmitInvoiceReturn {
       };
       await updateOrderWithAEATInfo(order, pendingAEATInfo);
 
-      // 6. Construir petición
+      // 6. Reservar número y construir petición
+      const reservedNumber = await invoiceBuilderService.reserveInvoiceNumber(
+        businessData.serieFactura
+      );
       const { request, invoiceNumber, taxBreakdown } = invoiceBuilderService.buildInvoiceRequest(
         order,
-        config().businessData,
+        businessData,
+        reservedNumber,
         state.taxRate
       );
 
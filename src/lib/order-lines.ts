@@ -0,0 +1,53 @@
+import type { OrderItem } from '@/models/Order';
+import type Product from '@/models/Product';
+
+function optionKey(item: Pick<OrderItem, 'modifiers'>): string {
+  return (item.modifiers ?? [])
+    .map((modifier) => modifier.optionId ?? modifier.name)
+    .join(',');
+}
+
+/**
+ * Misma línea de pedido: el mismo producto con las mismas opciones. Los menús
+ * llevan cada uno su propia línea.
+ */
+export function isSameLine(line: OrderItem, item: OrderItem | Product): boolean {
+  if ('lineId' in item && item.lineId && line.lineId) {
+    return line.lineId === item.lineId;
+  }
+  const modifiers = 'modifiers' in item ? item.modifiers : undefined;
+  const components = 'components' in item ? item.components : undefined;
+  return (
+    line.id === item.id &&
+    optionKey(line) === optionKey({ modifiers }) &&
+    !line.components?.length &&
+    !components?.length
+  );
+}
+
+/**
+ * Identificador de una línea nueva. Se envía al backend en cada guardado para
+ * que las comandas y las notas sigan a la misma línea.
+ */
+export function newLineId(): string {
+  return crypto.randomUUID();
+}
+
+/**
+ * Redondeo a céntimos igual que el backend: pasa antes por micro-céntimos para
+ * que 0,25 kg × 18,90 € dé 4,73 y no 4,72
+ */
+export function roundMoney(amount: number): number {
+  return Math.round(Math.round(amount * 1_000_000) / 10_000) / 100;
+}
+
+/**
+ * Total y número de unidades recalculados desde las líneas. Cada línea se
+ * redondea por separado, como al guardar el pedido.
+ */
+export function orderTotals(items: OrderItem[]): { total: number; itemCount: number } {
+  return {
+    total: roundMoney(items.reduce((sum, item) => sum + roundMoney(item.price * item.quantity), 0)),
+    itemCount: items.reduce((sum, item) => sum + item.quantity, 0),
+  };
+}
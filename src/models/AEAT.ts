@@ -53,6 +53,26 @@ export interface AEATBusinessData {
   descripcionOperacion: string;
 }
 
+/**
+ * Perfil fiscal del negocio tal y como se guarda en la base de datos
+ * (comando `get_business_profile` / `save_business_profile`)
+ */
+export interface BusinessProfile {
+  nif: string;
+  nombreRazon: string;
+  nombreComercial?: string | null;
+  direccion: string;
+  codigoPostal: string;
+  poblacion: string;
+  provincia?: string | null;
+  telefono?: string | null;
+  email?: string | null;
+  serieFactura: string;
+  tipoFactura: AEATBusinessData['tipoFactura'];
+  descripcionOperacion: string;
+  updatedAt?: string | null;
+}
+
 /**
  * Configuración completa de AEAT
  */
@@ -0,0 +1,28 @@
+/**
+ * Opción de un grupo de variantes o modificadores
+ */
+export interface ModifierOption {
+  id: number;
+  name: string;
+  /** Suplemento sobre el precio unitario, negativo si abarata */
+  priceDelta: number;
+  /** Producto de stock que consume cada unidad vendida */
+  productId?: number;
+  quantity: number;
+}
+
+/**
+ * Elecciones que se ofrecen al vender un producto: un grupo de variantes
+ * (caña, tercio, jarra) o de modificadores (extras, punto de la carne)
+ */
+export default interface ModifierGroup {
+  id: number;
+  name: string;
+  /** Se elige exactamente una opción */
+  variant: boolean;
+  required: boolean;
+  minSelect: number;
+  /** Sin límite si falta */
+  maxSelect?: number;
+  options: ModifierOption[];
+}
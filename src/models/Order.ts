@@ -1,3 +1,5 @@
+import type { SaleUnit } from '@/models/Product';
+
 /**
  * Estados posibles de una mesa/orden en el sistema TPV
  */
@@ -10,12 +12,57 @@ export enum TableStatus {
   ACTIVE = 'active',
 }
 
+/**
+ * Variante o modificador elegido en una línea (copiado del grupo al vender)
+ */
+export interface OrderItemModifier {
+  groupId?: number;
+  optionId?: number;
+  name: string;
+  /** Suplemento sobre el precio unitario, ya incluido en `price` */
+  priceDelta: number;
+  productId?: number;
+  quantity: number;
+  variant: boolean;
+}
+
+/**
+ * Producto servido dentro de un menú (combo)
+ */
+export interface BundleComponent {
+  slotId?: number;
+  slotName: string;
+  productId: number;
+  name: string;
+  category?: string;
+  /** Precio del producto por separado */
+  listPrice: number;
+  /** Parte del precio del menú que le corresponde, para el desglose de impuestos */
+  allocatedPrice: number;
+  /** IVA del producto; el general de la tienda si falta */
+  taxRate?: number;
+}
+
 export interface OrderItem {
+  /** Unidades, o kilos/litros en productos a peso o a granel */
   quantity: number;
+  /** Unidad de `quantity`; `price` es por esa unidad */
+  unit?: SaleUnit;
   id: number;
   name: string;
   price: number;
   category: string;
+  /** Coste unitario en el momento de la venta (lo fija el backend) */
+  costPrice?: number;
+  modifiers?: OrderItemModifier[];
+  /** Productos elegidos cuando la línea es un menú */
+  components?: BundleComponent[];
+  /** Identificador estable de la línea (lo asigna el backend si falta) */
+  lineId?: string;
+  /** Nota libre para cocina ("poco hecho"); una cadena vacía la borra */
+  notes?: string;
+  /** IVA del producto al guardar la línea; el general de la tienda si falta */
+  taxRate?: number;
 }
 
 /**
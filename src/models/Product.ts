@@ -1,5 +1,8 @@
 import type { Component, JSX } from 'solid-js';
 
+/** Unidad de venta: `price` es por unidad, por kilo o por litro */
+export type SaleUnit = 'unit' | 'kg' | 'l';
+
 export default interface Product {
   id: number;
   name: string;
@@ -11,4 +14,31 @@ export default interface Product {
   selectedIcon: string;
   uploadedImage: string | null;
   stock?: number;
+  /** Código principal, el que se imprime en las etiquetas */
+  barcode?: string;
+  /** Otros códigos con los que se vende; ningún código se repite entre productos */
+  barcodes?: string[];
+  allergens?: string[];
+  netQuantity?: number;
+  netUnit?: 'kg' | 'g' | 'l' | 'cl' | 'ml';
+  minStock?: number;
+  reorderQuantity?: number;
+  supplierId?: number;
+  ingredient?: boolean;
+  costPrice?: number;
+  saleUnit?: SaleUnit;
+  /** IVA incluido en el precio cuando no es el general de la tienda */
+  taxRate?: number;
+}
+
+/**
+ * Resultado de escanear un código: cantidad y precio unitario de la línea
+ * (tomados de la etiqueta en los códigos de balanza que empiezan por 2)
+ */
+export interface BarcodeMatch {
+  product: Product;
+  code: string;
+  quantity: number;
+  price: number;
+  embedded: boolean;
 }
@@ -4,9 +4,11 @@
  * Servicio para construir y validar facturas AEAT VERI*FACTU
  */
 
+import { invoke } from '@tauri-apps/api/core';
 import type {
   AEATBusinessData,
   AEATCabecera,
+  BusinessProfile,
   Desglose,
   IDFactura,
   RegistrarFacturaRequest,
@@ -14,10 +16,12 @@ import type {
 } from '@/models/AEAT';
 import type Order from '@/models/Order';
 import type { TaxBreakdownItem } from '@/models/Order';
+import { isTauri } from '@/services/platform';
 
 // ==================== Constants ====================
 
 const INVOICE_COUNTER_KEY = 'tpv-invoice-counter';
+const AEAT_CONFIG_KEY = 'tpv-aeat-config';
 const DEFAULT_TAX_RATE = 21; // IVA general en España
 
 // ==================== Types ====================
@@ -38,6 +42,94 @@ export interface BusinessDataValidationResult {
   errors: string[];
 }
 
+// ==================== Issuer Data ====================
+
+/**
+ * Lee el perfil fiscal guardado en la base de datos (solo escritorio)
+ */
+export async function getBusinessProfile(): Promise<BusinessProfile | null> {
+  if (!isTauri()) {
+    return null;
+  }
+  return invoke<BusinessProfile | null>('get_business_profile');
+}
+
+/**
+ * Campos del perfil fiscal que usa la facturación AEAT
+ */
+export function businessDataFromProfile(profile: BusinessProfile): AEATBusinessData {
+  return {
+    nif: profile.nif,
+    nombreRazon: profile.nombreRazon,
+    serieFactura: profile.serieFactura,
+    tipoFactura: profile.tipoFactura,
+    descripcionOperacion: profile.descripcionOperacion,
+  };
+}
+
+/**
+ * Guarda los datos fiscales en el perfil de la base de datos, conservando los
+ * campos que no se editan desde los ajustes de AEAT (dirección, teléfono...).
+ * El backend valida el NIF y devuelve el error si no es correcto.
+ */
+export async function saveBusinessData(
+  businessData: AEATBusinessData,
+  current?: BusinessProfile | null
+): Promise<void> {
+  const profile: BusinessProfile = {
+    direccion: '',
+    codigoPostal: '',
+    poblacion: '',
+    ...current,
+    ...businessData,
+  };
+  await invoke('save_business_profile', { profile });
+}
+
+/**
+ * Datos del emisor para facturar. En escritorio salen del perfil fiscal de la
+ * base de datos; en navegador, de la configuración AEAT local.
+ */
+export async function loadBusinessData(
+  fallback: AEATBusinessData
+): Promise<AEATBusinessData | null> {
+  if (!isTauri()) {
+    return fallback;
+  }
+
+  const profile = await getBusinessProfile();
+  return profile ? businessDataFromProfile(profile) : null;
+}
+
+/**
+ * Pasa una sola vez los datos fiscales que antes vivían en la configuración
+ * AEAT de localStorage al perfil de la base de datos. Si la base de datos ya
+ * tiene perfil, gana el de la base de datos; en ambos casos se borran de la
+ * configuración local para no volver a migrarlos.
+ */
+export async function migrateLegacyBusinessData(): Promise<void> {
+  if (!isTauri()) {
+    return;
+  }
+
+  const saved = localStorage.getItem(AEAT_CONFIG_KEY);
+  if (!saved) {
+    return;
+  }
+
+  const { businessData: legacy, ...rest } = JSON.parse(saved) as {
+    businessData?: AEATBusinessData;
+  };
+  if (!legacy) {
+    return;
+  }
+
+  if (legacy.nif && legacy.nombreRazon && !(await getBusinessProfile())) {
+    await saveBusinessData(legacy);
+  }
+  localStorage.setItem(AEAT_CONFIG_KEY, JSON.stringify(rest));
+}
+
 // ==================== Invoice Number Generation ====================
 
 /**
@@ -103,6 +195,32 @@ export function generateInvoiceNumber(serie: string): string {
   return `${serie}${currentYear}-${paddedNumber}`;
 }
 
+/**
+ * Reserva el siguiente número de factura. En escritorio el contador vive en la
+ * base de datos; se le pasa el último número emitido por el contador local para
+ * que la serie continúe donde estaba y no se dupliquen números.
+ */
+export async function reserveInvoiceNumber(serie: string): Promise<string> {
+  if (!isTauri()) {
+    return generateInvoiceNumber(serie);
+  }
+
+  const counter = getInvoiceCounter(serie);
+  const invoiceNumber = await invoke<string>('next_invoice_number', {
+    consume: true,
+    serie,
+    issued: counter.lastNumber,
+  });
+
+  // Mantener el contador local al día por si se vuelve al modo navegador
+  const lastNumber = Number.parseInt(invoiceNumber.slice(invoiceNumber.lastIndexOf('-') + 1), 10);
+  if (Number.isFinite(lastNumber)) {
+    saveInvoiceCounter({ serie, year: new Date().getFullYear(), lastNumber });
+  }
+
+  return invoiceNumber;
+}
+
 /**
  * Obtiene el siguiente número de factura sin incrementar el contador
  * (útil para previsualización)
@@ -163,6 +281,34 @@ export function calculateMultipleTaxBreakdown(
   return Array.from(breakdownMap.values());
 }
 
+/**
+ * Importe del pedido por tipo de IVA. Los menús se reparten entre sus
+ * productos según la parte del precio que le toca a cada uno.
+ */
+export function taxableAmounts(
+  order: Order,
+  taxRate: number = DEFAULT_TAX_RATE
+): Array<{ total: number; taxRate: number }> {
+  const totals = new Map<number, number>();
+  const add = (rate: number, amount: number) =>
+    totals.set(rate, (totals.get(rate) ?? 0) + Math.round(amount * 100) / 100);
+
+  for (const item of order.items) {
+    if (item.components?.length) {
+      for (const component of item.components) {
+        add(component.taxRate ?? item.taxRate ?? taxRate, component.allocatedPrice * item.quantity);
+      }
+    } else {
+      add(item.taxRate ?? taxRate, item.price * item.quantity);
+    }
+  }
+
+  return Array.from(totals, ([rate, total]) => ({
+    total: Math.round(total * 100) / 100,
+    taxRate: rate,
+  })).sort((a, b) => a.taxRate - b.taxRate);
+}
+
 // ==================== Validation ====================
 
 /**
@@ -306,13 +452,11 @@ function buildRegistroFactura(
 export function buildInvoiceRequest(
   order: Order,
   businessData: AEATBusinessData,
+  invoiceNumber: string,
   taxRate: number = DEFAULT_TAX_RATE
 ): { request: RegistrarFacturaRequest; invoiceNumber: string; taxBreakdown: TaxBreakdownItem[] } {
-  // Generar número de factura
-  const invoiceNumber = generateInvoiceNumber(businessData.serieFactura);
-
   // Calcular desglose de impuestos
-  const taxBreakdown = [calculateTaxBreakdown(order.total, taxRate)];
+  const taxBreakdown = calculateMultipleTaxBreakdown(taxableAmounts(order, taxRate));
 
   // Construir petición
   const request: RegistrarFacturaRequest = {
@@ -330,10 +474,17 @@ export function buildInvoiceRequest(
 // ==================== Export Service Object ====================
 
 export const invoiceBuilderService = {
+  getBusinessProfile,
+  businessDataFromProfile,
+  saveBusinessData,
+  loadBusinessData,
+  migrateLegacyBusinessData,
   generateInvoiceNumber,
+  reserveInvoiceNumber,
   peekNextInvoiceNumber,
   calculateTaxBreakdown,
   calculateMultipleTaxBreakdown,
+  taxableAmounts,
   validateOrder,
   validateBusinessData,
   buildInvoiceRequest,
@@ -0,0 +1,39 @@
+import { invoke } from '@tauri-apps/api/core';
+import type ModifierGroup from '@/models/Modifier';
+import type { OrderItem } from '@/models/Order';
+import { isTauri } from '@/services/platform';
+
+/**
+ * Grupos de variantes y modificadores de un producto. Fuera de Tauri no hay
+ * grupos y los productos se venden tal cual.
+ */
+export async function getProductModifierGroups(productId: number): Promise<ModifierGroup[]> {
+  if (!isTauri()) {
+    return [];
+  }
+  return invoke<ModifierGroup[]>('get_product_modifier_groups', { productId });
+}
+
+/**
+ * Línea de pedido con las opciones elegidas. El backend comprueba los
+ * límites de cada grupo y calcula el nombre y el precio unitario.
+ */
+export async function buildOrderItem(
+  productId: number,
+  optionIds: number[],
+  quantity?: number
+): Promise<OrderItem> {
+  return invoke<OrderItem>('build_order_item', { productId, optionIds, quantity });
+}
+
+/**
+ * Mínimo y máximo de opciones que admite un grupo, igual que los comprueba
+ * el backend: las variantes llevan exactamente una y los obligatorios al menos una
+ */
+export function selectionLimits(group: ModifierGroup): { min: number; max?: number } {
+  if (group.variant) {
+    return { min: 1, max: 1 };
+  }
+  const min = group.required ? Math.max(group.minSelect, 1) : group.minSelect;
+  return { min, max: group.maxSelect };
+}
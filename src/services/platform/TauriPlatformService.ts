@@ -30,7 +30,7 @@ export class TauriPlatformService implements PlatformService {
     lines.push('[BOLD:ON]TPV EL HAIDO[BOLD:OFF]');
     lines.push(`Ticket #${order.id}`);
     lines.push(`Mesa: ${order.tableNumber || 'Barra'}`);
-    lines.push(`Fecha: ${order.date}`);
+    lines.push(`Fecha: ${new Date(order.date).toLocaleString('es-ES')}`);
     lines.push('[ALIGN:LEFT]');
     lines.push('--------------------------------');
 
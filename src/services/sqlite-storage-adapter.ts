@@ -2,12 +2,23 @@ import { err, ok, tryCatchAsync } from '@mks2508/no-throw';
 import { invoke } from '@tauri-apps/api/core';
 import { StorageErrorCode } from '@/lib/error-codes';
 import type Category from '@/models/Category';
+import type Customer from '@/models/Customer';
 import type Order from '@/models/Order';
 import type Product from '@/models/Product';
 import type Table from '@/models/Table';
 import type User from '@/models/User';
+import useStore from '@/store/store';
 import type { IStorageAdapter, StorageResult } from './storage-adapter.interface';
 
+/**
+ * Who is saving the order and whether a cash payment should open the drawer.
+ * The backend opens it once, after the order is saved, and logs the user.
+ */
+function cashDrawerContext(): { userId?: number; openDrawer: boolean } {
+  const { state } = useStore();
+  return { userId: state.selectedUser?.id, openDrawer: state.autoOpenCashDrawer };
+}
+
 /**
  * SQLite storage adapter that uses Tauri commands to interact with
  * the embedded SQLite database in the Rust backend.
@@ -99,7 +110,7 @@ export class SqliteStorageAdapter implements IStorageAdapter {
 
   async createOrder(order: Order): Promise<StorageResult<void>> {
     const result = await tryCatchAsync(
-      async () => invoke('create_order', { order }),
+      async () => invoke('create_order', { order, ...cashDrawerContext() }),
       StorageErrorCode.WriteFailed
     );
     return result.ok
@@ -109,7 +120,7 @@ export class SqliteStorageAdapter implements IStorageAdapter {
 
   async updateOrder(order: Order): Promise<StorageResult<void>> {
     const result = await tryCatchAsync(
-      async () => invoke('update_order', { order }),
+      async () => invoke('update_order', { order, ...cashDrawerContext() }),
       StorageErrorCode.WriteFailed
     );
     return result.ok
@@ -127,6 +138,45 @@ export class SqliteStorageAdapter implements IStorageAdapter {
       : err({ code: StorageErrorCode.DeleteFailed, message: result.error.message });
   }
 
+  // ==================== Customers ====================
+
+  async getCustomers(): Promise<StorageResult<Customer[]>> {
+    return tryCatchAsync(
+      async () => invoke<Customer[]>('get_customers'),
+      StorageErrorCode.ReadFailed
+    );
+  }
+
+  async createCustomer(customer: Customer): Promise<StorageResult<void>> {
+    const result = await tryCatchAsync(
+      async () => invoke('create_customer', { customer }),
+      StorageErrorCode.WriteFailed
+    );
+    return result.ok
+      ? ok(undefined)
+      : err({ code: StorageErrorCode.WriteFailed, message: result.error.message });
+  }
+
+  async updateCustomer(customer: Customer): Promise<StorageResult<void>> {
+    const result = await tryCatchAsync(
+      async () => invoke('update_customer', { customer }),
+      StorageErrorCode.WriteFailed
+    );
+    return result.ok
+      ? ok(undefined)
+      : err({ code: StorageErrorCode.WriteFailed, message: result.error.message });
+  }
+
+  async deleteCustomer(customer: Customer): Promise<StorageResult<void>> {
+    const result = await tryCatchAsync(
+      async () => invoke('delete_customer', { id: customer.id }),
+      StorageErrorCode.DeleteFailed
+    );
+    return result.ok
+      ? ok(undefined)
+      : err({ code: StorageErrorCode.DeleteFailed, message: result.error.message });
+  }
+
   // ==================== Tables ====================
 
   async getTables(): Promise<StorageResult<Table[]>> {
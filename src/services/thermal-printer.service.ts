@@ -33,12 +33,12 @@ async function runThermalPrinterCommand(printSequence: string): Promise<string>
     // Parse print sequence to handle special commands
     const commands = printSequence.split('\n');
     let processedSequence = '';
-    let hasCashDrawerCommand = false;
 
     for (const command of commands) {
+      // The drawer is opened by the backend when a cash order is saved
+      // (see open_cash_drawer), never from a print sequence.
       if (command === '[CASH_DRAWER]') {
-        hasCashDrawerCommand = true;
-        console.log('Cash drawer command detected in print sequence');
+        continue;
       } else if (command.startsWith('[IMAGE:')) {
         // Handle image printing
         const imagePath = command.replace('[IMAGE:', '').replace(']', '');
@@ -54,12 +54,6 @@ async function runThermalPrinterCommand(printSequence: string): Promise<string>
 
     const cliArgs = ['--printerConfig', configPath, '--printSequence', processedSequence.trim()];
 
-    // Add cash drawer flag if needed
-    if (hasCashDrawerCommand) {
-      cliArgs.push('--openCashDrawer');
-      console.log('Adding --openCashDrawer flag to CLI command');
-    }
-
     console.log('Executing thermal printer command with args:', cliArgs);
     const command = Command.sidecar('binaries/thermal-printer-cli', cliArgs);
 
@@ -86,53 +80,83 @@ async function runThermalPrinterCommand(printSequence: string): Promise<string>
   }
 }
 
-export async function openCashDrawerOnly(): Promise<string> {
+export async function openCashDrawerOnly(userId?: number, reason?: string): Promise<void> {
   try {
-    const appDir = await appDataDir();
-    const configPath = `${appDir}printerSettings.json`;
-
     console.log('Attempting to open cash drawer only...');
-    const command = Command.sidecar('binaries/thermal-printer-cli', [
-      '--printerConfig',
-      configPath,
-      '--openCashDrawerOnly',
-    ]);
-
-    const output = await command.execute();
-
-    if (output.code !== 0) {
-      console.error('Cash drawer open command failed:', {
-        code: output.code,
-        stderr: output.stderr,
-        stdout: output.stdout,
-      });
-      throw new Error(`Cash drawer command failed with code ${output.code}: ${output.stderr}`);
-    }
-
+    await invoke('open_cash_drawer', { userId, reason });
     console.log('Cash drawer opened successfully');
-    return output.stdout;
   } catch (error) {
     console.error('Error opening cash drawer:', {
-      error: (error as Error).message,
-      stack: (error as Error).stack,
+      error: error instanceof Error ? error.message : error,
     });
     throw error;
   }
 }
 
-export async function writeJsonConfig(config: ThermalPrinterServiceOptions): Promise<void> {
+export type PrinterRole = 'ticket' | 'kitchen' | 'bar' | 'labels';
+
+export type LabelLanguage = 'zpl' | 'escpos';
+
+export interface LabelSettings {
+  language: LabelLanguage;
+  widthMm?: number;
+  heightMm?: number;
+  dpi?: number;
+}
+
+export interface PrinterConfig extends ThermalPrinterServiceOptions {
+  name: string;
+  role: PrinterRole;
+  enabled?: boolean;
+  paperWidth?: number;
+  logoPath?: string;
+  label?: LabelSettings;
+}
+
+export interface LabelRequest {
+  productIds?: number[];
+  category?: string;
+  copies?: number;
+  printerName?: string;
+  outputPath?: string;
+  language?: LabelLanguage;
+}
+
+export interface LabelResult {
+  count: number;
+  path?: string;
+}
+
+export async function printLabels(request: LabelRequest): Promise<LabelResult> {
+  return invoke<LabelResult>('print_labels', { request });
+}
+
+export async function getPrinterConfigs(): Promise<PrinterConfig[]> {
+  return invoke<PrinterConfig[]>('get_printer_configs');
+}
+
+export async function savePrinterConfig(config: PrinterConfig): Promise<PrinterConfig[]> {
   try {
-    console.log('Saving thermal printer configuration:', config);
-    await invoke('write_json_config', { config });
+    console.log('Saving printer configuration:', config);
+    const configs = await invoke<PrinterConfig[]>('save_printer_config', { config });
     console.log('Configuración guardada exitosamente');
+    return configs;
   } catch (error) {
     console.error('Error al guardar la configuración:', {
-      error: (error as Error).message,
-      stack: (error as Error).stack,
+      error: error instanceof Error ? error.message : error,
       config: config,
     });
     throw error;
   }
 }
 
+export async function deletePrinterConfig(name: string): Promise<PrinterConfig[]> {
+  return invoke<PrinterConfig[]>('delete_printer_config', { name });
+}
+
+/** Saves the options as the receipt printer. */
+export async function writeJsonConfig(config: ThermalPrinterServiceOptions): Promise<void> {
+  await savePrinterConfig({ name: 'Ticket', role: 'ticket', enabled: true, ...config });
+}
+
 export { runThermalPrinterCommand };
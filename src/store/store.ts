@@ -1,6 +1,7 @@
 import { batch, createRoot, createSignal } from 'solid-js';
 import { createStore, produce } from 'solid-js/store';
 import { config } from '@/lib/config';
+import { isSameLine, newLineId, orderTotals } from '@/lib/order-lines';
 import type Category from '@/models/Category';
 import type Customer from '@/models/Customer';
 import type Order from '@/models/Order';
@@ -400,7 +401,7 @@ function createAppStore() {
           paymentMethod: 'efectivo',
           items: [] as OrderItem[],
           total: 0,
-          date: new Date().toISOString().split('T')[0],
+          date: new Date().toISOString(),
           itemCount: 0,
           totalPaid: 0,
           change: 0,
@@ -471,7 +472,9 @@ function createAppStore() {
         const orderIndex = s.activeOrders.findIndex((order) => order.id === orderId);
         if (orderIndex !== -1) {
           const order = s.activeOrders[orderIndex];
-          const existingItemIndex = order.items.findIndex((orderItem) => orderItem.id === item.id);
+          const existingItemIndex = order.items.findIndex((orderItem) =>
+            isSameLine(orderItem, item)
+          );
           if (existingItemIndex !== -1) {
             order.items[existingItemIndex].quantity += 1;
           } else {
@@ -481,10 +484,15 @@ function createAppStore() {
               id: item.id,
               quantity: 'quantity' in item ? item.quantity : 1,
               category: item.category,
+              unit: 'unit' in item ? item.unit : undefined,
+              modifiers: 'modifiers' in item ? item.modifiers : undefined,
+              components: 'components' in item ? item.components : undefined,
+              lineId: ('lineId' in item && item.lineId) || newLineId(),
+              notes: 'notes' in item ? item.notes : undefined,
+              taxRate: item.taxRate,
             });
           }
-          order.itemCount += 1;
-          order.total += item.price;
+          Object.assign(order, orderTotals(order.items));
         }
       })
     );
@@ -495,15 +503,15 @@ function createAppStore() {
     }
   };
 
-  const removeFromOrder = async (orderId: number, productId: number) => {
-    console.log(`[removeFromOrder] Removing product ${productId} from order ${orderId}`);
+  const removeFromOrder = async (orderId: number, line: OrderItem) => {
+    console.log(`[removeFromOrder] Removing product ${line.id} from order ${orderId}`);
 
     setState(
       produce((s) => {
         const orderIndex = s.activeOrders.findIndex((order) => order.id === orderId);
         if (orderIndex !== -1) {
           const order = s.activeOrders[orderIndex];
-          const existingItemIndex = order.items.findIndex((item) => item.id === productId);
+          const existingItemIndex = order.items.findIndex((item) => isSameLine(item, line));
 
           if (existingItemIndex !== -1) {
             const item = order.items[existingItemIndex];
@@ -522,11 +530,7 @@ function createAppStore() {
             }
 
             // Recalculate totals from scratch
-            order.total = order.items.reduce(
-              (sum, orderItem) => sum + orderItem.price * orderItem.quantity,
-              0
-            );
-            order.itemCount = order.items.reduce((sum, orderItem) => sum + orderItem.quantity, 0);
+            Object.assign(order, orderTotals(order.items));
 
             console.log(
               `[removeFromOrder] New totals: total=${order.total}, itemCount=${order.itemCount}`
@@ -547,6 +551,31 @@ function createAppStore() {
     }
   };
 
+  /**
+   * Cambia la nota para cocina de una línea; una nota vacía la borra. Si la
+   * línea ya se envió, el backend manda el cambio a su estación.
+   */
+  const setLineNotes = async (orderId: number, line: OrderItem, notes: string) => {
+    setState(
+      produce((s) => {
+        const order = s.activeOrders.find((o) => o.id === orderId);
+        const item = order?.items.find((orderItem) => isSameLine(orderItem, line));
+        if (item) {
+          item.notes = notes;
+        }
+      })
+    );
+
+    const updatedOrder = state.activeOrders.find((order) => order.id === orderId);
+    if (updatedOrder) {
+      try {
+        await storageAdapter().updateOrder(updatedOrder);
+      } catch (error) {
+        console.error('[setLineNotes] Error updating order:', error);
+      }
+    }
+  };
+
   return {
     // State (reactive)
     state,
@@ -586,6 +615,7 @@ function createAppStore() {
     closeOrder,
     addToOrder,
     removeFromOrder,
+    setLineNotes,
   };
 }
 